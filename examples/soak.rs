@@ -0,0 +1,158 @@
+//! Soak test: many instances of one behaviour script, ticked together.
+//!
+//! Parses a typical behaviour script once, gives each of N instances its own
+//! interpreter with stubbed host functions, and runs every instance once per
+//! tick for T ticks, feeding fresh host state through globals before each
+//! run. Reports time per tick, allocations per tick and peak heap use.
+//!
+//! ```text
+//! cargo run --release --example soak -- [instances] [ticks]
+//! ```
+//!
+//! Defaults to 1000 instances and 10000 ticks. Scripts run in draft mode,
+//! which is what there is to run them with so far.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use shallows_vm::draft::{Interpreter, Value};
+use shallows_vm::{Lines, parse};
+
+const SCRIPT: &str = r#"
+// Pick a threat level from what the host tells us, then act on it.
+let threat = if hp < 30 {
+    yield 2;
+} elif enemy_dist < 5.0 {
+    yield 1;
+} else {
+    yield 0;
+};
+if threat == 2 {
+    flee(enemy_dist);
+} elif threat == 1 {
+    attack(target);
+} else {
+    let dx, dy = wander(tick);
+    move(dx * speed, dy * speed);
+}
+return threat;
+"#;
+
+/// Counts allocations and tracks the heap high-water mark.
+struct Counting;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        let live = LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(live, Ordering::Relaxed);
+        // SAFETY: forwarded unchanged to the system allocator.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        // SAFETY: `ptr` came from `alloc` above with the same layout.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Host calls made by the scripts, so the stubs are not optimised away.
+#[derive(Default)]
+struct HostCalls {
+    flee: Cell<u64>,
+    attack: Cell<u64>,
+    moves: Cell<u64>,
+}
+
+fn instance(calls: &Rc<HostCalls>, id: usize) -> Interpreter {
+    let (flee, attack, moves) = (calls.clone(), calls.clone(), calls.clone());
+    let mut interpreter = Interpreter::new()
+        .with_function("flee", move |_| {
+            flee.flee.set(flee.flee.get() + 1);
+            Ok(vec![])
+        })
+        .with_function("attack", move |_| {
+            attack.attack.set(attack.attack.get() + 1);
+            Ok(vec![])
+        })
+        .with_function("move", move |_| {
+            moves.moves.set(moves.moves.get() + 1);
+            Ok(vec![])
+        })
+        .with_function("wander", |args| {
+            let Some(Value::Int(tick)) = args.first() else {
+                return Err("wander expects the tick".to_string());
+            };
+            let angle = *tick as f64 * 0.1;
+            Ok(vec![Value::Float(angle.cos()), Value::Float(angle.sin())])
+        });
+    interpreter.set_global("target", Value::Int(id as i64));
+    interpreter.set_global("speed", Value::Float(1.5));
+    interpreter
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1).map(|a| a.parse::<usize>());
+    let instances = args
+        .next()
+        .map_or(1000, |n| n.expect("instances: a number"));
+    let ticks = args.next().map_or(10_000, |n| n.expect("ticks: a number"));
+
+    let program = parse(&Lines::from_string(SCRIPT.to_string()))
+        .into_result()
+        .expect("the soak script parses");
+
+    let calls = Rc::new(HostCalls::default());
+    let mut instances: Vec<Interpreter> = (0..instances).map(|id| instance(&calls, id)).collect();
+
+    let allocs_before = ALLOCS.load(Ordering::Relaxed);
+    let mut total = Duration::ZERO;
+    let mut slowest = Duration::ZERO;
+    for tick in 0..ticks {
+        let start = Instant::now();
+        for (id, interpreter) in instances.iter_mut().enumerate() {
+            // Vary the state so every branch of the script gets exercised.
+            let phase = (tick + id) % 100;
+            interpreter.set_global("hp", Value::Int(phase as i64));
+            interpreter.set_global("enemy_dist", Value::Float(((phase * 7) % 100) as f64 / 4.0));
+            interpreter.set_global("tick", Value::Int(tick as i64));
+            if let Err(trap) = interpreter.run(&program) {
+                panic!("instance {id} trapped at tick {tick}: {trap}");
+            }
+        }
+        let elapsed = start.elapsed();
+        total += elapsed;
+        slowest = slowest.max(elapsed);
+    }
+    let allocs = ALLOCS.load(Ordering::Relaxed) - allocs_before;
+
+    let ticks_f = ticks.max(1) as f64;
+    println!("{} instances x {ticks} ticks", instances.len());
+    println!(
+        "per tick: {:.3} ms mean, {:.3} ms worst",
+        total.as_secs_f64() * 1e3 / ticks_f,
+        slowest.as_secs_f64() * 1e3
+    );
+    println!("allocations per tick: {:.0}", allocs as f64 / ticks_f);
+    println!(
+        "peak heap: {:.1} MiB",
+        PEAK.load(Ordering::Relaxed) as f64 / (1024.0 * 1024.0)
+    );
+    println!(
+        "host calls: {} flee, {} attack, {} move",
+        calls.flee.get(),
+        calls.attack.get(),
+        calls.moves.get()
+    );
+}
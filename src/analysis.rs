@@ -0,0 +1,256 @@
+//! Background compilation for editors.
+//!
+//! An editor submits the current text of a document to its
+//! [`AnalysisHost`] on every change and polls the returned
+//! [`PendingAnalysis`] from its UI loop. Each compile runs on a worker
+//! thread, so the UI never blocks on it, and submitting a new version of the
+//! document cancels the one before: a user typing quickly only pays for the
+//! compile of the text they stop at.
+//!
+//! Cancellation is cooperative. [`analyze`] checks its
+//! [`CancellationToken`] between passes and gives up with [`Cancelled`] at
+//! the first check after the token is cancelled, so a pass that is already
+//! running finishes first.
+//!
+//! [`Plugins`] given to the host run in every compile it starts, and an
+//! [`ErrorSink`] given to it hears about every diagnostic of every compile
+//! that finishes.
+
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use crate::ast1::Program;
+use crate::ast2;
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::lexer::tokenize;
+use crate::line_map::Lines;
+use crate::parser::Parser;
+use crate::pass1;
+use crate::plugin::{Plugins, Stage};
+use crate::resolve;
+use crate::telemetry::{ErrorRecord, ErrorSink};
+use crate::typeck::{self, Types};
+
+/// A flag shared between whoever requests a compile and the compile itself.
+/// Clones share the flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// `Err(Cancelled)` once the token has been cancelled, for use with `?`
+    /// between passes.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The compile was abandoned before it finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("compile cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Everything known about one version of a document.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    pub program: Program,
+    /// `program` with its names resolved. The host's names are not known
+    /// here, so names found nowhere are taken to be the host's.
+    pub resolved: ast2::Program,
+    /// The types in `resolved`, or `None` if the passes before type
+    /// checking found errors.
+    pub types: Option<Types>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Analysis {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+}
+
+/// Runs every pass over `lines`, checking `token` before each one.
+pub fn analyze(lines: &Lines, token: &CancellationToken) -> Result<Analysis, Cancelled> {
+    analyze_with(lines, token, &Plugins::new())
+}
+
+/// Like [`analyze`], with `plugins` run at their stages.
+pub fn analyze_with(
+    lines: &Lines,
+    token: &CancellationToken,
+    plugins: &Plugins,
+) -> Result<Analysis, Cancelled> {
+    token.check()?;
+    let lexed = tokenize(lines);
+    token.check()?;
+    let mut diagnostics = Diagnostics::new();
+    diagnostics.extend(lexed.diagnostics);
+    let mut parser = Parser::new(lexed.tokens).with_diagnostics(diagnostics);
+    let mut program = parser.parse_program();
+    let mut diagnostics = parser.into_diagnostics();
+    token.check()?;
+    plugins.run(Stage::AfterParse, &mut program, &mut diagnostics);
+    token.check()?;
+    let mut diagnostics = diagnostics.into_vec();
+    diagnostics.extend(pass1::check(&program));
+    token.check()?;
+    let mut late = Diagnostics::new();
+    plugins.run(Stage::AfterPass1, &mut program, &mut late);
+    diagnostics.extend(late.into_vec());
+    token.check()?;
+    let (resolved, resolve_diagnostics) = resolve::resolve(&program, None);
+    diagnostics.extend(resolve_diagnostics);
+    token.check()?;
+    let mut late = Diagnostics::new();
+    plugins.check_resolved(&resolved, &mut late);
+    diagnostics.extend(late.into_vec());
+    // The checker expects a well-formed, resolved tree; on anything else it
+    // would mostly repeat what was reported already.
+    let mut types = None;
+    if !diagnostics.iter().any(Diagnostic::is_error) {
+        token.check()?;
+        let (checked, type_diagnostics) = typeck::check(&resolved);
+        diagnostics.extend(type_diagnostics);
+        types = Some(checked);
+    }
+    Ok(Analysis {
+        program,
+        resolved,
+        types,
+        diagnostics,
+    })
+}
+
+/// Compiles successive versions of one document in the background.
+///
+/// Use one host per open document. Dropping the host cancels whatever it is
+/// still compiling.
+#[derive(Default)]
+pub struct AnalysisHost {
+    current: Option<CancellationToken>,
+    plugins: Plugins,
+    error_sink: Option<Arc<dyn ErrorSink>>,
+}
+
+impl AnalysisHost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_plugins(mut self, plugins: Plugins) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    /// Reports the diagnostics of every finished compile to `sink`, under
+    /// the path of the submitted [`Lines`].
+    pub fn with_error_sink(mut self, sink: Arc<dyn ErrorSink>) -> Self {
+        self.error_sink = Some(sink);
+        self
+    }
+
+    /// Starts compiling `lines` on a worker thread, cancelling the previous
+    /// submission if it is still running.
+    pub fn submit(&mut self, lines: Lines) -> PendingAnalysis {
+        self.cancel();
+        let token = CancellationToken::new();
+        self.current = Some(token.clone());
+        let (sender, receiver) = mpsc::channel();
+        let worker = token.clone();
+        let plugins = self.plugins.clone();
+        let error_sink = self.error_sink.clone();
+        thread::spawn(move || {
+            let result = analyze_with(&lines, &worker, &plugins);
+            if let (Some(sink), Ok(analysis)) = (&error_sink, &result) {
+                let script = lines
+                    .path()
+                    .map_or_else(String::new, |path| path.display().to_string());
+                for diagnostic in &analysis.diagnostics {
+                    sink.record(&ErrorRecord::from_diagnostic(&script, diagnostic));
+                }
+            }
+            // The receiver may be gone if the editor lost interest; that is
+            // fine.
+            let _ = sender.send(result);
+        });
+        PendingAnalysis { token, receiver }
+    }
+
+    /// Cancels the latest submission, if any.
+    pub fn cancel(&mut self) {
+        if let Some(token) = self.current.take() {
+            token.cancel();
+        }
+    }
+}
+
+impl fmt::Debug for AnalysisHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnalysisHost")
+            .field("current", &self.current)
+            .field("plugins", &self.plugins)
+            .field("error_sink", &self.error_sink.is_some())
+            .finish()
+    }
+}
+
+impl Drop for AnalysisHost {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// A compile running in the background.
+#[derive(Debug)]
+pub struct PendingAnalysis {
+    token: CancellationToken,
+    receiver: Receiver<Result<Analysis, Cancelled>>,
+}
+
+impl PendingAnalysis {
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// The result if the compile has finished, without blocking. The result
+    /// is handed out once; later calls report [`Cancelled`].
+    pub fn poll(&self) -> Option<Result<Analysis, Cancelled>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            // Handed out already, or the worker panicked: either way no
+            // result is coming.
+            Err(TryRecvError::Disconnected) => Some(Err(Cancelled)),
+        }
+    }
+
+    /// Blocks until the compile finishes.
+    pub fn wait(self) -> Result<Analysis, Cancelled> {
+        self.receiver.recv().unwrap_or(Err(Cancelled))
+    }
+}
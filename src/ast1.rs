@@ -0,0 +1,852 @@
+//! First-stage AST: the program exactly as written.
+//!
+//! Names are kept as plain strings and nothing is resolved or checked beyond
+//! what the grammar requires; later passes lower this into AST2.
+//!
+//! Expressions and statements live in an [`AstArena`] and refer to their
+//! children by [`ExprId`] and [`StmtId`]. Children are always allocated
+//! before their parents, and ids are dense, so passes can keep per-node
+//! results in plain vectors indexed by id.
+//!
+//! Every node's `span` covers it from its first token to its last, e.g. a
+//! `let` including its attributes and `;`, or a binary expression from the
+//! start of its left operand to the end of its right one.
+//!
+//! With the `serde` feature every type here can be serialized, e.g. to dump
+//! parsed programs as JSON for external tools or snapshot tests.
+
+use std::ops::{Index, IndexMut};
+
+use crate::span::Span;
+use crate::token::NumSuffix;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Program {
+    pub arena: AstArena,
+    pub stmts: Vec<StmtId>,
+}
+
+/// Identifies an [`Expr`] in an [`AstArena`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExprId(pub(crate) u32);
+
+impl ExprId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Identifies a [`Stmt`] in an [`AstArena`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StmtId(pub(crate) u32);
+
+impl StmtId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Storage for the expressions and statements of one program.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AstArena {
+    exprs: Vec<Expr>,
+    stmts: Vec<Stmt>,
+}
+
+impl AstArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn alloc_expr(&mut self, expr: Expr) -> ExprId {
+        let id = ExprId(self.exprs.len() as u32);
+        self.exprs.push(expr);
+        id
+    }
+
+    pub fn alloc_stmt(&mut self, stmt: Stmt) -> StmtId {
+        let id = StmtId(self.stmts.len() as u32);
+        self.stmts.push(stmt);
+        id
+    }
+
+    /// Number of expressions, i.e. the length a side table indexed by
+    /// [`ExprId`] needs.
+    pub fn expr_count(&self) -> usize {
+        self.exprs.len()
+    }
+
+    pub fn stmt_count(&self) -> usize {
+        self.stmts.len()
+    }
+
+    pub fn exprs(&self) -> impl ExactSizeIterator<Item = (ExprId, &Expr)> {
+        self.exprs
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (ExprId(i as u32), e))
+    }
+
+    pub fn stmts(&self) -> impl ExactSizeIterator<Item = (StmtId, &Stmt)> {
+        self.stmts
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (StmtId(i as u32), s))
+    }
+
+    /// A checkpoint for [`truncate`](Self::truncate).
+    pub(crate) fn mark(&self) -> (usize, usize) {
+        (self.exprs.len(), self.stmts.len())
+    }
+
+    /// Drops everything allocated since `mark`, e.g. the pieces of a
+    /// statement that failed to parse.
+    pub(crate) fn truncate(&mut self, mark: (usize, usize)) {
+        self.exprs.truncate(mark.0);
+        self.stmts.truncate(mark.1);
+    }
+}
+
+impl Index<ExprId> for AstArena {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.index()]
+    }
+}
+
+impl IndexMut<ExprId> for AstArena {
+    fn index_mut(&mut self, id: ExprId) -> &mut Expr {
+        &mut self.exprs[id.index()]
+    }
+}
+
+impl Index<StmtId> for AstArena {
+    type Output = Stmt;
+
+    fn index(&self, id: StmtId) -> &Stmt {
+        &self.stmts[id.index()]
+    }
+}
+
+impl IndexMut<StmtId> for AstArena {
+    fn index_mut(&mut self, id: StmtId) -> &mut Stmt {
+        &mut self.stmts[id.index()]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+/// `{ stmt* }`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Block {
+    pub stmts: Vec<StmtId>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stmt {
+    Let(LetStmt),
+    Const(ConstDecl),
+    Fn(FnDecl),
+    Struct(StructDecl),
+    Enum(EnumDecl),
+    Assign(AssignStmt),
+    Return(ReturnStmt),
+    Yield(YieldStmt),
+    If(IfStmt),
+    While(WhileStmt),
+    For(ForStmt),
+    Expr(ExprStmt),
+    /// A statement that failed to parse. Its diagnostic has been reported.
+    Error(ErrorNode),
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Let(s) => s.span,
+            Stmt::Const(s) => s.span,
+            Stmt::Fn(s) => s.span,
+            Stmt::Struct(s) => s.span,
+            Stmt::Enum(s) => s.span,
+            Stmt::Assign(s) => s.span,
+            Stmt::Return(s) => s.span,
+            Stmt::Yield(s) => s.span,
+            Stmt::If(s) => s.span,
+            Stmt::While(s) => s.span,
+            Stmt::For(s) => s.span,
+            Stmt::Expr(s) => s.span,
+            Stmt::Error(s) => s.span,
+        }
+    }
+}
+
+/// `#[attr]* let mut? pattern (: type)? = value;`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LetStmt {
+    pub attrs: Vec<Attribute>,
+    /// Whether the names bound can be assigned; `let` alone binds them for
+    /// good.
+    pub mutable: bool,
+    pub pattern: Pattern,
+    pub ty: Option<TypeRef>,
+    pub value: ExprId,
+    pub span: Span,
+}
+
+/// `#[attr]* const NAME (: type)? = value;`
+///
+/// Constants are declared at the top level and initialized with a constant
+/// expression (both checked in [`pass1`](crate::pass1)), which may use the
+/// constants declared before them. They are read like globals, from anywhere
+/// in the script, and cannot be assigned.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConstDecl {
+    pub attrs: Vec<Attribute>,
+    pub name: Ident,
+    pub ty: Option<TypeRef>,
+    pub value: ExprId,
+    pub span: Span,
+}
+
+/// `#[attr]* fn name(param, ...) (-> type)? { body }`
+///
+/// Functions are declared at the top level (checked in
+/// [`pass1`](crate::pass1)) and can be called from anywhere in the program,
+/// including before their declaration. Without a return type the function
+/// returns nothing.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FnDecl {
+    pub attrs: Vec<Attribute>,
+    pub name: Ident,
+    pub params: Vec<Param>,
+    pub ret: Option<TypeRef>,
+    pub body: Block,
+    pub span: Span,
+}
+
+impl FnDecl {
+    /// How many arguments a call must pass: every parameter up to the last
+    /// one without a default.
+    pub fn min_args(&self) -> usize {
+        self.params
+            .iter()
+            .rposition(|p| p.default.is_none())
+            .map_or(0, |i| i + 1)
+    }
+
+    /// How many values the function returns: one per element of a tuple
+    /// return type, none for `()` or without a return type.
+    pub fn return_count(&self) -> usize {
+        match &self.ret {
+            None | Some(TypeRef::Unit(_)) => 0,
+            Some(TypeRef::Tuple(t)) => t.elems.len(),
+            Some(_) => 1,
+        }
+    }
+}
+
+/// `name: type (= default)?`
+///
+/// A default is a constant expression, evaluated for each call that leaves
+/// the parameter out. Parameters with defaults come last (checked in
+/// [`pass1`](crate::pass1)).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Param {
+    pub name: Ident,
+    pub ty: TypeRef,
+    pub default: Option<ExprId>,
+    pub span: Span,
+}
+
+/// `#[attr]* struct Name { field: type, ... }`
+///
+/// Like functions, structs are declared at the top level and can be used
+/// anywhere in the program.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructDecl {
+    pub attrs: Vec<Attribute>,
+    pub name: Ident,
+    pub fields: Vec<FieldDecl>,
+    pub span: Span,
+}
+
+/// `name: type` in a struct declaration.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldDecl {
+    pub name: Ident,
+    pub ty: TypeRef,
+    pub span: Span,
+}
+
+/// `#[attr]* enum Name { Variant (= INT)?, ... }`
+///
+/// Each variant is backed by an `int`: the value written after it, or one
+/// more than the previous variant's, starting from 0. Enums are declared at
+/// the top level like structs.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnumDecl {
+    pub attrs: Vec<Attribute>,
+    pub name: Ident,
+    pub variants: Vec<Variant>,
+    pub span: Span,
+}
+
+impl EnumDecl {
+    /// The backing value of each variant, in order. `None` where the value
+    /// does not fit in an `int`, and for every variant counting on from one
+    /// that does not.
+    pub fn backing_values(&self) -> Vec<Option<i64>> {
+        let mut next = Some(0i64);
+        self.variants
+            .iter()
+            .map(|variant| {
+                let value = match &variant.value {
+                    Some(lit) => lit.raw.parse().ok(),
+                    None => next,
+                };
+                next = value.and_then(|v| v.checked_add(1));
+                value
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Variant {
+    pub name: Ident,
+    /// The backing value, if written.
+    pub value: Option<IntLit>,
+    pub span: Span,
+}
+
+/// `#[name]` or `#[name(arg, ...)]` in front of a declaration.
+///
+/// The parser accepts any name and arguments; which attributes exist and
+/// what they take is checked in [`pass1`](crate::pass1).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Attribute {
+    pub name: Ident,
+    pub args: Vec<ExprId>,
+    pub span: Span,
+}
+
+/// `target = value;`, or `target[i].field = value;` to replace part of it.
+///
+/// Only `let mut` locals can be assigned (checked in
+/// [`pass1`](crate::pass1)); constants and the host's globals are
+/// read-only (checked in [`resolve`](crate::resolve)).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AssignStmt {
+    pub target: Ident,
+    /// The indexing and field accesses after the target, outermost first;
+    /// empty when the whole variable is assigned.
+    pub path: Vec<Projection>,
+    pub value: ExprId,
+    pub span: Span,
+}
+
+/// One step from an assignment's target towards the part assigned.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Projection {
+    /// `[index]`
+    Index(ExprId),
+    /// `.field`
+    Field(Ident),
+}
+
+/// `return (value (, value)*)?;`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReturnStmt {
+    pub values: Vec<ExprId>,
+    pub span: Span,
+}
+
+/// `yield (value (, value)*)?;` gives the enclosing block expression its
+/// value.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct YieldStmt {
+    pub values: Vec<ExprId>,
+    pub span: Span,
+}
+
+/// `if cond { } (elif cond { })* (else { })?` in statement position. The
+/// blocks are run for their effects.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IfStmt {
+    pub cond: ExprId,
+    pub then_block: Block,
+    pub elifs: Vec<ElifBranch>,
+    pub else_block: Option<Block>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ElifBranch {
+    pub cond: ExprId,
+    pub block: Block,
+    pub span: Span,
+}
+
+/// `while cond { body }`: runs the body for as long as the condition holds.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WhileStmt {
+    pub cond: ExprId,
+    pub body: Block,
+    pub span: Span,
+}
+
+/// `for var in range { body }`: runs the body once for each value of the
+/// range, with `var` bound to it in a scope of its own.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ForStmt {
+    pub var: Ident,
+    /// Always an [`Expr::Range`].
+    pub range: ExprId,
+    pub body: Block,
+    pub span: Span,
+}
+
+/// An expression evaluated for its effects: `expr;`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExprStmt {
+    pub expr: ExprId,
+    pub span: Span,
+}
+
+/// Placeholder for source that could not be parsed, covering the skipped
+/// tokens. Later passes treat it as already reported and stay quiet about
+/// anything that depends on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ErrorNode {
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pattern {
+    Ident(Ident),
+    /// `x, y`: destructures a multi-value into at least two bindings.
+    Tuple(TuplePattern),
+}
+
+impl Pattern {
+    pub fn span(&self) -> Span {
+        match self {
+            Pattern::Ident(ident) => ident.span,
+            Pattern::Tuple(p) => p.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TuplePattern {
+    pub elems: Vec<Pattern>,
+    pub span: Span,
+}
+
+/// A type annotation. Primitive types (`int`, `uint`, `float`, `bool`,
+/// `str`) are ordinary names here and only recognised by the type checker.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TypeRef {
+    Named(Ident),
+    /// `()`, the type of no value. A function returning `()` returns
+    /// nothing, like one without a return type.
+    Unit(Span),
+    /// `(float, int)`, always with at least two elements.
+    Tuple(TupleType),
+    /// `[int]`, an array of any length.
+    Array(ArrayType),
+    /// `{str: float}`
+    Map(MapType),
+    /// `int?`: a value of the inner type, or `none`.
+    Optional(OptionalType),
+}
+
+impl TypeRef {
+    pub fn span(&self) -> Span {
+        match self {
+            TypeRef::Named(ident) => ident.span,
+            TypeRef::Unit(span) => *span,
+            TypeRef::Tuple(t) => t.span,
+            TypeRef::Array(t) => t.span,
+            TypeRef::Map(t) => t.span,
+            TypeRef::Optional(t) => t.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TupleType {
+    pub elems: Vec<TypeRef>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArrayType {
+    pub elem: Box<TypeRef>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapType {
+    pub key: Box<TypeRef>,
+    pub value: Box<TypeRef>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OptionalType {
+    pub inner: Box<TypeRef>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expr {
+    Int(IntLit),
+    Float(FloatLit),
+    Str(StrLit),
+    Bool(BoolLit),
+    None(NoneLit),
+    Ident(Ident),
+    Call(CallExpr),
+    Unary(UnaryExpr),
+    Cast(CastExpr),
+    Binary(BinaryExpr),
+    Paren(ParenExpr),
+    If(IfExpr),
+    Range(RangeExpr),
+    Array(ArrayExpr),
+    Map(MapExpr),
+    Index(IndexExpr),
+    Struct(StructExpr),
+    Field(FieldExpr),
+    Variant(VariantExpr),
+    /// An expression that failed to parse. Its diagnostic has been reported.
+    Error(ErrorNode),
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Int(e) => e.span,
+            Expr::Float(e) => e.span,
+            Expr::Str(e) => e.span,
+            Expr::Bool(e) => e.span,
+            Expr::None(e) => e.span,
+            Expr::Ident(e) => e.span,
+            Expr::Call(e) => e.span,
+            Expr::Unary(e) => e.span,
+            Expr::Cast(e) => e.span,
+            Expr::Binary(e) => e.span,
+            Expr::Paren(e) => e.span,
+            Expr::If(e) => e.span,
+            Expr::Range(e) => e.span,
+            Expr::Array(e) => e.span,
+            Expr::Map(e) => e.span,
+            Expr::Index(e) => e.span,
+            Expr::Struct(e) => e.span,
+            Expr::Field(e) => e.span,
+            Expr::Variant(e) => e.span,
+            Expr::Error(e) => e.span,
+        }
+    }
+}
+
+/// An integer literal. `raw` holds the digits as written; the value is
+/// computed once the literal's type is known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IntLit {
+    pub raw: String,
+    pub suffix: Option<NumSuffix>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FloatLit {
+    pub raw: String,
+    pub suffix: Option<NumSuffix>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StrLit {
+    pub value: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoolLit {
+    pub value: bool,
+    pub span: Span,
+}
+
+/// `none`, the absent value of every optional type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NoneLit {
+    pub span: Span,
+}
+
+/// `callee(arg, ...)`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CallExpr {
+    pub callee: Ident,
+    pub args: Vec<ExprId>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+impl UnaryOp {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "!",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnaryExpr {
+    pub op: UnaryOp,
+    pub operand: ExprId,
+    pub span: Span,
+}
+
+/// `expr as type`, converting between `int`, `uint` and `float`.
+///
+/// Conversions to `float` and between equal types are lossless (beyond
+/// rounding very large integers). `float` to an integer type truncates
+/// towards zero, and every conversion that would land outside the target's
+/// range, or start from NaN or an infinity, is an error rather than a
+/// wrap-around or a saturated value. An enum converts as its backing `int`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CastExpr {
+    pub expr: ExprId,
+    pub ty: TypeRef,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+    /// `a ?? b`: `a` unless it is `none`, else `b`, which is only evaluated
+    /// then.
+    Coalesce,
+}
+
+impl BinaryOp {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+            BinaryOp::Coalesce => "??",
+        }
+    }
+
+    pub fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BinaryExpr {
+    pub op: BinaryOp,
+    pub lhs: ExprId,
+    pub rhs: ExprId,
+    pub span: Span,
+}
+
+/// `(inner)`, kept so tools can reproduce the source.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParenExpr {
+    pub inner: ExprId,
+    pub span: Span,
+}
+
+/// An `if` in expression position. Each branch gives the expression its
+/// value with `yield`, so an `else` branch is required (checked in
+/// [`pass1`](crate::pass1)).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IfExpr {
+    pub cond: ExprId,
+    pub then_block: Block,
+    pub elifs: Vec<ElifBranch>,
+    pub else_block: Option<Block>,
+    pub span: Span,
+}
+
+/// `start..end`, the integers from `start` up to but not including `end`.
+/// Only a `for` loop's header parses one.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RangeExpr {
+    pub start: ExprId,
+    pub end: ExprId,
+    pub span: Span,
+}
+
+/// `[elem, ...]`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArrayExpr {
+    pub elems: Vec<ExprId>,
+    pub span: Span,
+}
+
+/// `{key: value, ...}`. Keys are strings or integers.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapExpr {
+    pub entries: Vec<MapEntry>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapEntry {
+    pub key: ExprId,
+    pub value: ExprId,
+    pub span: Span,
+}
+
+/// `base[index]`, an element of an array or the value of a map's key.
+/// Array indices are checked against the length and map keys against the
+/// entries at runtime.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndexExpr {
+    pub base: ExprId,
+    pub index: ExprId,
+    pub span: Span,
+}
+
+/// `Name { field: value, ... }`, a value of a declared struct. Every field
+/// is given exactly once, in any order.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructExpr {
+    pub name: Ident,
+    pub fields: Vec<FieldInit>,
+    pub span: Span,
+}
+
+/// `field: value` in a struct literal.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldInit {
+    pub name: Ident,
+    pub value: ExprId,
+    pub span: Span,
+}
+
+/// `base.field`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldExpr {
+    pub base: ExprId,
+    pub field: Ident,
+    pub span: Span,
+}
+
+/// `Enum::Variant`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VariantExpr {
+    pub enum_name: Ident,
+    pub variant: Ident,
+    pub span: Span,
+}
+
+impl From<IfStmt> for IfExpr {
+    fn from(s: IfStmt) -> Self {
+        IfExpr {
+            cond: s.cond,
+            then_block: s.then_block,
+            elifs: s.elifs,
+            else_block: s.else_block,
+            span: s.span,
+        }
+    }
+}
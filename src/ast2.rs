@@ -0,0 +1,342 @@
+//! Second-stage AST: the program with every name resolved.
+//!
+//! [`resolve`](crate::resolve) builds it from an [`ast1`](crate::ast1)
+//! program that passed [`pass1`](crate::pass1). Where ast1 has a name, this
+//! tree has the [`SymbolId`] the name refers to, and type annotations are
+//! [`Type`]s with the primitives told apart from structs and enums. The
+//! [`Program`] also keeps the symbols and the scopes they were bound in.
+//!
+//! Expressions and statements keep the ids they had in ast1: the node at an
+//! [`ExprId`] or [`StmtId`] here is the resolved form of the one at the same
+//! id there. Nodes without names are shared with ast1 as they are, and
+//! anything not needed after resolution, like attributes, is only in ast1.
+//!
+//! Names that did not resolve get a symbol of kind
+//! [`SymbolKind::Unresolved`](crate::scope::SymbolKind::Unresolved), so the tree is complete even when there are
+//! errors.
+
+use std::ops::Index;
+
+use crate::ast1::{
+    ArrayExpr, BinaryExpr, Block, BoolLit, ErrorNode, ExprId, ExprStmt, FieldExpr, FieldInit,
+    FloatLit, Ident, IfExpr, IfStmt, IndexExpr, IntLit, MapExpr, NoneLit, ParenExpr, Projection,
+    RangeExpr, ReturnStmt, StmtId, StrLit, UnaryExpr, Variant, WhileStmt, YieldStmt,
+};
+use crate::scope::{ScopeTree, SymbolId, SymbolTable};
+use crate::span::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Program {
+    pub arena: AstArena,
+    pub symbols: SymbolTable,
+    pub scopes: ScopeTree,
+    pub stmts: Vec<StmtId>,
+}
+
+/// Storage for the expressions and statements of one program.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AstArena {
+    pub(crate) exprs: Vec<Expr>,
+    pub(crate) stmts: Vec<Stmt>,
+}
+
+impl AstArena {
+    pub fn expr_count(&self) -> usize {
+        self.exprs.len()
+    }
+
+    pub fn stmt_count(&self) -> usize {
+        self.stmts.len()
+    }
+
+    pub fn exprs(&self) -> impl ExactSizeIterator<Item = (ExprId, &Expr)> {
+        self.exprs
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (ExprId(i as u32), e))
+    }
+
+    pub fn stmts(&self) -> impl ExactSizeIterator<Item = (StmtId, &Stmt)> {
+        self.stmts
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (StmtId(i as u32), s))
+    }
+}
+
+impl Index<ExprId> for AstArena {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.index()]
+    }
+}
+
+impl Index<StmtId> for AstArena {
+    type Output = Stmt;
+
+    fn index(&self, id: StmtId) -> &Stmt {
+        &self.stmts[id.index()]
+    }
+}
+
+/// A use of a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NameRef {
+    pub symbol: SymbolId,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stmt {
+    Let(LetStmt),
+    Const(ConstDecl),
+    Fn(FnDecl),
+    Struct(StructDecl),
+    Enum(EnumDecl),
+    Assign(AssignStmt),
+    Return(ReturnStmt),
+    Yield(YieldStmt),
+    If(IfStmt),
+    While(WhileStmt),
+    For(ForStmt),
+    Expr(ExprStmt),
+    Error(ErrorNode),
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Let(s) => s.span,
+            Stmt::Const(s) => s.span,
+            Stmt::Fn(s) => s.span,
+            Stmt::Struct(s) => s.span,
+            Stmt::Enum(s) => s.span,
+            Stmt::Assign(s) => s.span,
+            Stmt::Return(s) => s.span,
+            Stmt::Yield(s) => s.span,
+            Stmt::If(s) => s.span,
+            Stmt::While(s) => s.span,
+            Stmt::For(s) => s.span,
+            Stmt::Expr(s) => s.span,
+            Stmt::Error(s) => s.span,
+        }
+    }
+}
+
+/// `let mut? pattern (: type)? = value;`; the pattern's symbols say whether
+/// they are mutable.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LetStmt {
+    pub pattern: Pattern,
+    /// The annotated type and where it is written.
+    pub ty: Option<(Type, Span)>,
+    pub value: ExprId,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConstDecl {
+    pub symbol: SymbolId,
+    pub ty: Option<Type>,
+    pub value: ExprId,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FnDecl {
+    pub symbol: SymbolId,
+    pub params: Vec<Param>,
+    /// `None` without a return type; `-> ()` is [`Type::Unit`].
+    pub ret: Option<Type>,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Param {
+    pub symbol: SymbolId,
+    pub ty: Type,
+    pub default: Option<ExprId>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructDecl {
+    pub symbol: SymbolId,
+    pub fields: Vec<FieldDecl>,
+    pub span: Span,
+}
+
+/// Fields are found through the type of the value they belong to, so their
+/// names stay names.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldDecl {
+    pub name: Ident,
+    pub ty: Type,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnumDecl {
+    pub symbol: SymbolId,
+    pub variants: Vec<Variant>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AssignStmt {
+    pub target: NameRef,
+    pub path: Vec<Projection>,
+    pub value: ExprId,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ForStmt {
+    pub var: SymbolId,
+    pub range: ExprId,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pattern {
+    Name(NameRef),
+    Tuple(Vec<Pattern>),
+}
+
+impl Pattern {
+    pub fn span(&self) -> Span {
+        match self {
+            Pattern::Name(name) => name.span,
+            Pattern::Tuple(elems) => match (elems.first(), elems.last()) {
+                (Some(first), Some(last)) => first.span().to(last.span()),
+                _ => Span::default(),
+            },
+        }
+    }
+}
+
+/// A resolved type annotation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Type {
+    Int,
+    UInt,
+    Float,
+    Bool,
+    Str,
+    Unit,
+    Struct(SymbolId),
+    Enum(SymbolId),
+    Tuple(Vec<Type>),
+    Array(Box<Type>),
+    Map(Box<Type>, Box<Type>),
+    Optional(Box<Type>),
+    /// A name that is not a type. Its diagnostic has been reported.
+    ///
+    /// The [type checker](crate::typeck) also gives it to values whose type
+    /// it cannot know, and lets it fit everywhere.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expr {
+    Int(IntLit),
+    Float(FloatLit),
+    Str(StrLit),
+    Bool(BoolLit),
+    None(NoneLit),
+    Name(NameRef),
+    Call(CallExpr),
+    Unary(UnaryExpr),
+    Cast(CastExpr),
+    Binary(BinaryExpr),
+    Paren(ParenExpr),
+    If(IfExpr),
+    Range(RangeExpr),
+    Array(ArrayExpr),
+    Map(MapExpr),
+    Index(IndexExpr),
+    Struct(StructExpr),
+    Field(FieldExpr),
+    Variant(VariantExpr),
+    Error(ErrorNode),
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Int(e) => e.span,
+            Expr::Float(e) => e.span,
+            Expr::Str(e) => e.span,
+            Expr::Bool(e) => e.span,
+            Expr::None(e) => e.span,
+            Expr::Name(e) => e.span,
+            Expr::Call(e) => e.span,
+            Expr::Unary(e) => e.span,
+            Expr::Cast(e) => e.span,
+            Expr::Binary(e) => e.span,
+            Expr::Paren(e) => e.span,
+            Expr::If(e) => e.span,
+            Expr::Range(e) => e.span,
+            Expr::Array(e) => e.span,
+            Expr::Map(e) => e.span,
+            Expr::Index(e) => e.span,
+            Expr::Struct(e) => e.span,
+            Expr::Field(e) => e.span,
+            Expr::Variant(e) => e.span,
+            Expr::Error(e) => e.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CallExpr {
+    pub callee: NameRef,
+    pub args: Vec<ExprId>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CastExpr {
+    pub expr: ExprId,
+    pub ty: Type,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructExpr {
+    pub name: NameRef,
+    pub fields: Vec<FieldInit>,
+    pub span: Span,
+}
+
+/// `Enum::Variant`. The variant is found in the enum's declaration, so it
+/// stays a name.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VariantExpr {
+    pub enum_name: NameRef,
+    pub variant: Ident,
+    pub span: Span,
+}
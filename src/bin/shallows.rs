@@ -0,0 +1,98 @@
+//! Command-line driver.
+
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+usage: shallows <command> [args]
+
+commands:
+    check <file>           report everything wrong with a script, types
+                           included, without running it
+    explain <code>         show the long explanation of a diagnostic code
+    run --draft <file>     run a script without type checking, for quick
+                           iteration while writing it";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["check", path] => check(path),
+        ["explain", code] => explain(code),
+        ["run", "--draft", path] => run_draft(path),
+        ["run", _] => {
+            eprintln!("error: checked runs are not available yet; pass `--draft`");
+            ExitCode::FAILURE
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn explain(code: &str) -> ExitCode {
+    match shallows_vm::codes::explain(code) {
+        Some(text) => {
+            print!("{text}");
+            ExitCode::SUCCESS
+        }
+        None => {
+            eprintln!("error: no explanation for `{code}`");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn check(path: &str) -> ExitCode {
+    let Some(lines) = read(path) else {
+        return ExitCode::FAILURE;
+    };
+    let token = shallows_vm::CancellationToken::new();
+    let analysis = shallows_vm::analysis::analyze(&lines, &token).expect("never cancelled");
+    for d in &analysis.diagnostics {
+        eprint!("{}", d.render(&lines));
+    }
+    if analysis.has_errors() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn run_draft(path: &str) -> ExitCode {
+    let Some(lines) = read(path) else {
+        return ExitCode::FAILURE;
+    };
+    let parsed = shallows_vm::parse(&lines);
+    let mut diagnostics = parsed.diagnostics;
+    diagnostics.extend(shallows_vm::pass1::check(&parsed.program));
+    diagnostics.extend(shallows_vm::resolve::resolve(&parsed.program, None).1);
+    for d in &diagnostics {
+        eprint!("{}", d.render(&lines));
+    }
+    if diagnostics.iter().any(|d| d.is_error()) {
+        return ExitCode::FAILURE;
+    }
+    let mut interpreter = shallows_vm::draft::Interpreter::new();
+    let result = interpreter.run(&parsed.program);
+    for line in interpreter.output() {
+        println!("{line}");
+    }
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(trap) => {
+            eprint!("{}", trap.to_diagnostic().render(&lines));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn read(path: &str) -> Option<shallows_vm::Lines> {
+    match shallows_vm::Lines::from_path(path) {
+        Ok(lines) => Some(lines),
+        Err(err) => {
+            eprintln!("error: cannot read `{path}`: {err}");
+            None
+        }
+    }
+}
@@ -0,0 +1,902 @@
+//! Long-form explanations for diagnostic codes.
+//!
+//! Every code passed to [`Diagnostic::with_code`](crate::Diagnostic::with_code)
+//! has an entry here. Codes are grouped by the pass that emits them:
+//! `E01xx` lexer, `E02xx` parser, `E03xx` pass 1, `E04xx` name resolution,
+//! `E05xx` type checking. Warnings use `W` in place of `E` with the same
+//! numbering. A check that moves to another pass keeps its code, as
+//! `E0316` and `W0302` did when locals moved to name resolution.
+
+/// Documentation for one diagnostic code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeInfo {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub explanation: &'static str,
+}
+
+pub static CODES: &[CodeInfo] = &[
+    CodeInfo {
+        code: "E0101",
+        title: "unexpected character",
+        explanation: "\
+The source contains a character that cannot start any token.
+
+Erroneous example:
+
+    let price = 5 $ 2;
+
+Identifiers may contain letters, digits and `_`; everything else must be an
+operator or punctuation the language knows. Remove the character, or put it
+inside a string literal if it is meant as text:
+
+    let label = \"$5\";",
+    },
+    CodeInfo {
+        code: "E0102",
+        title: "unterminated string literal",
+        explanation: "\
+A string literal is missing its closing `\"`.
+
+Erroneous example:
+
+    let name = \"goblin;
+
+String literals end on the line they start on. Close the literal, and use
+`\\n` for line breaks inside it:
+
+    let name = \"goblin\";
+    let text = \"line one\\nline two\";",
+    },
+    CodeInfo {
+        code: "E0103",
+        title: "unknown escape sequence",
+        explanation: "\
+A string literal contains a backslash followed by a character that is not a
+known escape.
+
+Erroneous example:
+
+    let path = \"data\\maps\";
+
+The supported escapes are `\\n`, `\\t`, `\\r`, `\\0`, `\\\\` and `\\\"`. Write
+`\\\\` for a literal backslash:
+
+    let path = \"data\\\\maps\";",
+    },
+    CodeInfo {
+        code: "E0201",
+        title: "unexpected token",
+        explanation: "\
+The parser needed a specific token, such as `;` or `)`, and found something
+else.
+
+Erroneous example:
+
+    let speed = 10.0
+    move(speed);
+
+Every statement ends with `;`, even at the end of a line:
+
+    let speed = 10.0;
+    move(speed);",
+    },
+    CodeInfo {
+        code: "E0202",
+        title: "expected expression",
+        explanation: "\
+A value was required but the next token cannot start an expression.
+
+Erroneous example:
+
+    let damage = * 2;
+
+Expressions are literals, names, calls, parenthesized expressions, and
+operators applied to them:
+
+    let damage = base * 2;",
+    },
+    CodeInfo {
+        code: "E0203",
+        title: "expected statement",
+        explanation: "\
+A statement was expected but the next token cannot start one.
+
+Erroneous example:
+
+    else { flee(); }
+
+Statements are `let`, assignments, `return`, `yield`, `if` and expressions
+followed by `;`. An `else` must directly follow the block of an `if`:
+
+    if hp < 10 { heal(); } else { flee(); }",
+    },
+    CodeInfo {
+        code: "E0204",
+        title: "chained comparison",
+        explanation: "\
+Comparison operators cannot be chained.
+
+Erroneous example:
+
+    if 0 < x < 10 { }
+
+Unlike in mathematics, this does not test that `x` lies between the bounds.
+Combine two comparisons with `&&`:
+
+    if 0 < x && x < 10 { }",
+    },
+    CodeInfo {
+        code: "E0205",
+        title: "expected type",
+        explanation: "\
+A type annotation is missing or malformed.
+
+Erroneous example:
+
+    let speed: = 10.0;
+
+Write a type name after `:`, or leave out the annotation to have the type
+inferred:
+
+    let speed: float = 10.0;
+    let speed = 10.0;",
+    },
+    CodeInfo {
+        code: "E0206",
+        title: "expected pattern",
+        explanation: "\
+`let` must be followed by the name(s) to bind.
+
+Erroneous example:
+
+    let = 10;
+
+Write the name of the new variable:
+
+    let count = 10;",
+    },
+    CodeInfo {
+        code: "E0207",
+        title: "tuple with one element",
+        explanation: "\
+A tuple type has a single element. Tuple types have at least two; `()`, with
+none, is the type of no value.
+
+Erroneous example:
+
+    let x: (float) = 1.0;
+
+A single value is written without parentheses:
+
+    let x: float = 1.0;
+    let pos: (float, float) = origin();",
+    },
+    CodeInfo {
+        code: "E0208",
+        title: "nesting too deep",
+        explanation: "\
+An expression, block or type is nested more deeply than the parser allows.
+Each operator in a chain like `a + b + c` counts as a level too, as do `?`
+suffixes on a type.
+
+Erroneous example, continued for a few hundred levels:
+
+    let x = ((((((((((((((((1))))))))))))))));
+    let y = 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1;
+
+The limit keeps pathological or generated input from exhausting the stack of
+the host program. Split the expression into several `let` statements. Hosts
+that really need deeper nesting can raise the limit with
+`Parser::with_max_depth`.",
+    },
+    CodeInfo {
+        code: "E0209",
+        title: "attributes without a declaration",
+        explanation: "\
+Attributes must be followed by the declaration they apply to.
+
+Erroneous example:
+
+    #[export]
+    move(1.0);
+
+Attributes describe a declaration, such as a `let`, to the host and to later
+passes; a plain statement has nothing for them to describe. Remove the
+attribute or put it in front of a declaration:
+
+    #[export]
+    let speed = 1.0;",
+    },
+    CodeInfo {
+        code: "E0301",
+        title: "`if` expression without `else`",
+        explanation: "\
+An `if` used as a value has no `else` branch.
+
+Erroneous example:
+
+    let speed = if running { yield 8.0; };
+
+When no condition holds, an `if` without `else` has nothing to yield. Add an
+`else` branch that yields a value of the same kind:
+
+    let speed = if running { yield 8.0; } else { yield 2.0; };
+
+An `if` at the start of a statement is run for its effects only and does not
+need an `else`.",
+    },
+    CodeInfo {
+        code: "E0302",
+        title: "malformed attribute",
+        explanation: "\
+A known attribute was given arguments it does not take.
+
+Erroneous example:
+
+    #[deprecated(3)]
+    let speed = 1.0;
+
+`#[export]` and `#[inline]` take no arguments. `#[deprecated]` takes at most
+one, a string literal explaining what to use instead:
+
+    #[deprecated(\"use `velocity`\")]
+    let speed = 1.0;",
+    },
+    CodeInfo {
+        code: "E0303",
+        title: "declaration inside a block",
+        explanation: "\
+A `fn`, `struct`, `enum` or `const` declaration appears inside a block instead
+of at the top level of the script.
+
+Erroneous example:
+
+    if ready {
+        fn fire() { launch(); }
+    }
+
+Functions, types and constants cannot be declared inside `if` branches or
+other functions. Move the declaration to the top level; it can be used from anywhere
+in the script, including before the line it is declared on:
+
+    fn fire() { launch(); }
+    if ready { fire(); }",
+    },
+    CodeInfo {
+        code: "E0304",
+        title: "wrong number of arguments",
+        explanation: "\
+A script function is called with a different number of arguments than it
+declares parameters.
+
+Erroneous example:
+
+    fn heal(amount: int) { hp = hp + amount; }
+    heal();
+
+Pass one argument for each parameter, in order. Parameters with a default
+value can be left out at the end of the call:
+
+    heal(10);",
+    },
+    CodeInfo {
+        code: "E0305",
+        title: "function declared twice",
+        explanation: "\
+Two top-level `fn` declarations have the same name.
+
+Erroneous example:
+
+    fn step() { move(1.0, 0.0); }
+    fn step() { move(0.0, 1.0); }
+
+Every function name must be unique within a script. Rename or remove one of
+the declarations.",
+    },
+    CodeInfo {
+        code: "E0306",
+        title: "type declared twice",
+        explanation: "\
+Two top-level `struct` or `enum` declarations have the same name.
+
+Erroneous example:
+
+    struct Target { x: float, y: float }
+    enum Target { Enemy, Ally }
+
+Structs and enums share one set of names, and each name must be unique within
+a script. Rename or remove one of the declarations.",
+    },
+    CodeInfo {
+        code: "E0307",
+        title: "field named twice",
+        explanation: "\
+A struct declaration or struct literal names the same field twice.
+
+Erroneous example:
+
+    struct Vec2 { x: float, y: float }
+    let v = Vec2 { x: 1.0, x: 2.0 };
+
+Each field is declared once and given once in every literal:
+
+    let v = Vec2 { x: 1.0, y: 2.0 };",
+    },
+    CodeInfo {
+        code: "E0308",
+        title: "struct literal does not match its declaration",
+        explanation: "\
+A struct literal gives a field the struct does not declare, or leaves out one
+that it does.
+
+Erroneous example:
+
+    struct Vec2 { x: float, y: float }
+    let v = Vec2 { x: 1.0, z: 2.0 };
+
+A literal gives a value for every declared field and nothing else, in any
+order:
+
+    let v = Vec2 { y: 2.0, x: 1.0 };",
+    },
+    CodeInfo {
+        code: "E0309",
+        title: "invalid enum variant",
+        explanation: "\
+An enum declares the same variant twice, gives two variants the same value,
+or has a variant whose value does not fit in an `int`.
+
+Erroneous example:
+
+    enum State { Idle, Walk, Run = 1 }
+
+Every variant is backed by an `int`: the value written after it, or one more
+than the previous variant's, starting from 0. Here `Walk` is 1 as well. Each
+name and each value may appear once:
+
+    enum State { Idle, Walk, Run = 5 }",
+    },
+    CodeInfo {
+        code: "E0310",
+        title: "unknown enum variant",
+        explanation: "\
+An enum variant is used that the enum does not declare.
+
+Erroneous example:
+
+    enum State { Idle, Walk }
+    let s = State::Run;
+
+Use one of the declared variants, or add the missing one to the declaration:
+
+    enum State { Idle, Walk, Run }
+    let s = State::Run;",
+    },
+    CodeInfo {
+        code: "E0311",
+        title: "constant declared twice",
+        explanation: "\
+Two top-level `const` declarations have the same name.
+
+Erroneous example:
+
+    const GRAVITY: float = 9.81;
+    const GRAVITY: float = 1.62;
+
+Each constant name must be unique within a script. Rename or remove one of
+the declarations.",
+    },
+    CodeInfo {
+        code: "E0312",
+        title: "not a constant expression",
+        explanation: "\
+A `const` initializer or a parameter default uses something that is only known
+when the script runs, such as a call, a variable or a global.
+
+Erroneous example:
+
+    const MAX_SPEED: float = base_speed() * 2.0;
+    fn attack(power: float = strength) { }
+
+Constants are worked out before the script runs, and defaults must give the
+same value on every call, so both may only use literals, enum variants,
+constants and operators. A `const` initializer may only use constants declared
+before it. Use a `let` for values computed at runtime, and pass them as
+arguments:
+
+    let max_speed = base_speed() * 2.0;
+    fn attack(power: float = 1.0) { }",
+    },
+    CodeInfo {
+        code: "E0313",
+        title: "invalid cast target",
+        explanation: "\
+An `as` cast converts to something other than `int`, `uint` or `float`.
+
+Erroneous example:
+
+    let alive = hp as bool;
+
+Casts only convert between the number types. Write the conversion out for
+anything else:
+
+    let alive = hp > 0;",
+    },
+    CodeInfo {
+        code: "E0314",
+        title: "parameter without a default after one with a default",
+        explanation: "\
+A function parameter without a default value follows one that has a default.
+
+Erroneous example:
+
+    fn attack(power: float = 1.0, target: int) { }
+
+Arguments are matched to parameters in order, so a call can only leave out
+parameters at the end. Move the parameters with defaults last:
+
+    fn attack(target: int, power: float = 1.0) { }",
+    },
+    CodeInfo {
+        code: "E0315",
+        title: "wrong number of values",
+        explanation: "\
+A `return` gives a different number of values than its function declares,
+or a `let` unpacks a different number of values than the function called
+returns.
+
+Erroneous example:
+
+    fn nearest() -> (float, int) {
+        return 1.5;
+    }
+    let dist, id, kind = nearest();
+
+A function declared `-> (float, int)` returns exactly two values, one
+declared `-> float` one value, and one without a return type none. Make the
+`return` give what the declaration says, and unpack every value:
+
+    fn nearest() -> (float, int) {
+        return 1.5, 3;
+    }
+    let dist, id = nearest();",
+    },
+    CodeInfo {
+        code: "E0316",
+        title: "assignment to a binding that is not `mut`",
+        explanation: "\
+A local is assigned, but it was declared without `mut`, or it is a parameter
+or a loop variable.
+
+Erroneous example:
+
+    let hp = 10;
+    hp = hp - 1;
+
+Bindings are immutable unless declared with `let mut`, which also goes for
+assigning part of one, like an array element or a struct field. Declare the
+local `mut` if it is meant to change:
+
+    let mut hp = 10;
+    hp = hp - 1;
+
+Parameters and loop variables cannot be assigned; copy them into a `let mut`
+local instead.",
+    },
+    CodeInfo {
+        code: "E0317",
+        title: "missing `return`",
+        explanation: "\
+A function declares a return type, but a path through its body reaches the
+end without a `return`.
+
+Erroneous example:
+
+    fn sign(n: int) -> int {
+        if n < 0 {
+            return -1;
+        } elif n > 0 {
+            return 1;
+        }
+    }
+
+Without an `else`, no branch runs when every condition is false, and the
+function ends without a value. Return on every path, for example with an
+`else` or a final `return`:
+
+    fn sign(n: int) -> int {
+        if n < 0 {
+            return -1;
+        } elif n > 0 {
+            return 1;
+        }
+        return 0;
+    }",
+    },
+    CodeInfo {
+        code: "E0318",
+        title: "missing `yield`",
+        explanation: "\
+A branch of an `if` used as a value can reach its end without a `yield`.
+
+Erroneous example:
+
+    let speed = if running {
+        yield 2.0;
+    } else {
+        print(\"walking\");
+    };
+
+Every branch gives the `if` its value with `yield` (or leaves the function
+with `return`), on every path through it:
+
+    let speed = if running {
+        yield 2.0;
+    } else {
+        yield 1.0;
+    };",
+    },
+    CodeInfo {
+        code: "E0401",
+        title: "unknown name",
+        explanation: "\
+A name is used as a value, but no local, parameter, constant or global of
+the host has that name.
+
+Erroneous example:
+
+    fn heal(amount: int) -> int {
+        return hp + amount;
+    }
+
+A function body sees its parameters, the constants and the host's globals,
+but not the locals of the top level. Check the spelling, declare the local
+before its use, or pass the value in as a parameter:
+
+    fn heal(hp: int, amount: int) -> int {
+        return hp + amount;
+    }",
+    },
+    CodeInfo {
+        code: "E0402",
+        title: "unknown function",
+        explanation: "\
+A call names a function that the script does not declare, the host does not
+provide and that is not built in.
+
+Erroneous example:
+
+    play_sound(\"step\");
+
+If the function only exists in some versions of the host, call it through
+`try_call`, which gives `none` when the function is missing:
+
+    try_call(play_sound(\"step\"));",
+    },
+    CodeInfo {
+        code: "E0403",
+        title: "unknown type",
+        explanation: "\
+A type annotation, struct literal or enum variant names a type the script
+does not declare.
+
+Erroneous example:
+
+    let pos: Vec2 = origin();
+
+The primitive types are `int`, `uint`, `float`, `bool` and `str`; any other
+type is a struct or enum declared in the script:
+
+    struct Vec2 { x: float, y: float }
+    let pos: Vec2 = origin();",
+    },
+    CodeInfo {
+        code: "E0404",
+        title: "assignment to a constant or global",
+        explanation: "\
+An assignment targets a constant or a global provided by the host.
+
+Erroneous example:
+
+    hp = hp - 10;
+
+Constants never change, and globals belong to the host, which may hand the
+script fresh values between runs. Copy the value into a local and change
+that, or call a host function that updates the host's state:
+
+    let mut hp = hp;
+    hp = hp - 10;",
+    },
+    CodeInfo {
+        code: "E0501",
+        title: "mismatched types",
+        explanation: "\
+A value has a different type than the place it goes to: an annotated
+`let`, a parameter, a struct field, an assignment, a `return`, or the other
+elements of an array or map.
+
+Erroneous example:
+
+    fn heal(amount: float) { }
+    heal(10);
+
+Numbers are never converted implicitly. Write the value with the right type,
+or convert it with `as`:
+
+    heal(10.0);
+    heal(n as float);",
+    },
+    CodeInfo {
+        code: "E0502",
+        title: "operator cannot be applied",
+        explanation: "\
+An operator is used with operands it does not work on.
+
+Erroneous example:
+
+    let speed = base + 0.5;    // `base` is an `int`
+
+Arithmetic and ordering need two numbers of the same type, `+` also joins
+two strings, `==` and `!=` compare values of one type, `-` negates an `int`
+or `float` and `!` negates a `bool`. Convert one operand with `as`:
+
+    let speed = base as float + 0.5;",
+    },
+    CodeInfo {
+        code: "E0503",
+        title: "value of the wrong kind",
+        explanation: "\
+A position that needs a particular kind of value gets something else:
+conditions and the operands of `&&` and `||` need a `bool`, array indices an
+`int` or `uint`, map keys a `str` or `int`, and the bounds of a range two
+`int`s or two `uint`s.
+
+Erroneous example:
+
+    if count { attack(); }
+
+There is no implicit truthiness; compare explicitly:
+
+    if count > 0 { attack(); }",
+    },
+    CodeInfo {
+        code: "E0504",
+        title: "no value",
+        explanation: "\
+A value is needed, but the expression gives none: a call to a function
+without a return type, or a `yield` without values in an `if` expression.
+
+Erroneous example:
+
+    fn greet() { print(\"hi\"); }
+    let g = greet();
+
+Call such a function as a statement, or give it a return type and return a
+value:
+
+    greet();",
+    },
+    CodeInfo {
+        code: "E0505",
+        title: "invalid cast",
+        explanation: "\
+An `as` cast converts from a type that is not a number.
+
+Erroneous example:
+
+    let n = alive as int;
+
+Only `int`, `uint`, `float` and enum values can be cast, and only to
+`int`, `uint` or `float`. Use a conditional for other conversions:
+
+    let n = if alive { yield 1; } else { yield 0; };",
+    },
+    CodeInfo {
+        code: "E0506",
+        title: "invalid index or field access",
+        explanation: "\
+A value is indexed or has a field read, but its type has no elements or no
+such field.
+
+Erroneous example:
+
+    let hp = player.health;    // `Player` has a field `hp`
+
+Only arrays and maps can be indexed, and only structs have fields. Check the
+declaration for the field names:
+
+    let hp = player.hp;",
+    },
+    CodeInfo {
+        code: "E0507",
+        title: "invalid built-in call",
+        explanation: "\
+A built-in function gets the wrong number or types of arguments.
+
+Erroneous example:
+
+    let n = len(42);
+
+`len` takes a string, array or map; `contains` and `starts_with` take two
+strings; `substring` takes a string and two `int`s; `is_some` takes one
+value and `try_call` a single call:
+
+    let n = len(name);",
+    },
+    CodeInfo {
+        code: "E0508",
+        title: "wrong number of tuple values",
+        explanation: "\
+A tuple has a different number of values than its annotation says, or a
+`let` unpacks it into a different number of names.
+
+Erroneous example:
+
+    let pos: (float, float, float) = origin();    // returns `(float, float)`
+    let x, y = pos;
+
+A tuple type lists one type per value, and a `let` that unpacks a tuple
+names every value. Make the two sides agree:
+
+    let pos: (float, float) = origin();
+    let x, y = pos;
+
+When the values come straight from a call, the count is checked against the
+function's declaration instead; see E0315.",
+    },
+    CodeInfo {
+        code: "E0509",
+        title: "suffix on a float literal",
+        explanation: "\
+A literal with a fractional part has the suffix `u` or `i`.
+
+Erroneous example:
+
+    let step = 10.5u;
+
+The suffixes `u` and `i` make a literal a `uint` or an `int`, which hold
+whole numbers only. Drop the suffix for a `float`, or write a whole number:
+
+    let step = 10.5;
+    let step = 10u;",
+    },
+    CodeInfo {
+        code: "E0510",
+        title: "integer literal out of range",
+        explanation: "\
+An integer literal does not fit in its type: it is larger than the type
+holds, or it is a `uint` literal with a `-` in front.
+
+Erroneous example:
+
+    let big = 10000000000000000000;
+    let back = -1u;
+
+An `int` holds values from -9223372036854775808 to 9223372036854775807, and a
+`uint` from 0 to 18446744073709551615. Use the type that fits the value:
+
+    let big = 10000000000000000000u;
+    let back = -1;",
+    },
+    CodeInfo {
+        code: "W0301",
+        title: "unknown attribute",
+        explanation: "\
+An attribute name is not one the language knows. It is ignored.
+
+Example:
+
+    #[exported]
+    let speed = 1.0;
+
+The known attributes are `#[export]`, `#[inline]` and `#[deprecated]`. This is
+a warning rather than an error so scripts keep compiling when they carry
+attributes meant for newer versions or other tools; fix the spelling if the
+attribute is meant to have an effect:
+
+    #[export]
+    let speed = 1.0;",
+    },
+    CodeInfo {
+        code: "W0302",
+        title: "unused variable",
+        explanation: "\
+A local, parameter or loop variable is never read.
+
+Example:
+
+    fn on_hit(damage: int, source: str) {
+        let scaled = damage * 2;
+        print(\"hit\");
+    }
+
+Assigning to a local does not count as reading it. Remove the binding if it
+is not needed, or start its name with `_` if it must exist anyway, like a
+parameter the host always passes:
+
+    fn on_hit(_damage: int, _source: str) {
+        print(\"hit\");
+    }
+
+Locals marked `#[export]` are read by the host and never reported.",
+    },
+    CodeInfo {
+        code: "W0303",
+        title: "unused value",
+        explanation: "\
+An expression statement computes a value that is then thrown away.
+
+Example:
+
+    let hp = 10;
+    hp - 1;
+
+Calls are exempt, since they are usually made for what they do. Anything
+else in statement position has no effect, which tends to mean a missing
+assignment or `return`:
+
+    let mut hp = 10;
+    hp = hp - 1;
+
+Write `let _ = ...;` to discard a value on purpose.",
+    },
+    CodeInfo {
+        code: "W0304",
+        title: "unreachable code",
+        explanation: "\
+Statements follow a `return`, a `yield`, an `if` whose every branch ends
+with one of those, or a `while true` loop. They never run.
+
+Example:
+
+    fn on_hit(hp: int) -> int {
+        return hp - 1;
+        print(\"hit\");
+    }
+
+This is often a leftover from an earlier version of the script. Remove the
+statements, or move them before the `return` if they should run:
+
+    fn on_hit(hp: int) -> int {
+        print(\"hit\");
+        return hp - 1;
+    }
+
+Declarations of functions, structs, enums and constants are not reported;
+they do not run, and take effect wherever they are.",
+    },
+    CodeInfo {
+        code: "W0401",
+        title: "name declared again in the same scope",
+        explanation: "\
+A `let`, parameter or pattern binds a name that the same block already
+binds.
+
+Example:
+
+    let hp = 10;
+    let hp = hp - 1;
+
+The second binding shadows the first for the rest of the block, which is
+allowed, but usually a `let mut` and an assignment were meant:
+
+    let mut hp = 10;
+    hp = hp - 1;
+
+Shadowing a name from an enclosing block, or a parameter inside the
+function body, is not reported.",
+    },
+];
+
+pub fn lookup(code: &str) -> Option<&'static CodeInfo> {
+    CODES
+        .iter()
+        .find(|info| info.code.eq_ignore_ascii_case(code))
+}
+
+/// The full explanation of `code`, formatted for display, or `None` if the
+/// code is unknown.
+pub fn explain(code: &str) -> Option<String> {
+    lookup(code).map(|info| format!("{}: {}\n\n{}\n", info.code, info.title, info.explanation))
+}
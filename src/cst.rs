@@ -0,0 +1,793 @@
+//! Concrete syntax tree: the program with every token kept.
+//!
+//! Where [`ast1`](crate::ast1) keeps only what the program means, the CST
+//! keeps how it is written: whitespace, comments, line terminators and text
+//! the lexer rejected all stay in the tree, so [`SyntaxNode::text`]
+//! reproduces the source exactly. That makes it the input for formatters
+//! and refactorings; [`lower`] turns it into the AST1 the rest of the
+//! pipeline works on.
+//!
+//! The tree is built from the same parser as AST1, so both always agree on
+//! the grammar. Trivia before a node's first token belongs to the enclosing
+//! node, e.g. a comment above a statement is a child of the block or
+//! program, not of the statement.
+
+use crate::ast1::*;
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::lexer::Lexer;
+use crate::line_map::Lines;
+use crate::parser::Parser;
+use crate::span::Span;
+use crate::token::{Token, TokenKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    Program,
+    LetStmt,
+    ConstDecl,
+    FnDecl,
+    /// `name: type` in a function's parameter list.
+    Param,
+    StructDecl,
+    /// `name: type` in a struct declaration.
+    FieldDecl,
+    EnumDecl,
+    /// `Name = value` in an enum declaration.
+    Variant,
+    AssignStmt,
+    ReturnStmt,
+    YieldStmt,
+    IfStmt,
+    WhileStmt,
+    ForStmt,
+    ExprStmt,
+    /// `elif cond { }` of an `if` statement or expression.
+    ElifBranch,
+    /// `else { }` of an `if` statement or expression.
+    ElseBranch,
+    Block,
+    /// `#[name(args)]` in front of a declaration.
+    Attribute,
+    /// The bindings of a `let`, one name or several.
+    Pattern,
+    NamedType,
+    TupleType,
+    ArrayType,
+    MapType,
+    OptionalType,
+    Literal,
+    /// A name used as an expression.
+    NameRef,
+    CallExpr,
+    UnaryExpr,
+    CastExpr,
+    BinaryExpr,
+    ParenExpr,
+    IfExpr,
+    RangeExpr,
+    ArrayExpr,
+    MapExpr,
+    /// `key: value` in a map literal.
+    MapEntry,
+    IndexExpr,
+    StructExpr,
+    /// `field: value` in a struct literal.
+    FieldInit,
+    FieldExpr,
+    VariantExpr,
+    /// Tokens that failed to parse, possibly with whatever complete nodes
+    /// were found among them.
+    Error,
+}
+
+/// A token together with its exact source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    pub kind: TokenKind,
+    pub span: Span,
+    /// The source text, e.g. the escapes of a string literal as written, or
+    /// the `\r\n` ending a line.
+    pub text: String,
+}
+
+impl SyntaxToken {
+    /// Whitespace, comments, line ends, rejected text and the end of file.
+    pub fn is_trivia(&self) -> bool {
+        self.kind.is_trivia() || matches!(self.kind, TokenKind::Newline | TokenKind::Eof)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxNode {
+    kind: SyntaxKind,
+    children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn children(&self) -> &[SyntaxElement] {
+        &self.children
+    }
+
+    /// Child nodes, skipping tokens.
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|c| match c {
+            SyntaxElement::Node(n) => Some(n),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Child tokens that are not trivia, skipping nodes.
+    pub fn tokens(&self) -> impl Iterator<Item = &SyntaxToken> {
+        self.children.iter().filter_map(|c| match c {
+            SyntaxElement::Token(t) if !t.is_trivia() => Some(t),
+            _ => None,
+        })
+    }
+
+    /// The first child token of `kind`.
+    pub fn token(&self, kind: &TokenKind) -> Option<&SyntaxToken> {
+        self.tokens().find(|t| &t.kind == kind)
+    }
+
+    /// The span from the first to the last non-trivia token anywhere in the
+    /// node, or `None` if it has none.
+    pub fn span(&self) -> Option<Span> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        let mut significant = tokens.iter().filter(|t| !t.is_trivia());
+        let first = significant.next()?;
+        let last = significant.next_back().unwrap_or(first);
+        Some(first.span.to(last.span))
+    }
+
+    fn collect_tokens<'a>(&'a self, out: &mut Vec<&'a SyntaxToken>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(n) => n.collect_tokens(out),
+                SyntaxElement::Token(t) => out.push(t),
+            }
+        }
+    }
+
+    /// The source text the node was parsed from. For the root this is the
+    /// whole file, byte for byte.
+    pub fn text(&self) -> String {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens.iter().map(|t| t.text.as_str()).collect()
+    }
+}
+
+/// A concrete syntax tree together with everything reported while lexing
+/// and parsing it. The diagnostics are the same [`parse`](crate::parse)
+/// reports.
+#[derive(Debug, Clone)]
+pub struct CstParse {
+    pub root: SyntaxNode,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// How the parser describes the tree it recognizes: nodes open and close
+/// around the tokens it consumes. Trivia is not mentioned; it is filled in
+/// from the token stream when the tree is built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Event {
+    /// Opens a node. The parser pushes it with no `kind` where a node may
+    /// start, and fills the kind in once it knows the node is there; starts
+    /// it never fills in are skipped. A node that wraps one already
+    /// finished, as for left-associative operators, starts later in the
+    /// log, `forward_parent` events after the node it wraps.
+    Start {
+        kind: Option<SyntaxKind>,
+        forward_parent: Option<usize>,
+    },
+    Finish,
+    /// The parser consumed the next non-trivia token.
+    Token,
+}
+
+impl Event {
+    /// A start with no kind yet, or one whose node was already opened.
+    pub(crate) const TOMBSTONE: Event = Event::Start {
+        kind: None,
+        forward_parent: None,
+    };
+}
+
+/// Parses a whole program into a lossless tree. Like [`parse`](crate::parse),
+/// syntax errors do not stop the parse; they show up as
+/// [`SyntaxKind::Error`] nodes.
+pub fn parse(lines: &Lines) -> CstParse {
+    let lexed = Lexer::new(lines.config()).with_trivia(true).tokenize(lines);
+    let mut diagnostics = Diagnostics::new();
+    diagnostics.extend(lexed.diagnostics);
+    let mut parser = Parser::new(lexed.tokens.clone())
+        .with_diagnostics(diagnostics)
+        .with_events();
+    parser.parse_program();
+    let events = parser.take_events();
+    CstParse {
+        root: build(lines, lexed.tokens, events),
+        diagnostics: parser.into_diagnostics().into_vec(),
+    }
+}
+
+fn build(lines: &Lines, tokens: Vec<Token>, mut events: Vec<Event>) -> SyntaxNode {
+    let mut tokens = tokens
+        .into_iter()
+        .map(|t| SyntaxToken {
+            text: token_text(lines, &t),
+            kind: t.kind,
+            span: t.span,
+        })
+        .peekable();
+    let mut stack = vec![SyntaxNode {
+        kind: SyntaxKind::Program,
+        children: Vec::new(),
+    }];
+    for i in 0..events.len() {
+        let event = events[i];
+        if let Event::Start { kind: None, .. } = event {
+            continue;
+        }
+        // Trivia goes to the innermost node open before the next token,
+        // which is never one that starts at that token.
+        if event != Event::Finish {
+            let parent = stack.last_mut().expect("unbalanced events");
+            while let Some(t) = tokens.next_if(|t| t.is_trivia() && t.kind != TokenKind::Eof) {
+                parent.children.push(SyntaxElement::Token(t));
+            }
+        }
+        match event {
+            Event::Start {
+                kind: Some(kind),
+                mut forward_parent,
+            } => {
+                // Open the wrapping nodes first, taking them out of the log
+                // so they are not opened again where they were recorded.
+                let mut kinds = vec![kind];
+                let mut at = i;
+                while let Some(distance) = forward_parent {
+                    at += distance;
+                    let taken = std::mem::replace(&mut events[at], Event::TOMBSTONE);
+                    let Event::Start {
+                        kind: Some(kind),
+                        forward_parent: next,
+                    } = taken
+                    else {
+                        unreachable!("a forward parent is a started node");
+                    };
+                    kinds.push(kind);
+                    forward_parent = next;
+                }
+                for kind in kinds.into_iter().rev() {
+                    stack.push(SyntaxNode {
+                        kind,
+                        children: Vec::new(),
+                    });
+                }
+            }
+            Event::Start { kind: None, .. } => unreachable!("skipped above"),
+            Event::Finish => {
+                let node = stack.pop().expect("unbalanced events");
+                let parent = stack.last_mut().expect("unbalanced events");
+                parent.children.push(SyntaxElement::Node(node));
+            }
+            Event::Token => {
+                let t = tokens.next().expect("parser consumed a missing token");
+                let parent = stack.last_mut().expect("unbalanced events");
+                parent.children.push(SyntaxElement::Token(t));
+            }
+        }
+    }
+    let mut root = stack.pop().expect("unbalanced events");
+    assert!(stack.is_empty(), "unbalanced events");
+    root.children.extend(tokens.map(SyntaxElement::Token));
+    root
+}
+
+fn token_text(lines: &Lines, token: &Token) -> String {
+    let source = lines.source();
+    match token.kind {
+        TokenKind::Eof => String::new(),
+        TokenKind::Newline => {
+            let idx = token.span.start.line;
+            let line = lines.get(idx).expect("newline of a missing line");
+            let end_of_content = lines.line_start(idx).unwrap() + line.content.len();
+            let next = lines.line_start(idx + 1).unwrap_or(source.len());
+            source[end_of_content..next].to_string()
+        }
+        _ => {
+            let start = lines.byte_offset(token.span.start);
+            let end = lines.byte_offset(token.span.end);
+            source[start.unwrap()..end.unwrap()].to_string()
+        }
+    }
+}
+
+/// Lowers a tree produced by [`parse`] into AST1. The result equals what
+/// [`parse`](crate::parse) returns for the same source.
+pub fn lower(root: &SyntaxNode) -> Program {
+    let mut lower = Lower {
+        arena: AstArena::new(),
+    };
+    let stmts = root.nodes().map(|n| lower.stmt(n)).collect();
+    Program {
+        arena: lower.arena,
+        stmts,
+    }
+}
+
+/// Allocates nodes in the same order as the parser: children before their
+/// parents, in source order.
+struct Lower {
+    arena: AstArena,
+}
+
+impl Lower {
+    fn stmt(&mut self, node: &SyntaxNode) -> StmtId {
+        let span = node.span().expect("statements have tokens");
+        let stmt = match node.kind {
+            SyntaxKind::LetStmt => {
+                let mut nodes = node.nodes().peekable();
+                let attrs = self.attrs(&mut nodes);
+                let pattern = lower_pattern(nodes.next().expect("let pattern"));
+                let mut value = nodes.next().expect("let value");
+                let ty = match value.kind {
+                    SyntaxKind::NamedType
+                    | SyntaxKind::TupleType
+                    | SyntaxKind::ArrayType
+                    | SyntaxKind::MapType
+                    | SyntaxKind::OptionalType => {
+                        let ty = lower_type(value);
+                        value = nodes.next().expect("let value");
+                        Some(ty)
+                    }
+                    _ => None,
+                };
+                let value = match value.kind {
+                    // An error without tokens sits right after the `=`.
+                    SyntaxKind::Error => {
+                        let span = value.span().unwrap_or(Span::point(
+                            node.token(&TokenKind::Eq).expect("`=`").span.end,
+                        ));
+                        self.arena.alloc_expr(Expr::Error(ErrorNode { span }))
+                    }
+                    _ => self.expr(value),
+                };
+                Stmt::Let(LetStmt {
+                    attrs,
+                    mutable: node.token(&TokenKind::Mut).is_some(),
+                    pattern,
+                    ty,
+                    value,
+                    span,
+                })
+            }
+            SyntaxKind::ConstDecl => {
+                let mut nodes = node.nodes().peekable();
+                let attrs = self.attrs(&mut nodes);
+                let mut value = nodes.next().expect("constant value");
+                let ty = match value.kind {
+                    SyntaxKind::NamedType
+                    | SyntaxKind::TupleType
+                    | SyntaxKind::ArrayType
+                    | SyntaxKind::MapType
+                    | SyntaxKind::OptionalType => {
+                        let ty = lower_type(value);
+                        value = nodes.next().expect("constant value");
+                        Some(ty)
+                    }
+                    _ => None,
+                };
+                Stmt::Const(ConstDecl {
+                    attrs,
+                    name: ident(node),
+                    ty,
+                    value: self.expr(value),
+                    span,
+                })
+            }
+            SyntaxKind::FnDecl => {
+                let mut nodes = node.nodes().peekable();
+                let attrs = self.attrs(&mut nodes);
+                let mut params = Vec::new();
+                while let Some(param) = nodes.next_if(|n| n.kind == SyntaxKind::Param) {
+                    let mut parts = param.nodes();
+                    params.push(Param {
+                        name: ident(param),
+                        ty: lower_type(parts.next().expect("parameter type")),
+                        default: parts.next().map(|default| self.expr(default)),
+                        span: param.span().expect("parameter name"),
+                    });
+                }
+                let ret = nodes
+                    .next_if(|n| n.kind != SyntaxKind::Block)
+                    .map(lower_type);
+                Stmt::Fn(FnDecl {
+                    attrs,
+                    name: ident(node),
+                    params,
+                    ret,
+                    body: self.block(nodes.next().expect("function body")),
+                    span,
+                })
+            }
+            SyntaxKind::StructDecl => {
+                let mut nodes = node.nodes().peekable();
+                let attrs = self.attrs(&mut nodes);
+                let fields = nodes
+                    .map(|field| FieldDecl {
+                        name: ident(field),
+                        ty: lower_type(field.nodes().next().expect("field type")),
+                        span: field.span().expect("field name"),
+                    })
+                    .collect();
+                Stmt::Struct(StructDecl {
+                    attrs,
+                    name: ident(node),
+                    fields,
+                    span,
+                })
+            }
+            SyntaxKind::EnumDecl => {
+                let mut nodes = node.nodes().peekable();
+                let attrs = self.attrs(&mut nodes);
+                let variants = nodes
+                    .map(|variant| Variant {
+                        name: ident(variant),
+                        value: variant.tokens().find_map(|t| match &t.kind {
+                            TokenKind::Int { raw, suffix } => Some(IntLit {
+                                raw: raw.clone(),
+                                suffix: *suffix,
+                                span: t.span,
+                            }),
+                            _ => None,
+                        }),
+                        span: variant.span().expect("variant name"),
+                    })
+                    .collect();
+                Stmt::Enum(EnumDecl {
+                    attrs,
+                    name: ident(node),
+                    variants,
+                    span,
+                })
+            }
+            SyntaxKind::AssignStmt => {
+                // Indices are nodes and field names bare tokens after a `.`;
+                // the node after the `=` is the value.
+                let mut path = Vec::new();
+                let mut value = None;
+                let (mut after_dot, mut after_eq) = (false, false);
+                for child in node.children() {
+                    match child {
+                        SyntaxElement::Node(n) if after_eq => value = Some(self.expr(n)),
+                        SyntaxElement::Node(n) => path.push(Projection::Index(self.expr(n))),
+                        SyntaxElement::Token(t) => match t.kind {
+                            TokenKind::Dot => after_dot = true,
+                            TokenKind::Eq => after_eq = true,
+                            _ if after_dot => {
+                                if let Some(field) = as_ident(t) {
+                                    path.push(Projection::Field(field));
+                                    after_dot = false;
+                                }
+                            }
+                            _ => {}
+                        },
+                    }
+                }
+                Stmt::Assign(AssignStmt {
+                    target: ident(node),
+                    path,
+                    value: value.expect("assigned value"),
+                    span,
+                })
+            }
+            SyntaxKind::ReturnStmt => Stmt::Return(ReturnStmt {
+                values: node.nodes().map(|n| self.expr(n)).collect(),
+                span,
+            }),
+            SyntaxKind::YieldStmt => Stmt::Yield(YieldStmt {
+                values: node.nodes().map(|n| self.expr(n)).collect(),
+                span,
+            }),
+            SyntaxKind::IfStmt => Stmt::If(self.if_(node)),
+            SyntaxKind::WhileStmt => {
+                let mut nodes = node.nodes();
+                let cond = self.expr(nodes.next().expect("while condition"));
+                let body = self.block(nodes.next().expect("while body"));
+                Stmt::While(WhileStmt { cond, body, span })
+            }
+            SyntaxKind::ForStmt => {
+                let mut nodes = node.nodes();
+                let range = self.expr(nodes.next().expect("for range"));
+                let body = self.block(nodes.next().expect("for body"));
+                Stmt::For(ForStmt {
+                    var: ident(node),
+                    range,
+                    body,
+                    span,
+                })
+            }
+            SyntaxKind::ExprStmt => {
+                let expr = self.expr(node.nodes().next().expect("expression"));
+                Stmt::Expr(ExprStmt { expr, span })
+            }
+            SyntaxKind::Error => Stmt::Error(ErrorNode { span }),
+            kind => panic!("{kind:?} is not a statement"),
+        };
+        self.arena.alloc_stmt(stmt)
+    }
+
+    /// Attributes at the front of a declaration's child nodes.
+    fn attrs<'n>(
+        &mut self,
+        nodes: &mut std::iter::Peekable<impl Iterator<Item = &'n SyntaxNode>>,
+    ) -> Vec<Attribute> {
+        let mut attrs = Vec::new();
+        while let Some(attr) = nodes.next_if(|n| n.kind == SyntaxKind::Attribute) {
+            attrs.push(Attribute {
+                name: ident(attr),
+                args: attr.nodes().map(|n| self.expr(n)).collect(),
+                span: attr.span().expect("`#`"),
+            });
+        }
+        attrs
+    }
+
+    fn if_(&mut self, node: &SyntaxNode) -> IfStmt {
+        let mut nodes = node.nodes();
+        let cond = self.expr(nodes.next().expect("if condition"));
+        let then_block = self.block(nodes.next().expect("if block"));
+        let mut elifs = Vec::new();
+        let mut else_block = None;
+        for branch in nodes {
+            let mut parts = branch.nodes();
+            match branch.kind {
+                SyntaxKind::ElifBranch => {
+                    let cond = self.expr(parts.next().expect("elif condition"));
+                    elifs.push(ElifBranch {
+                        cond,
+                        block: self.block(parts.next().expect("elif block")),
+                        span: branch.span().expect("`elif`"),
+                    });
+                }
+                SyntaxKind::ElseBranch => {
+                    else_block = Some(self.block(parts.next().expect("else block")));
+                }
+                kind => panic!("{kind:?} is not an if branch"),
+            }
+        }
+        IfStmt {
+            cond,
+            then_block,
+            elifs,
+            else_block,
+            span: node.span().expect("`if`"),
+        }
+    }
+
+    fn block(&mut self, node: &SyntaxNode) -> Block {
+        Block {
+            stmts: node.nodes().map(|n| self.stmt(n)).collect(),
+            span: node.span().expect("`{`"),
+        }
+    }
+
+    fn expr(&mut self, node: &SyntaxNode) -> ExprId {
+        let first = || node.tokens().next().expect("expression token");
+        let span = || node.span().expect("expression token");
+        let expr = match node.kind {
+            SyntaxKind::Literal => {
+                let token = first();
+                let span = token.span;
+                match token.kind.clone() {
+                    TokenKind::Int { raw, suffix } => Expr::Int(IntLit { raw, suffix, span }),
+                    TokenKind::Float { raw, suffix } => Expr::Float(FloatLit { raw, suffix, span }),
+                    TokenKind::Str(value) => Expr::Str(StrLit { value, span }),
+                    TokenKind::True => Expr::Bool(BoolLit { value: true, span }),
+                    TokenKind::False => Expr::Bool(BoolLit { value: false, span }),
+                    TokenKind::None => Expr::None(NoneLit { span }),
+                    kind => panic!("{kind} is not a literal"),
+                }
+            }
+            SyntaxKind::NameRef => Expr::Ident(ident(node)),
+            SyntaxKind::CallExpr => {
+                let callee = ident(node);
+                Expr::Call(CallExpr {
+                    span: span(),
+                    callee,
+                    args: node.nodes().map(|n| self.expr(n)).collect(),
+                })
+            }
+            SyntaxKind::UnaryExpr => {
+                let token = first();
+                let op = match token.kind {
+                    TokenKind::Minus => UnaryOp::Neg,
+                    TokenKind::Bang => UnaryOp::Not,
+                    ref kind => panic!("{kind} is not a unary operator"),
+                };
+                Expr::Unary(UnaryExpr {
+                    op,
+                    operand: self.expr(node.nodes().next().expect("operand")),
+                    span: span(),
+                })
+            }
+            SyntaxKind::CastExpr => {
+                let mut parts = node.nodes();
+                Expr::Cast(CastExpr {
+                    expr: self.expr(parts.next().expect("cast operand")),
+                    ty: lower_type(parts.next().expect("cast type")),
+                    span: span(),
+                })
+            }
+            SyntaxKind::BinaryExpr => {
+                let token = first();
+                let op = crate::parser::binary_op(&token.kind).expect("binary operator");
+                let mut operands = node.nodes();
+                Expr::Binary(BinaryExpr {
+                    op,
+                    lhs: self.expr(operands.next().expect("left operand")),
+                    rhs: self.expr(operands.next().expect("right operand")),
+                    span: span(),
+                })
+            }
+            SyntaxKind::ParenExpr => Expr::Paren(ParenExpr {
+                inner: self.expr(node.nodes().next().expect("inner expression")),
+                span: span(),
+            }),
+            SyntaxKind::IfExpr => Expr::If(self.if_(node).into()),
+            SyntaxKind::ArrayExpr => Expr::Array(ArrayExpr {
+                elems: node.nodes().map(|n| self.expr(n)).collect(),
+                span: span(),
+            }),
+            SyntaxKind::MapExpr => Expr::Map(MapExpr {
+                entries: node
+                    .nodes()
+                    .map(|entry| {
+                        let mut parts = entry.nodes();
+                        MapEntry {
+                            key: self.expr(parts.next().expect("map key")),
+                            value: self.expr(parts.next().expect("map value")),
+                            span: entry.span().expect("map key"),
+                        }
+                    })
+                    .collect(),
+                span: span(),
+            }),
+            SyntaxKind::IndexExpr => {
+                let mut parts = node.nodes();
+                Expr::Index(IndexExpr {
+                    base: self.expr(parts.next().expect("indexed expression")),
+                    index: self.expr(parts.next().expect("index")),
+                    span: span(),
+                })
+            }
+            SyntaxKind::StructExpr => Expr::Struct(StructExpr {
+                name: ident(node),
+                fields: node
+                    .nodes()
+                    .map(|field| FieldInit {
+                        name: ident(field),
+                        value: self.expr(field.nodes().next().expect("field value")),
+                        span: field.span().expect("field name"),
+                    })
+                    .collect(),
+                span: span(),
+            }),
+            SyntaxKind::FieldExpr => Expr::Field(FieldExpr {
+                base: self.expr(node.nodes().next().expect("field base")),
+                field: ident(node),
+                span: span(),
+            }),
+            SyntaxKind::VariantExpr => {
+                let mut names = node.tokens().filter_map(as_ident);
+                Expr::Variant(VariantExpr {
+                    enum_name: names.next().expect("enum name"),
+                    variant: names.next().expect("variant name"),
+                    span: span(),
+                })
+            }
+            SyntaxKind::RangeExpr => {
+                let mut bounds = node.nodes();
+                Expr::Range(RangeExpr {
+                    start: self.expr(bounds.next().expect("range start")),
+                    end: self.expr(bounds.next().expect("range end")),
+                    span: span(),
+                })
+            }
+            kind => panic!("{kind:?} is not an expression"),
+        };
+        self.arena.alloc_expr(expr)
+    }
+}
+
+fn lower_pattern(node: &SyntaxNode) -> Pattern {
+    let mut elems: Vec<Pattern> = node
+        .tokens()
+        .filter_map(as_ident)
+        .map(Pattern::Ident)
+        .collect();
+    if elems.len() == 1 {
+        return elems.pop().unwrap();
+    }
+    let span = elems[0].span().to(elems[elems.len() - 1].span());
+    Pattern::Tuple(TuplePattern { elems, span })
+}
+
+fn lower_type(node: &SyntaxNode) -> TypeRef {
+    match node.kind {
+        SyntaxKind::NamedType => TypeRef::Named(ident(node)),
+        SyntaxKind::TupleType => {
+            let mut elems: Vec<TypeRef> = node.nodes().map(lower_type).collect();
+            // `(T)` was reported and stands for `T`.
+            if elems.len() == 1 {
+                return elems.pop().unwrap();
+            }
+            let open = node.token(&TokenKind::LParen).expect("`(`").span;
+            let close = node.token(&TokenKind::RParen).expect("`)`").span;
+            if elems.is_empty() {
+                return TypeRef::Unit(open.to(close));
+            }
+            TypeRef::Tuple(TupleType {
+                elems,
+                span: open.to(close),
+            })
+        }
+        SyntaxKind::ArrayType => {
+            let open = node.token(&TokenKind::LBracket).expect("`[`").span;
+            let close = node.token(&TokenKind::RBracket).expect("`]`").span;
+            TypeRef::Array(ArrayType {
+                elem: Box::new(lower_type(node.nodes().next().expect("element type"))),
+                span: open.to(close),
+            })
+        }
+        SyntaxKind::OptionalType => {
+            let inner = lower_type(node.nodes().next().expect("optional's inner type"));
+            let question = node.token(&TokenKind::Question).expect("`?`").span;
+            TypeRef::Optional(OptionalType {
+                span: inner.span().to(question),
+                inner: Box::new(inner),
+            })
+        }
+        SyntaxKind::MapType => {
+            let open = node.token(&TokenKind::LBrace).expect("`{`").span;
+            let close = node.token(&TokenKind::RBrace).expect("`}`").span;
+            let mut parts = node.nodes();
+            TypeRef::Map(MapType {
+                key: Box::new(lower_type(parts.next().expect("key type"))),
+                value: Box::new(lower_type(parts.next().expect("value type"))),
+                span: open.to(close),
+            })
+        }
+        kind => panic!("{kind:?} is not a type"),
+    }
+}
+
+fn as_ident(token: &SyntaxToken) -> Option<Ident> {
+    match &token.kind {
+        TokenKind::Ident(name) => Some(Ident {
+            name: name.clone(),
+            span: token.span,
+        }),
+        _ => None,
+    }
+}
+
+/// The first identifier token directly in `node`.
+fn ident(node: &SyntaxNode) -> Ident {
+    node.tokens()
+        .find_map(as_ident)
+        .expect("node has an identifier")
+}
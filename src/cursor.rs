@@ -0,0 +1,75 @@
+//! Character cursor over a single source line.
+
+use std::str::Chars;
+
+use crate::line_map::{Line, SourceConfig};
+use crate::span::Loc;
+
+/// Walks the characters of one [`Line`], tracking the current [`Loc`].
+#[derive(Debug, Clone)]
+pub struct Cursor<'a> {
+    line: usize,
+    content: &'a str,
+    chars: Chars<'a>,
+    col: usize,
+    display_col: usize,
+    tab_width: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(line: Line<'a>, config: SourceConfig) -> Self {
+        Self {
+            line: line.idx,
+            content: line.content,
+            chars: line.content.chars(),
+            col: 0,
+            display_col: 0,
+            tab_width: config.tab_width.max(1),
+        }
+    }
+
+    /// The location of the next character (or of the end of the line).
+    pub fn loc(&self) -> Loc {
+        Loc {
+            line: self.line,
+            col: self.col,
+            display_col: self.display_col,
+        }
+    }
+
+    /// Byte offset of the next character within the line.
+    pub fn byte_col(&self) -> usize {
+        self.content.len() - self.chars.as_str().len()
+    }
+
+    pub fn peek(&self) -> Option<char> {
+        self.chars.clone().next()
+    }
+
+    /// The character after [`peek`](Self::peek).
+    pub fn peek2(&self) -> Option<char> {
+        let mut chars = self.chars.clone();
+        chars.next();
+        chars.next()
+    }
+
+    pub fn is_eol(&self) -> bool {
+        self.chars.as_str().is_empty()
+    }
+
+    pub fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.col += 1;
+        self.display_col = match c {
+            '\t' => (self.display_col / self.tab_width + 1) * self.tab_width,
+            _ => self.display_col + 1,
+        };
+        Some(c)
+    }
+
+    pub fn eat_while(&mut self, mut pred: impl FnMut(char) -> bool) {
+        while self.peek().is_some_and(&mut pred) {
+            self.bump();
+        }
+    }
+}
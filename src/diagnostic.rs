@@ -0,0 +1,290 @@
+//! Compiler diagnostics and the sink that collects them.
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+
+use crate::line_map::Lines;
+use crate::span::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+}
+
+/// A secondary span with an explanation, e.g. "first declared here".
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, span: Span, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error(span: Span, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, span, message)
+    }
+
+    pub fn warning(span: Span, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, span, message)
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Renders the diagnostic with a source snippet and carets under the
+    /// offending text.
+    pub fn render(&self, lines: &Lines) -> String {
+        let mut out = String::new();
+        match self.code {
+            Some(code) => {
+                let _ = writeln!(
+                    out,
+                    "{}[{}]: {}",
+                    self.severity.as_str(),
+                    code,
+                    self.message
+                );
+            }
+            None => {
+                let _ = writeln!(out, "{}: {}", self.severity.as_str(), self.message);
+            }
+        }
+        let path = lines
+            .path()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "<source>".to_string());
+        let gutter = (self.span.start.line + 1).to_string().len().max(
+            self.labels
+                .iter()
+                .map(|l| (l.span.start.line + 1).to_string().len())
+                .max()
+                .unwrap_or(0),
+        );
+        let _ = writeln!(
+            out,
+            "{:gutter$}--> {}:{}:{}",
+            "",
+            path,
+            self.span.start.line + 1,
+            self.span.start.col + 1,
+        );
+        render_snippet(&mut out, lines, self.span, None, gutter);
+        for label in &self.labels {
+            render_snippet(&mut out, lines, label.span, Some(&label.message), gutter);
+        }
+        for note in &self.notes {
+            let _ = writeln!(out, "{:gutter$} = note: {}", "", note);
+        }
+        out
+    }
+}
+
+fn render_snippet(
+    out: &mut String,
+    lines: &Lines,
+    span: Span,
+    message: Option<&str>,
+    gutter: usize,
+) {
+    let Some(line) = lines.get(span.start.line) else {
+        return;
+    };
+    let tab_width = lines.config().tab_width.max(1);
+    let mut text = String::new();
+    for c in line.content.chars() {
+        if c == '\t' {
+            let pad = tab_width - text.chars().count() % tab_width;
+            text.extend(std::iter::repeat_n(' ', pad));
+        } else {
+            text.push(c);
+        }
+    }
+    let start = span.start.display_col;
+    let end = if span.end.line == span.start.line {
+        span.end.display_col
+    } else {
+        text.chars().count()
+    };
+    let width = end.saturating_sub(start).max(1);
+    let _ = writeln!(out, "{:gutter$} |", "");
+    let _ = writeln!(out, "{:>gutter$} | {}", span.start.line + 1, text);
+    // Not a format width: those are limited to `u16::MAX`, and lines can be
+    // longer.
+    let _ = write!(
+        out,
+        "{:gutter$} | {}{}",
+        "",
+        " ".repeat(start),
+        "^".repeat(width)
+    );
+    if let Some(message) = message {
+        let _ = write!(out, " {message}");
+    }
+    out.push('\n');
+}
+
+/// Collects diagnostics for one compilation.
+///
+/// Besides storing diagnostics in emission order, the sink keeps cascades
+/// from burying the root cause:
+///
+/// - identical diagnostics are reported once;
+/// - an error starting at the same location as an earlier error is dropped,
+///   as it is almost always a consequence of the first one;
+/// - errors inside a [poisoned](Diagnostics::poison) span are dropped, which
+///   passes use for regions they already reported and skipped over;
+/// - with [`max_errors`](Diagnostics::with_max_errors) set, errors past the
+///   limit are only counted.
+#[derive(Debug, Default, Clone)]
+pub struct Diagnostics {
+    items: Vec<Diagnostic>,
+    seen: HashSet<Diagnostic>,
+    poisoned: Vec<Span>,
+    max_errors: Option<usize>,
+    errors: usize,
+    omitted: usize,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_errors(mut self, max: usize) -> Self {
+        self.max_errors = Some(max);
+        self
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        if diagnostic.is_error() {
+            let loc = diagnostic.span.start;
+            let follow_on = self.poisoned.iter().any(|s| s.contains(loc))
+                || self.items.iter().any(|d| {
+                    d.is_error() && d.span.start == loc && d.message != diagnostic.message
+                });
+            if follow_on {
+                return;
+            }
+        }
+        if !self.seen.insert(diagnostic.clone()) {
+            return;
+        }
+        if diagnostic.is_error() {
+            if self.max_errors.is_some_and(|max| self.errors >= max) {
+                self.omitted += 1;
+                return;
+            }
+            self.errors += 1;
+        }
+        self.items.push(diagnostic);
+    }
+
+    pub fn extend(&mut self, diagnostics: impl IntoIterator<Item = Diagnostic>) {
+        for d in diagnostics {
+            self.push(d);
+        }
+    }
+
+    /// Suppresses further errors that start inside `span`.
+    pub fn poison(&mut self, span: Span) {
+        self.poisoned.push(span);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors + self.omitted > 0
+    }
+
+    /// Number of errors seen, including omitted ones.
+    pub fn error_count(&self) -> usize {
+        self.errors + self.omitted
+    }
+
+    /// Number of errors dropped because of the error limit.
+    pub fn omitted(&self) -> usize {
+        self.omitted
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.items.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn into_vec(self) -> Vec<Diagnostic> {
+        self.items
+    }
+
+    /// Renders every diagnostic, followed by a summary of omitted errors.
+    pub fn render(&self, lines: &Lines) -> String {
+        let mut out = String::new();
+        for d in &self.items {
+            out.push_str(&d.render(lines));
+            out.push('\n');
+        }
+        match self.omitted {
+            0 => {}
+            1 => out.push_str("1 more error omitted\n"),
+            n => {
+                let _ = writeln!(out, "{n} more errors omitted");
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,1695 @@
+//! Draft mode: runs [`ast1`](crate::ast1) directly, without type checking
+//! or optimisation.
+//!
+//! Draft mode is for the edit-run loop while a script is being written. A
+//! program runs as soon as it parses, and the checks the type checker would
+//! make up front happen at runtime instead, as a [`Trap`] at the offending
+//! expression. Programs the checker would reject may therefore run fine
+//! until they reach the bad code, and even statements that failed to parse
+//! only trap when executed. Scripts are shipped through the strict pipeline;
+//! draft mode is never the default.
+//!
+//! Names resolve dynamically: locals first, innermost block outwards, then
+//! the script's constants, then host-provided globals. Constants and globals
+//! are read-only, and constants are evaluated, in order, before the rest of
+//! the program runs. A function body sees its parameters, the constants and
+//! the globals, not its caller's locals. Calls go to the
+//! script's own functions, then to host functions registered with
+//! [`Interpreter::with_function`], then to the built-ins: `print`, which
+//! records its arguments as one line of [output](Interpreter::output),
+//! `is_some`, which tells whether its argument is anything but `none`,
+//! `try_call`, and the string functions.
+//!
+//! Strings join with `+`. `len(s)` counts the characters of a string (or the
+//! elements of an array or map), `contains(s, part)` and
+//! `starts_with(s, prefix)` search one, and `substring(s, start, end)` takes
+//! its characters from `start` up to but not including `end`. Positions
+//! count characters, not bytes.
+//!
+//! `try_call(f(x))` is `f(x)` if `f` exists and `none` if it does not, so a
+//! script written against a newer host can still run on an older one that
+//! lacks some of its functions. The arguments are not evaluated when `f` is
+//! missing.
+//!
+//! Optional values are not wrapped: an `int?` holds an `int` or
+//! [`Value::None`], and host functions return `Value::None` for "nothing".
+//! Draft mode does not track which optional type a `none` stands for, so
+//! `none` can go wherever a value of any type is expected, except where an
+//! annotation asks for a type that is not optional.
+//!
+//! Hosts exchange enums as their backing `int`s. Enum values reach host
+//! functions as [`Value::Enum`], which carries the backing value, and an
+//! `int` bound where an annotation or parameter asks for an enum becomes the
+//! variant with that value, e.g. `let state: State = host_state();`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::ast1::*;
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+use crate::telemetry::{ErrorRecord, ErrorSink};
+use crate::token::NumSuffix;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    /// Several values at once, from `return a, b;`, `yield a, b;` or a
+    /// function returning more than one value.
+    Tuple(Vec<Value>),
+    /// Elements of one type. Arrays are values: assigning one copies it.
+    Array(Vec<Value>),
+    /// Values of one type by keys of one type, in key order. Copied on
+    /// assignment like arrays.
+    Map(BTreeMap<MapKey, Value>),
+    /// A value of a script struct. Copied on assignment like arrays.
+    Struct(StructValue),
+    /// A variant of a script enum.
+    Enum(EnumValue),
+    /// `none`, the absence of a value of an optional type.
+    None,
+}
+
+impl Value {
+    /// The name of the value's type, as written in annotations. Arrays,
+    /// maps and tuples go by their kind alone.
+    pub fn type_name(&self) -> &str {
+        match self {
+            Value::Int(_) => "int",
+            Value::UInt(_) => "uint",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::Str(_) => "str",
+            Value::Tuple(_) => "tuple",
+            Value::Array(_) => "array",
+            Value::Map(_) => "map",
+            Value::Struct(s) => &s.name,
+            Value::Enum(e) => &e.name,
+            Value::None => "none",
+        }
+    }
+
+    /// Whether `other` has the same type as far as [`type_name`](Self::type_name)
+    /// tells them apart. `none` has the type of whatever it stands for.
+    fn same_type(&self, other: &Value) -> bool {
+        matches!(self, Value::None)
+            || matches!(other, Value::None)
+            || self.type_name() == other.type_name()
+    }
+
+    /// Packs multiple results into one value: a single result stays as it
+    /// is, anything else becomes a tuple.
+    fn from_values(mut values: Vec<Value>) -> Value {
+        if values.len() == 1 {
+            values.pop().unwrap()
+        } else {
+            Value::Tuple(values)
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(v) => write!(f, "{v}"),
+            Value::UInt(v) => write!(f, "{v}"),
+            // `{:?}` keeps the `.0` on whole numbers, so `2.0` does not
+            // print like the int `2`.
+            Value::Float(v) => write!(f, "{v:?}"),
+            Value::Bool(v) => write!(f, "{v}"),
+            Value::Str(v) => f.write_str(v),
+            Value::Tuple(values) => {
+                f.write_str("(")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_str(")")
+            }
+            Value::Array(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_str("]")
+            }
+            Value::Map(entries) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                }
+                f.write_str("}")
+            }
+            Value::Struct(s) => {
+                write!(f, "{} {{", s.name)?;
+                for (i, (name, value)) in s.fields.iter().enumerate() {
+                    let sep = if i > 0 { "," } else { "" };
+                    write!(f, "{sep} {name}: {value}")?;
+                }
+                f.write_str(" }")
+            }
+            Value::Enum(e) => write!(f, "{}::{}", e.name, e.variant),
+            Value::None => f.write_str("none"),
+        }
+    }
+}
+
+/// An enum variant together with its backing value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumValue {
+    pub name: String,
+    pub variant: String,
+    pub value: i64,
+}
+
+/// The fields of a struct value, in declaration order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructValue {
+    pub name: String,
+    pub fields: Vec<(String, Value)>,
+}
+
+/// A map key: maps are keyed by strings or by `int`s.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MapKey {
+    Int(i64),
+    Str(String),
+}
+
+impl MapKey {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            MapKey::Int(_) => "int",
+            MapKey::Str(_) => "str",
+        }
+    }
+}
+
+impl fmt::Display for MapKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapKey::Int(v) => write!(f, "{v}"),
+            MapKey::Str(v) => f.write_str(v),
+        }
+    }
+}
+
+/// A runtime error that stopped a draft-mode run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trap {
+    pub message: String,
+    pub span: Span,
+    /// Whether the type checker would have reported it before running.
+    typed: bool,
+}
+
+impl Trap {
+    fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+            typed: false,
+        }
+    }
+
+    /// A trap for a type error, which the type checker reports before a
+    /// normal run.
+    fn typed(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            typed: true,
+            ..Self::new(span, message)
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.span, self.message.clone());
+        if self.typed {
+            diagnostic.with_note("reported at runtime; draft mode does not type-check")
+        } else {
+            diagnostic
+        }
+    }
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.span.start.line + 1,
+            self.span.start.col + 1,
+            self.message
+        )
+    }
+}
+
+/// A host function. It gets the evaluated arguments and returns any number
+/// of results, or an error message that becomes a [`Trap`] at the call.
+pub type HostFn = Box<dyn FnMut(&[Value]) -> Result<Vec<Value>, String>>;
+
+/// Functions every program can call, unless the script or the host
+/// defines its own.
+pub const BUILTINS: [&str; 7] = [
+    "print",
+    "is_some",
+    "try_call",
+    "len",
+    "contains",
+    "starts_with",
+    "substring",
+];
+
+/// Runs programs in draft mode.
+///
+/// Globals and host functions stay registered between runs; locals do not.
+#[derive(Default)]
+pub struct Interpreter {
+    globals: HashMap<String, Value>,
+    functions: HashMap<String, HostFn>,
+    scopes: Vec<HashMap<String, Value>>,
+    /// The running program's functions, by name.
+    fns: HashMap<String, StmtId>,
+    /// The running program's structs, by name.
+    structs: HashMap<String, StmtId>,
+    /// The running program's enums, by name.
+    enums: HashMap<String, StmtId>,
+    /// The running program's constants, by name.
+    consts: HashMap<String, Value>,
+    /// Script function calls in progress.
+    depth: usize,
+    output: Vec<String>,
+    /// Where traps are reported, with the script name to report them under.
+    error_sink: Option<(String, Arc<dyn ErrorSink>)>,
+    /// Whether float arithmetic that gives NaN or an infinity traps.
+    float_checks: bool,
+}
+
+/// Why execution left a statement early.
+enum Exit {
+    Return(Vec<Value>),
+    Trap(Trap),
+}
+
+impl From<Trap> for Exit {
+    fn from(trap: Trap) -> Self {
+        Exit::Trap(trap)
+    }
+}
+
+type Exec<T> = Result<T, Exit>;
+
+impl Interpreter {
+    /// Nesting limit for script function calls. Deeper recursion traps
+    /// instead of overflowing the native stack; the limit is kept low enough
+    /// for unoptimised builds on a 2 MiB thread stack.
+    pub const MAX_CALL_DEPTH: usize = 64;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_global(mut self, name: impl Into<String>, value: Value) -> Self {
+        self.globals.insert(name.into(), value);
+        self
+    }
+
+    /// Sets a global between runs, e.g. to hand the script fresh host state
+    /// every tick.
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        match self.globals.get_mut(name) {
+            Some(slot) => *slot = value,
+            None => {
+                self.globals.insert(name.to_string(), value);
+            }
+        }
+    }
+
+    pub fn with_function(
+        mut self,
+        name: impl Into<String>,
+        f: impl FnMut(&[Value]) -> Result<Vec<Value>, String> + 'static,
+    ) -> Self {
+        self.functions.insert(name.into(), Box::new(f));
+        self
+    }
+
+    /// Reports every trap to `sink`, as coming from the script `script`.
+    pub fn with_error_sink(mut self, script: impl Into<String>, sink: Arc<dyn ErrorSink>) -> Self {
+        self.error_sink = Some((script.into(), sink));
+        self
+    }
+
+    /// Traps at the first float arithmetic that gives NaN or an infinity,
+    /// naming the operator and its operands, instead of letting the value
+    /// spread through later calculations. A debugging aid: scripts that
+    /// divide by zero on purpose trap too.
+    pub fn with_float_checks(mut self) -> Self {
+        self.float_checks = true;
+        self
+    }
+
+    /// Runs `program` from the top. Returns the values of the top-level
+    /// `return`, or nothing if the program runs off its end.
+    pub fn run(&mut self, program: &Program) -> Result<Vec<Value>, Trap> {
+        let ast = &program.arena;
+        for &stmt in &program.stmts {
+            match &ast[stmt] {
+                Stmt::Fn(f) => {
+                    self.fns.insert(f.name.name.clone(), stmt);
+                }
+                Stmt::Struct(s) => {
+                    self.structs.insert(s.name.name.clone(), stmt);
+                }
+                Stmt::Enum(e) => {
+                    self.enums.insert(e.name.name.clone(), stmt);
+                }
+                _ => {}
+            }
+        }
+        self.scopes = vec![HashMap::new()];
+        self.depth = 0;
+        let result = self
+            .eval_consts(ast, &program.stmts)
+            .and_then(|()| self.exec_stmts(ast, &program.stmts));
+        let result = body_result(ast, &program.stmts, result);
+        self.scopes.clear();
+        self.fns.clear();
+        self.structs.clear();
+        self.enums.clear();
+        self.consts.clear();
+        match result {
+            Ok(values) => Ok(values),
+            Err(Exit::Return(_)) => unreachable!("handled by body_result"),
+            Err(Exit::Trap(trap)) => {
+                if let Some((script, sink)) = &self.error_sink {
+                    sink.record(&ErrorRecord::from_trap(script, &trap));
+                }
+                Err(trap)
+            }
+        }
+    }
+
+    /// Lines printed so far.
+    pub fn output(&self) -> &[String] {
+        &self.output
+    }
+
+    pub fn take_output(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.output)
+    }
+
+    fn eval_consts(&mut self, ast: &AstArena, stmts: &[StmtId]) -> Exec<()> {
+        for &stmt in stmts {
+            if let Stmt::Const(c) = &ast[stmt] {
+                let mut value = self.eval(ast, c.value)?;
+                if let Some(ty) = &c.ty {
+                    value = self.enum_from_int(ast, ty, value)?;
+                    self.check_type(ty, &value)?;
+                }
+                self.consts.insert(c.name.name.clone(), value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs statements until one yields, returning the yielded values.
+    fn exec_stmts(&mut self, ast: &AstArena, stmts: &[StmtId]) -> Exec<Option<Vec<Value>>> {
+        for &stmt in stmts {
+            if let Some(values) = self.exec_stmt(ast, stmt)? {
+                return Ok(Some(values));
+            }
+        }
+        Ok(None)
+    }
+
+    fn exec_block(&mut self, ast: &AstArena, block: &Block) -> Exec<Option<Vec<Value>>> {
+        self.scopes.push(HashMap::new());
+        let result = self.exec_stmts(ast, &block.stmts);
+        self.scopes.pop();
+        result
+    }
+
+    fn exec_stmt(&mut self, ast: &AstArena, stmt: StmtId) -> Exec<Option<Vec<Value>>> {
+        match &ast[stmt] {
+            Stmt::Let(s) => {
+                let mut value = self.eval(ast, s.value)?;
+                if let Some(ty) = &s.ty {
+                    value = self.enum_from_int(ast, ty, value)?;
+                    self.check_type(ty, &value)?;
+                }
+                self.bind(&s.pattern, value)?;
+            }
+            // Declarations were collected, and constants evaluated, before the
+            // program started.
+            Stmt::Const(_) | Stmt::Fn(_) | Stmt::Struct(_) | Stmt::Enum(_) => {}
+            Stmt::Assign(s) => {
+                let mut indices = Vec::new();
+                for step in &s.path {
+                    if let Projection::Index(index) = step {
+                        indices.push(self.eval(ast, *index)?);
+                    }
+                }
+                let value = self.eval(ast, s.value)?;
+                if s.path.is_empty() {
+                    self.assign(&s.target, value, s.span)?;
+                } else {
+                    self.assign_part(ast, s, indices, value)?;
+                }
+            }
+            Stmt::Return(s) => {
+                let values = self.eval_all(ast, &s.values)?;
+                return Err(Exit::Return(values));
+            }
+            Stmt::Yield(s) => return Ok(Some(self.eval_all(ast, &s.values)?)),
+            Stmt::If(s) => {
+                if let Some(block) =
+                    self.choose_branch(ast, s.cond, &s.then_block, &s.elifs, s.else_block.as_ref())?
+                {
+                    return self.exec_block(ast, block);
+                }
+            }
+            Stmt::While(s) => {
+                while self.eval_cond(ast, s.cond)? {
+                    if let Some(values) = self.exec_block(ast, &s.body)? {
+                        return Ok(Some(values));
+                    }
+                }
+            }
+            Stmt::For(s) => match self.eval_range(ast, s.range)? {
+                (Value::Int(lo), Value::Int(hi)) => {
+                    for i in lo..hi {
+                        if let Some(values) = self.exec_iteration(ast, s, Value::Int(i))? {
+                            return Ok(Some(values));
+                        }
+                    }
+                }
+                (Value::UInt(lo), Value::UInt(hi)) => {
+                    for i in lo..hi {
+                        if let Some(values) = self.exec_iteration(ast, s, Value::UInt(i))? {
+                            return Ok(Some(values));
+                        }
+                    }
+                }
+                _ => unreachable!("checked by eval_range"),
+            },
+            Stmt::Expr(s) => {
+                // A call in statement position may return nothing.
+                match &ast[s.expr] {
+                    Expr::Call(call) => {
+                        self.call(ast, call)?;
+                    }
+                    _ => {
+                        self.eval(ast, s.expr)?;
+                    }
+                }
+            }
+            Stmt::Error(e) => return Err(parse_error(e.span).into()),
+        }
+        Ok(None)
+    }
+
+    /// Evaluates the conditions of an `if` in order and returns the block to
+    /// run, if any.
+    fn choose_branch<'b>(
+        &mut self,
+        ast: &AstArena,
+        cond: ExprId,
+        then_block: &'b Block,
+        elifs: &'b [ElifBranch],
+        else_block: Option<&'b Block>,
+    ) -> Exec<Option<&'b Block>> {
+        if self.eval_cond(ast, cond)? {
+            return Ok(Some(then_block));
+        }
+        for elif in elifs {
+            if self.eval_cond(ast, elif.cond)? {
+                return Ok(Some(&elif.block));
+            }
+        }
+        Ok(else_block)
+    }
+
+    /// Runs one pass of a `for` body with the loop variable bound to
+    /// `value`.
+    fn exec_iteration(
+        &mut self,
+        ast: &AstArena,
+        s: &ForStmt,
+        value: Value,
+    ) -> Exec<Option<Vec<Value>>> {
+        self.scopes
+            .push(HashMap::from([(s.var.name.clone(), value)]));
+        let result = self.exec_block(ast, &s.body);
+        self.scopes.pop();
+        result
+    }
+
+    /// The bounds of a range, both `int` or both `uint`.
+    fn eval_range(&mut self, ast: &AstArena, expr: ExprId) -> Exec<(Value, Value)> {
+        let Expr::Range(range) = &ast[expr] else {
+            return Err(Trap::new(ast[expr].span(), "`for` can only iterate over a range").into());
+        };
+        let lo = self.eval(ast, range.start)?;
+        let hi = self.eval(ast, range.end)?;
+        match (&lo, &hi) {
+            (Value::Int(_), Value::Int(_)) | (Value::UInt(_), Value::UInt(_)) => Ok((lo, hi)),
+            _ => Err(Trap::typed(
+                range.span,
+                format!(
+                    "range bounds must both be `int` or both `uint`, found `{}` and `{}`",
+                    lo.type_name(),
+                    hi.type_name()
+                ),
+            )
+            .into()),
+        }
+    }
+
+    fn eval_cond(&mut self, ast: &AstArena, cond: ExprId) -> Exec<bool> {
+        match self.eval(ast, cond)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(Trap::typed(
+                ast[cond].span(),
+                format!("condition must be `bool`, found `{}`", other.type_name()),
+            )
+            .into()),
+        }
+    }
+
+    fn bind(&mut self, pattern: &Pattern, value: Value) -> Result<(), Trap> {
+        match pattern {
+            Pattern::Ident(ident) => {
+                self.scopes
+                    .last_mut()
+                    .expect("a scope is open while running")
+                    .insert(ident.name.clone(), value);
+                Ok(())
+            }
+            Pattern::Tuple(p) => match value {
+                Value::Tuple(values) if values.len() == p.elems.len() => {
+                    for (elem, value) in p.elems.iter().zip(values) {
+                        self.bind(elem, value)?;
+                    }
+                    Ok(())
+                }
+                other => Err(Trap::typed(
+                    p.span,
+                    format!(
+                        "cannot destructure {} into {} names",
+                        describe_count(&other),
+                        p.elems.len()
+                    ),
+                )),
+            },
+        }
+    }
+
+    fn assign(&mut self, target: &Ident, value: Value, span: Span) -> Result<(), Trap> {
+        let slot = self.local_mut(target)?;
+        if !slot.same_type(&value) {
+            return Err(Trap::typed(
+                span,
+                format!(
+                    "cannot assign `{}` to `{}`, which holds `{}`",
+                    value.type_name(),
+                    target.name,
+                    slot.type_name()
+                ),
+            ));
+        }
+        *slot = value;
+        Ok(())
+    }
+
+    /// `target[i].field = value;` with the indices already evaluated, in
+    /// order. A missing key in the last map is inserted.
+    fn assign_part(
+        &mut self,
+        ast: &AstArena,
+        s: &AssignStmt,
+        indices: Vec<Value>,
+        value: Value,
+    ) -> Result<(), Trap> {
+        let element_type = |slot: &Value| {
+            if slot.same_type(&value) {
+                return Ok(());
+            }
+            let part = match s.path.last() {
+                Some(Projection::Field(field)) => format!("field `{}`", field.name),
+                _ => "an element".to_string(),
+            };
+            Err(Trap::typed(
+                s.span,
+                format!(
+                    "cannot assign `{}` to {part} of `{}`, which holds `{}`",
+                    value.type_name(),
+                    s.target.name,
+                    slot.type_name()
+                ),
+            ))
+        };
+        let mut indices = indices.into_iter();
+        let mut slot = self.local_mut(&s.target)?;
+        for (n, step) in s.path.iter().enumerate() {
+            let expr = match step {
+                Projection::Index(expr) => *expr,
+                Projection::Field(field) => {
+                    slot = field_mut(slot, field)?;
+                    continue;
+                }
+            };
+            let index = indices.next().expect("every index was evaluated");
+            let span = ast[expr].span();
+            slot = match slot {
+                Value::Array(elems) => {
+                    let i = element_index(elems.len(), &index, span)?;
+                    &mut elems[i]
+                }
+                Value::Map(entries) => {
+                    let key = map_key(entries, index, span)?;
+                    if n + 1 == s.path.len() && !entries.contains_key(&key) {
+                        if let Some(existing) = entries.values().next() {
+                            element_type(existing)?;
+                        }
+                        entries.insert(key, value);
+                        return Ok(());
+                    }
+                    entries
+                        .get_mut(&key)
+                        .ok_or_else(|| missing_key(&key, span))?
+                }
+                other => {
+                    return Err(Trap::typed(
+                        span,
+                        format!("cannot index into `{}`", other.type_name()),
+                    ));
+                }
+            };
+        }
+        element_type(slot)?;
+        *slot = value;
+        Ok(())
+    }
+
+    /// The local `target` refers to, innermost scope first.
+    fn local_mut(&mut self, target: &Ident) -> Result<&mut Value, Trap> {
+        let message = if self.consts.contains_key(&target.name) {
+            format!("cannot assign to constant `{}`", target.name)
+        } else if self.globals.contains_key(&target.name) {
+            format!("cannot assign to global `{}`", target.name)
+        } else {
+            format!("cannot assign to undeclared `{}`", target.name)
+        };
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(&target.name))
+            .ok_or_else(|| Trap::new(target.span, message))
+    }
+
+    fn lookup(&self, ident: &Ident) -> Result<Value, Trap> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&ident.name))
+            .or_else(|| self.consts.get(&ident.name))
+            .or_else(|| self.globals.get(&ident.name))
+            .cloned()
+            .ok_or_else(|| Trap::new(ident.span, format!("unknown name `{}`", ident.name)))
+    }
+
+    fn eval_all(&mut self, ast: &AstArena, exprs: &[ExprId]) -> Exec<Vec<Value>> {
+        exprs.iter().map(|&e| self.eval(ast, e)).collect()
+    }
+
+    fn eval(&mut self, ast: &AstArena, expr: ExprId) -> Exec<Value> {
+        match &ast[expr] {
+            Expr::Int(lit) => Ok(int_value(lit)?),
+            Expr::Float(lit) => Ok(float_value(lit)?),
+            Expr::Str(lit) => Ok(Value::Str(lit.value.clone())),
+            Expr::Bool(lit) => Ok(Value::Bool(lit.value)),
+            Expr::None(_) => Ok(Value::None),
+            Expr::Ident(ident) => Ok(self.lookup(ident)?),
+            Expr::Call(call) => {
+                let values = self.call(ast, call)?;
+                if values.is_empty() {
+                    return Err(Trap::typed(
+                        call.span,
+                        format!("`{}` returned `()`, which is not a value", call.callee.name),
+                    )
+                    .into());
+                }
+                Ok(Value::from_values(values))
+            }
+            Expr::Unary(e) => {
+                let operand = match &ast[e.operand] {
+                    // Negated as it is read, so that `-9223372036854775808`
+                    // fits.
+                    Expr::Int(lit)
+                        if e.op == UnaryOp::Neg
+                            && matches!(lit.suffix, None | Some(NumSuffix::I)) =>
+                    {
+                        return Ok(negative_int_value(lit)?);
+                    }
+                    _ => self.eval(ast, e.operand)?,
+                };
+                Ok(unary(e.op, operand, e.span)?)
+            }
+            Expr::Cast(e) => {
+                let value = self.eval(ast, e.expr)?;
+                Ok(cast(value, &e.ty, e.span)?)
+            }
+            Expr::Binary(e) if matches!(e.op, BinaryOp::And | BinaryOp::Or) => {
+                let lhs = self.eval_logic_operand(ast, e.op, e.lhs)?;
+                // `&&` and `||` only evaluate their right side when the left
+                // does not already decide the result.
+                if lhs == (e.op == BinaryOp::Or) {
+                    return Ok(Value::Bool(lhs));
+                }
+                Ok(Value::Bool(self.eval_logic_operand(ast, e.op, e.rhs)?))
+            }
+            // The default is only evaluated when it is needed.
+            Expr::Binary(e) if e.op == BinaryOp::Coalesce => match self.eval(ast, e.lhs)? {
+                Value::None => self.eval(ast, e.rhs),
+                value => Ok(value),
+            },
+            Expr::Binary(e) => {
+                let lhs = self.eval(ast, e.lhs)?;
+                let rhs = self.eval(ast, e.rhs)?;
+                let operands = match (&lhs, &rhs) {
+                    (Value::Float(a), Value::Float(b)) if self.float_checks => Some((*a, *b)),
+                    _ => None,
+                };
+                let result = binary(e.op, lhs, rhs, e.span)?;
+                if let (Some((a, b)), Value::Float(v)) = (operands, &result)
+                    && !v.is_finite()
+                {
+                    return Err(Trap::new(
+                        e.span,
+                        format!("`{a:?} {} {b:?}` gives {v:?}", e.op.as_str()),
+                    )
+                    .into());
+                }
+                Ok(result)
+            }
+            Expr::Paren(e) => self.eval(ast, e.inner),
+            Expr::If(e) => {
+                let Some(block) = self.choose_branch(
+                    ast,
+                    e.cond,
+                    &e.then_block,
+                    &e.elifs,
+                    e.else_block.as_ref(),
+                )?
+                else {
+                    return Err(Trap::new(e.span, "no branch of this `if` was taken").into());
+                };
+                match self.exec_block(ast, block)? {
+                    Some(values) if !values.is_empty() => Ok(Value::from_values(values)),
+                    Some(_) => Err(Trap::typed(
+                        block.span,
+                        "this block yields `()`, but the `if` needs a value",
+                    )
+                    .into()),
+                    None => Err(Trap::new(block.span, "block ended without `yield`").into()),
+                }
+            }
+            Expr::Array(e) => {
+                let values = self.eval_all(ast, &e.elems)?;
+                if let Some(first) = values.iter().find(|v| **v != Value::None) {
+                    for (value, &expr) in values.iter().zip(&e.elems) {
+                        if !value.same_type(first) {
+                            return Err(Trap::typed(
+                                ast[expr].span(),
+                                format!(
+                                    "array elements must have one type: expected `{}`, found `{}`",
+                                    first.type_name(),
+                                    value.type_name()
+                                ),
+                            )
+                            .into());
+                        }
+                    }
+                }
+                Ok(Value::Array(values))
+            }
+            Expr::Index(e) => {
+                let base = self.eval(ast, e.base)?;
+                let index = self.eval(ast, e.index)?;
+                let span = ast[e.index].span();
+                match base {
+                    Value::Array(mut elems) => {
+                        let i = element_index(elems.len(), &index, span)?;
+                        Ok(elems.swap_remove(i))
+                    }
+                    Value::Map(mut entries) => {
+                        let key = map_key(&entries, index, span)?;
+                        Ok(entries
+                            .remove(&key)
+                            .ok_or_else(|| missing_key(&key, span))?)
+                    }
+                    other => Err(Trap::typed(
+                        ast[e.base].span(),
+                        format!("cannot index into `{}`", other.type_name()),
+                    )
+                    .into()),
+                }
+            }
+            Expr::Map(e) => {
+                let mut entries = BTreeMap::new();
+                for entry in &e.entries {
+                    let key = self.eval(ast, entry.key)?;
+                    let key = map_key(&entries, key, ast[entry.key].span())?;
+                    let value = self.eval(ast, entry.value)?;
+                    if let Some(first) = entries.values().find(|v| **v != Value::None)
+                        && !first.same_type(&value)
+                    {
+                        return Err(Trap::typed(
+                            ast[entry.value].span(),
+                            format!(
+                                "map values must have one type: expected `{}`, found `{}`",
+                                first.type_name(),
+                                value.type_name()
+                            ),
+                        )
+                        .into());
+                    }
+                    if entries.contains_key(&key) {
+                        return Err(Trap::new(
+                            ast[entry.key].span(),
+                            format!("key `{key}` appears twice in this map"),
+                        )
+                        .into());
+                    }
+                    entries.insert(key, value);
+                }
+                Ok(Value::Map(entries))
+            }
+            Expr::Struct(e) => Ok(self.eval_struct(ast, e)?),
+            Expr::Variant(e) => {
+                let Some(&decl) = self.enums.get(&e.enum_name.name) else {
+                    return Err(Trap::new(
+                        e.enum_name.span,
+                        format!("unknown enum `{}`", e.enum_name.name),
+                    )
+                    .into());
+                };
+                let Stmt::Enum(decl) = &ast[decl] else {
+                    unreachable!("only enums are collected");
+                };
+                let Some(i) = decl
+                    .variants
+                    .iter()
+                    .position(|v| v.name.name == e.variant.name)
+                else {
+                    return Err(Trap::new(
+                        e.variant.span,
+                        format!(
+                            "enum `{}` has no variant `{}`",
+                            decl.name.name, e.variant.name
+                        ),
+                    )
+                    .into());
+                };
+                let Some(value) = decl.backing_values()[i] else {
+                    return Err(Trap::new(
+                        decl.variants[i].span,
+                        format!(
+                            "the value of variant `{}` does not fit in `int`",
+                            e.variant.name
+                        ),
+                    )
+                    .into());
+                };
+                Ok(Value::Enum(EnumValue {
+                    name: decl.name.name.clone(),
+                    variant: e.variant.name.clone(),
+                    value,
+                }))
+            }
+            Expr::Field(e) => match self.eval(ast, e.base)? {
+                Value::Struct(s) => {
+                    let name = s.name;
+                    s.fields
+                        .into_iter()
+                        .find(|(f, _)| *f == e.field.name)
+                        .map(|(_, value)| value)
+                        .ok_or_else(|| no_field(&name, &e.field).into())
+                }
+                other => Err(Trap::typed(
+                    e.field.span,
+                    format!(
+                        "cannot access field `{}` of `{}`",
+                        e.field.name,
+                        other.type_name()
+                    ),
+                )
+                .into()),
+            },
+            Expr::Range(e) => {
+                Err(Trap::new(e.span, "a range can only be used by a `for` loop").into())
+            }
+            Expr::Error(e) => Err(parse_error(e.span).into()),
+        }
+    }
+
+    /// A struct literal, with its fields checked against the declaration.
+    fn eval_struct(&mut self, ast: &AstArena, e: &StructExpr) -> Exec<Value> {
+        let Some(&decl) = self.structs.get(&e.name.name) else {
+            return Err(Trap::new(e.name.span, format!("unknown struct `{}`", e.name.name)).into());
+        };
+        let Stmt::Struct(decl) = &ast[decl] else {
+            unreachable!("only structs are collected");
+        };
+        let mut values: Vec<Option<Value>> = vec![None; decl.fields.len()];
+        for init in &e.fields {
+            let value = self.eval(ast, init.value)?;
+            let Some(i) = decl
+                .fields
+                .iter()
+                .position(|f| f.name.name == init.name.name)
+            else {
+                return Err(no_field(&decl.name.name, &init.name).into());
+            };
+            if values[i].is_some() {
+                return Err(Trap::new(
+                    init.name.span,
+                    format!("field `{}` is given twice", init.name.name),
+                )
+                .into());
+            }
+            let ty = &decl.fields[i].ty;
+            if self.check_type(ty, &value).is_err() {
+                return Err(Trap::typed(
+                    ast[init.value].span(),
+                    format!(
+                        "field `{}` of `{}` expects `{}`, found `{}`",
+                        init.name.name,
+                        decl.name.name,
+                        type_text(ty),
+                        value.type_name()
+                    ),
+                )
+                .into());
+            }
+            values[i] = Some(value);
+        }
+        let mut fields = Vec::with_capacity(values.len());
+        for (field, value) in decl.fields.iter().zip(values) {
+            let Some(value) = value else {
+                return Err(Trap::new(
+                    e.name.span,
+                    format!(
+                        "missing field `{}` in `{}`",
+                        field.name.name, decl.name.name
+                    ),
+                )
+                .into());
+            };
+            fields.push((field.name.name.clone(), value));
+        }
+        Ok(Value::Struct(StructValue {
+            name: decl.name.name.clone(),
+            fields,
+        }))
+    }
+
+    fn eval_logic_operand(&mut self, ast: &AstArena, op: BinaryOp, expr: ExprId) -> Exec<bool> {
+        match self.eval(ast, expr)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(Trap::typed(
+                ast[expr].span(),
+                format!(
+                    "operands of `{}` must be `bool`, found `{}`",
+                    op.as_str(),
+                    other.type_name()
+                ),
+            )
+            .into()),
+        }
+    }
+
+    fn call(&mut self, ast: &AstArena, call: &CallExpr) -> Exec<Vec<Value>> {
+        if call.callee.name == "try_call" && !self.is_defined("try_call") {
+            return self.try_call(ast, call);
+        }
+        let args = self.eval_all(ast, &call.args)?;
+        if let Some(&decl) = self.fns.get(&call.callee.name) {
+            let Stmt::Fn(f) = &ast[decl] else {
+                unreachable!("only functions are collected");
+            };
+            return self.call_fn(ast, f, call, args);
+        }
+        if let Some(f) = self.functions.get_mut(&call.callee.name) {
+            return f(&args).map_err(|message| Trap::new(call.span, message).into());
+        }
+        Ok(self.call_builtin(call, args)?)
+    }
+
+    fn call_builtin(&mut self, call: &CallExpr, args: Vec<Value>) -> Result<Vec<Value>, Trap> {
+        let name = call.callee.name.as_str();
+        let count = |expected: usize| {
+            let s = if expected == 1 { "" } else { "s" };
+            Trap::typed(
+                call.span,
+                format!(
+                    "`{name}` takes {expected} argument{s}, but {} were given",
+                    args.len()
+                ),
+            )
+        };
+        let types = |expected: &str| {
+            let found: Vec<&str> = args.iter().map(Value::type_name).collect();
+            Trap::typed(
+                call.span,
+                format!(
+                    "`{name}` expects `({expected})`, found `({})`",
+                    found.join(", ")
+                ),
+            )
+        };
+        let value = match name {
+            "print" => {
+                let line: Vec<String> = args.iter().map(Value::to_string).collect();
+                self.output.push(line.join(" "));
+                return Ok(Vec::new());
+            }
+            "is_some" => {
+                let [value] = args.as_slice() else {
+                    return Err(count(1));
+                };
+                Value::Bool(*value != Value::None)
+            }
+            "len" => {
+                let [value] = args.as_slice() else {
+                    return Err(count(1));
+                };
+                let len = match value {
+                    Value::Str(s) => s.chars().count(),
+                    Value::Array(elems) => elems.len(),
+                    Value::Map(entries) => entries.len(),
+                    other => {
+                        return Err(Trap::typed(
+                            call.span,
+                            format!(
+                                "`len` expects a string, array or map, found `{}`",
+                                other.type_name()
+                            ),
+                        ));
+                    }
+                };
+                Value::Int(len as i64)
+            }
+            "contains" | "starts_with" => {
+                let [s, part] = args.as_slice() else {
+                    return Err(count(2));
+                };
+                let (Value::Str(s), Value::Str(part)) = (s, part) else {
+                    return Err(types("str, str"));
+                };
+                Value::Bool(if name == "contains" {
+                    s.contains(part.as_str())
+                } else {
+                    s.starts_with(part.as_str())
+                })
+            }
+            "substring" => {
+                let [s, start, end] = args.as_slice() else {
+                    return Err(count(3));
+                };
+                let (Value::Str(s), &Value::Int(start), &Value::Int(end)) = (s, start, end) else {
+                    return Err(types("str, int, int"));
+                };
+                let chars = s.chars().count();
+                let range = usize::try_from(start)
+                    .ok()
+                    .zip(usize::try_from(end).ok())
+                    .filter(|&(start, end)| start <= end && end <= chars);
+                let Some((start, end)) = range else {
+                    return Err(Trap::new(
+                        call.span,
+                        format!(
+                            "substring {start}..{end} is out of range for a string of \
+                             {chars} characters"
+                        ),
+                    ));
+                };
+                Value::Str(s.chars().skip(start).take(end - start).collect())
+            }
+            _ => {
+                return Err(Trap::new(
+                    call.callee.span,
+                    format!("unknown function `{name}`"),
+                ));
+            }
+        };
+        Ok(vec![value])
+    }
+
+    /// Whether the script or the host defines a function called `name`.
+    fn is_defined(&self, name: &str) -> bool {
+        self.fns.contains_key(name) || self.functions.contains_key(name)
+    }
+
+    /// `try_call(f(x))`: calls `f` if it exists, and is `none` without
+    /// evaluating the arguments if it does not.
+    fn try_call(&mut self, ast: &AstArena, call: &CallExpr) -> Exec<Vec<Value>> {
+        let inner = match call.args.as_slice() {
+            [arg] => match &ast[*arg] {
+                Expr::Call(inner) => inner,
+                _ => return Err(Trap::typed(ast[*arg].span(), "`try_call` expects a call").into()),
+            },
+            args => {
+                return Err(Trap::typed(
+                    call.span,
+                    format!("`try_call` takes 1 argument, but {} were given", args.len()),
+                )
+                .into());
+            }
+        };
+        if self.is_defined(&inner.callee.name) || BUILTINS.contains(&inner.callee.name.as_str()) {
+            self.call(ast, inner)
+        } else {
+            Ok(vec![Value::None])
+        }
+    }
+
+    fn call_fn(
+        &mut self,
+        ast: &AstArena,
+        f: &FnDecl,
+        call: &CallExpr,
+        args: Vec<Value>,
+    ) -> Exec<Vec<Value>> {
+        let name = &f.name.name;
+        if !(f.min_args()..=f.params.len()).contains(&args.len()) {
+            let expected = match (f.min_args(), f.params.len()) {
+                (_, 1) => "1 argument".to_string(),
+                (min, max) if min == max => format!("{max} arguments"),
+                (min, max) => format!("{min} to {max} arguments"),
+            };
+            return Err(Trap::typed(
+                call.span,
+                format!("`{name}` takes {expected}, but {} were given", args.len()),
+            )
+            .into());
+        }
+        if self.depth >= Self::MAX_CALL_DEPTH {
+            return Err(Trap::new(call.span, "call stack overflow").into());
+        }
+        let mut scope = HashMap::new();
+        let mut args = args.into_iter();
+        for (i, param) in f.params.iter().enumerate() {
+            // Parameters left out take their default, evaluated at the call.
+            let (arg, expr) = match (args.next(), param.default) {
+                (Some(arg), _) => (arg, call.args[i]),
+                (None, Some(default)) => (self.eval(ast, default)?, default),
+                (None, None) => unreachable!("checked against `min_args`"),
+            };
+            let arg = self.enum_from_int(ast, &param.ty, arg)?;
+            if self.check_type(&param.ty, &arg).is_err() {
+                return Err(Trap::typed(
+                    ast[expr].span(),
+                    format!(
+                        "parameter `{}` of `{name}` expects `{}`, found `{}`",
+                        param.name.name,
+                        type_text(&param.ty),
+                        arg.type_name()
+                    ),
+                )
+                .into());
+            }
+            scope.insert(param.name.name.clone(), arg);
+        }
+
+        let caller = std::mem::replace(&mut self.scopes, vec![scope]);
+        self.depth += 1;
+        let result = self.exec_stmts(ast, &f.body.stmts);
+        self.depth -= 1;
+        self.scopes = caller;
+        let values = body_result(ast, &f.body.stmts, result)?;
+
+        match &f.ret {
+            None | Some(TypeRef::Unit(_)) if values.is_empty() => Ok(values),
+            Some(TypeRef::Unit(span)) => Err(Trap::typed(
+                *span,
+                format!("`{name}` returned a value but its return type is `()`"),
+            )
+            .into()),
+            None => Err(Trap::typed(
+                f.name.span,
+                format!("`{name}` returned a value but declares no return type"),
+            )
+            .into()),
+            Some(ty) if values.is_empty() => Err(Trap::new(
+                ty.span(),
+                format!("`{name}` ended without returning `{}`", type_text(ty)),
+            )
+            .into()),
+            Some(ty) => {
+                let value = Value::from_values(values);
+                if self.check_type(ty, &value).is_err() {
+                    return Err(Trap::typed(
+                        ty.span(),
+                        format!(
+                            "`{name}` must return `{}`, but returned `{}`",
+                            type_text(ty),
+                            value.type_name()
+                        ),
+                    )
+                    .into());
+                }
+                Ok(match value {
+                    Value::Tuple(values) => values,
+                    value => vec![value],
+                })
+            }
+        }
+    }
+
+    /// Turns an `int` into the variant backed by it if `ty` names an enum,
+    /// as when a host hands over an enum. Anything else is left as it is.
+    fn enum_from_int(&self, ast: &AstArena, ty: &TypeRef, value: Value) -> Result<Value, Trap> {
+        let ty = match ty {
+            TypeRef::Optional(t) => &t.inner,
+            ty => ty,
+        };
+        let (TypeRef::Named(name), Value::Int(n)) = (ty, &value) else {
+            return Ok(value);
+        };
+        let Some(&decl) = self.enums.get(&name.name) else {
+            return Ok(value);
+        };
+        let Stmt::Enum(decl) = &ast[decl] else {
+            unreachable!("only enums are collected");
+        };
+        decl.variants
+            .iter()
+            .zip(decl.backing_values())
+            .find(|(_, v)| *v == Some(*n))
+            .map(|(variant, _)| {
+                Value::Enum(EnumValue {
+                    name: decl.name.name.clone(),
+                    variant: variant.name.name.clone(),
+                    value: *n,
+                })
+            })
+            .ok_or_else(|| {
+                Trap::new(
+                    name.span,
+                    format!("no variant of `{}` has the value {n}", decl.name.name),
+                )
+            })
+    }
+
+    /// Checks `value` against an annotation. Struct and enum names are known
+    /// from the running program.
+    fn check_type(&self, ty: &TypeRef, value: &Value) -> Result<(), Trap> {
+        let ok = match (ty, value) {
+            (TypeRef::Optional(_), Value::None) => true,
+            (TypeRef::Optional(t), _) => return self.check_type(&t.inner, value),
+            (TypeRef::Named(name), _) => match name.name.as_str() {
+                "int" | "uint" | "float" | "bool" | "str" => name.name == value.type_name(),
+                other if self.structs.contains_key(other) || self.enums.contains_key(other) => {
+                    other == value.type_name()
+                }
+                other => return Err(Trap::new(name.span, format!("unknown type `{other}`"))),
+            },
+            (TypeRef::Tuple(t), Value::Tuple(values)) if t.elems.len() == values.len() => {
+                for (elem, value) in t.elems.iter().zip(values) {
+                    self.check_type(elem, value)?;
+                }
+                true
+            }
+            (TypeRef::Tuple(_), _) => false,
+            // `()` is the type of no value, so no value has it.
+            (TypeRef::Unit(_), _) => false,
+            (TypeRef::Array(t), Value::Array(values)) => {
+                for value in values {
+                    self.check_type(&t.elem, value)?;
+                }
+                true
+            }
+            (TypeRef::Array(_), _) => false,
+            (TypeRef::Map(t), Value::Map(entries)) => {
+                for (key, value) in entries {
+                    let key = match key {
+                        MapKey::Int(v) => Value::Int(*v),
+                        MapKey::Str(v) => Value::Str(v.clone()),
+                    };
+                    self.check_type(&t.key, &key)?;
+                    self.check_type(&t.value, value)?;
+                }
+                true
+            }
+            (TypeRef::Map(_), _) => false,
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(Trap::typed(
+                ty.span(),
+                format!("value of type `{}` does not match", value.type_name()),
+            ))
+        }
+    }
+}
+
+/// What running a function or program body amounts to: the values of its
+/// `return`, or nothing if it runs off the end.
+fn body_result(
+    ast: &AstArena,
+    stmts: &[StmtId],
+    result: Exec<Option<Vec<Value>>>,
+) -> Exec<Vec<Value>> {
+    match result {
+        Ok(None) => Ok(Vec::new()),
+        Ok(Some(_)) => {
+            let span = find_yield(ast, stmts).unwrap_or_default();
+            Err(Trap::new(span, "`yield` outside an `if` expression").into())
+        }
+        Err(Exit::Return(values)) => Ok(values),
+        Err(trap) => Err(trap),
+    }
+}
+
+fn type_text(ty: &TypeRef) -> String {
+    match ty {
+        TypeRef::Named(name) => name.name.clone(),
+        TypeRef::Unit(_) => "()".to_string(),
+        TypeRef::Tuple(t) => {
+            let elems: Vec<String> = t.elems.iter().map(type_text).collect();
+            format!("({})", elems.join(", "))
+        }
+        TypeRef::Array(t) => format!("[{}]", type_text(&t.elem)),
+        TypeRef::Map(t) => format!("{{{}: {}}}", type_text(&t.key), type_text(&t.value)),
+        TypeRef::Optional(t) => format!("{}?", type_text(&t.inner)),
+    }
+}
+
+/// Turns `index` into a key of `entries`, which must be of the same type as
+/// the keys already there.
+fn map_key(entries: &BTreeMap<MapKey, Value>, index: Value, span: Span) -> Result<MapKey, Trap> {
+    let key = match index {
+        Value::Int(v) => MapKey::Int(v),
+        Value::Str(v) => MapKey::Str(v),
+        other => {
+            return Err(Trap::typed(
+                span,
+                format!(
+                    "map keys must be `str` or `int`, found `{}`",
+                    other.type_name()
+                ),
+            ));
+        }
+    };
+    if let Some(first) = entries.keys().next()
+        && std::mem::discriminant(first) != std::mem::discriminant(&key)
+    {
+        return Err(Trap::typed(
+            span,
+            format!(
+                "map keys must have one type: expected `{}`, found `{}`",
+                first.type_name(),
+                key.type_name()
+            ),
+        ));
+    }
+    Ok(key)
+}
+
+fn missing_key(key: &MapKey, span: Span) -> Trap {
+    Trap::new(span, format!("no entry for key `{key}`"))
+}
+
+/// The field `field` of the struct in `slot`.
+fn field_mut<'v>(slot: &'v mut Value, field: &Ident) -> Result<&'v mut Value, Trap> {
+    let Value::Struct(s) = slot else {
+        return Err(Trap::typed(
+            field.span,
+            format!(
+                "cannot access field `{}` of `{}`",
+                field.name,
+                slot.type_name()
+            ),
+        ));
+    };
+    let name = &s.name;
+    match s.fields.iter_mut().find(|(f, _)| *f == field.name) {
+        Some((_, value)) => Ok(value),
+        None => Err(no_field(name, field)),
+    }
+}
+
+fn no_field(struct_name: &str, field: &Ident) -> Trap {
+    Trap::typed(
+        field.span,
+        format!("struct `{struct_name}` has no field `{}`", field.name),
+    )
+}
+
+/// Checks `index` against the length of the array it indexes.
+fn element_index(len: usize, index: &Value, span: Span) -> Result<usize, Trap> {
+    let i = match *index {
+        Value::Int(i) => usize::try_from(i).ok(),
+        Value::UInt(i) => usize::try_from(i).ok(),
+        ref other => {
+            return Err(Trap::typed(
+                span,
+                format!(
+                    "array index must be `int` or `uint`, found `{}`",
+                    other.type_name()
+                ),
+            ));
+        }
+    };
+    i.filter(|&i| i < len).ok_or_else(|| {
+        Trap::new(
+            span,
+            format!("index {index} is out of bounds for an array of length {len}"),
+        )
+    })
+}
+
+fn parse_error(span: Span) -> Trap {
+    Trap::new(span, "reached code that failed to parse")
+}
+
+/// Finds the `yield` that escaped to the top level, for the trap's span.
+fn find_yield(ast: &AstArena, stmts: &[StmtId]) -> Option<Span> {
+    stmts.iter().find_map(|&stmt| match &ast[stmt] {
+        Stmt::Yield(s) => Some(s.span),
+        Stmt::If(s) => std::iter::once(&s.then_block)
+            .chain(s.elifs.iter().map(|e| &e.block))
+            .chain(&s.else_block)
+            .find_map(|block| find_yield(ast, &block.stmts)),
+        Stmt::While(WhileStmt { body, .. }) | Stmt::For(ForStmt { body, .. }) => {
+            find_yield(ast, &body.stmts)
+        }
+        _ => None,
+    })
+}
+
+fn describe_count(value: &Value) -> String {
+    match value {
+        Value::Tuple(values) => format!("{} values", values.len()),
+        other => format!("a single `{}`", other.type_name()),
+    }
+}
+
+fn int_value(lit: &IntLit) -> Result<Value, Trap> {
+    let out_of_range = |ty: &str| {
+        Trap::typed(
+            lit.span,
+            format!("literal `{}` does not fit in `{ty}`", lit.raw),
+        )
+    };
+    match lit.suffix {
+        None | Some(NumSuffix::I) => lit
+            .raw
+            .parse()
+            .map(Value::Int)
+            .map_err(|_| out_of_range("int")),
+        Some(NumSuffix::U) => lit
+            .raw
+            .parse()
+            .map(Value::UInt)
+            .map_err(|_| out_of_range("uint")),
+        Some(NumSuffix::F) => Ok(Value::Float(lit.raw.parse().unwrap_or(f64::INFINITY))),
+    }
+}
+
+/// The value of `-lit`, for an `int` literal.
+fn negative_int_value(lit: &IntLit) -> Result<Value, Trap> {
+    format!("-{}", lit.raw)
+        .parse()
+        .map(Value::Int)
+        .map_err(|_| {
+            Trap::typed(
+                lit.span,
+                format!("literal `-{}` does not fit in `int`", lit.raw),
+            )
+        })
+}
+
+fn float_value(lit: &FloatLit) -> Result<Value, Trap> {
+    match lit.suffix {
+        None | Some(NumSuffix::F) => Ok(Value::Float(lit.raw.parse().unwrap_or(f64::INFINITY))),
+        Some(suffix) => Err(Trap::typed(
+            lit.span,
+            format!("float literal cannot have suffix `{}`", suffix.as_str()),
+        )),
+    }
+}
+
+fn unary(op: UnaryOp, operand: Value, span: Span) -> Result<Value, Trap> {
+    match (op, operand) {
+        (UnaryOp::Neg, Value::Int(v)) => v
+            .checked_neg()
+            .map(Value::Int)
+            .ok_or_else(|| Trap::new(span, "integer overflow")),
+        (UnaryOp::Neg, Value::Float(v)) => Ok(Value::Float(-v)),
+        (UnaryOp::Not, Value::Bool(v)) => Ok(Value::Bool(!v)),
+        (op, operand) => Err(Trap::typed(
+            span,
+            format!(
+                "cannot apply `{}` to `{}`",
+                op.as_str(),
+                operand.type_name()
+            ),
+        )),
+    }
+}
+
+/// `value as ty`, by the rules on [`CastExpr`].
+fn cast(value: Value, ty: &TypeRef, span: Span) -> Result<Value, Trap> {
+    let target = type_text(ty);
+    let value = match value {
+        Value::Enum(e) => Value::Int(e.value),
+        value => value,
+    };
+    let converted = match (&value, target.as_str()) {
+        (Value::Int(v), "int") => Some(Value::Int(*v)),
+        (Value::Int(v), "uint") => u64::try_from(*v).ok().map(Value::UInt),
+        (Value::Int(v), "float") => Some(Value::Float(*v as f64)),
+        (Value::UInt(v), "int") => i64::try_from(*v).ok().map(Value::Int),
+        (Value::UInt(v), "uint") => Some(Value::UInt(*v)),
+        (Value::UInt(v), "float") => Some(Value::Float(*v as f64)),
+        // The bounds are powers of two, so exact as floats; NaN fails both.
+        (Value::Float(v), "int") => {
+            let v = v.trunc();
+            (v >= i64::MIN as f64 && v < -(i64::MIN as f64)).then_some(Value::Int(v as i64))
+        }
+        (Value::Float(v), "uint") => {
+            let v = v.trunc();
+            (v >= 0.0 && v < u64::MAX as f64).then_some(Value::UInt(v as u64))
+        }
+        (Value::Float(v), "float") => Some(Value::Float(*v)),
+        _ => {
+            return Err(Trap::typed(
+                span,
+                format!("cannot cast `{}` to `{target}`", value.type_name()),
+            ));
+        }
+    };
+    converted.ok_or_else(|| Trap::new(span, format!("`{value}` does not fit in `{target}`")))
+}
+
+fn binary(op: BinaryOp, lhs: Value, rhs: Value, span: Span) -> Result<Value, Trap> {
+    use std::cmp::Ordering;
+
+    let mismatch = |lhs: &Value, rhs: &Value| {
+        Trap::typed(
+            span,
+            format!(
+                "cannot apply `{}` to `{}` and `{}`",
+                op.as_str(),
+                lhs.type_name(),
+                rhs.type_name()
+            ),
+        )
+    };
+    if op.is_comparison() {
+        if !lhs.same_type(&rhs) {
+            return Err(mismatch(&lhs, &rhs));
+        }
+        let ordering = match (&lhs, &rhs) {
+            (Value::Int(a), Value::Int(b)) => a.partial_cmp(b),
+            (Value::UInt(a), Value::UInt(b)) => a.partial_cmp(b),
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+            _ => None,
+        };
+        let result = match op {
+            BinaryOp::Eq => lhs == rhs,
+            BinaryOp::Ne => lhs != rhs,
+            _ => {
+                let number =
+                    |v: &Value| matches!(v, Value::Int(_) | Value::UInt(_) | Value::Float(_));
+                if !number(&lhs) || !number(&rhs) {
+                    return Err(mismatch(&lhs, &rhs));
+                }
+                // NaN is unordered: every ordering comparison with it is false.
+                ordering.is_some_and(|o| match op {
+                    BinaryOp::Lt => o == Ordering::Less,
+                    BinaryOp::Le => o != Ordering::Greater,
+                    BinaryOp::Gt => o == Ordering::Greater,
+                    _ => o != Ordering::Less,
+                })
+            }
+        };
+        return Ok(Value::Bool(result));
+    }
+
+    let overflow = || Trap::new(span, "integer overflow");
+    let div_zero = || Trap::new(span, "division by zero");
+    match (lhs, rhs) {
+        (Value::Int(a), Value::Int(b)) => {
+            if matches!(op, BinaryOp::Div | BinaryOp::Rem) && b == 0 {
+                return Err(div_zero());
+            }
+            let result = match op {
+                BinaryOp::Add => a.checked_add(b),
+                BinaryOp::Sub => a.checked_sub(b),
+                BinaryOp::Mul => a.checked_mul(b),
+                BinaryOp::Div => a.checked_div(b),
+                _ => a.checked_rem(b),
+            };
+            result.map(Value::Int).ok_or_else(overflow)
+        }
+        (Value::UInt(a), Value::UInt(b)) => {
+            if matches!(op, BinaryOp::Div | BinaryOp::Rem) && b == 0 {
+                return Err(div_zero());
+            }
+            let result = match op {
+                BinaryOp::Add => a.checked_add(b),
+                BinaryOp::Sub => a.checked_sub(b),
+                BinaryOp::Mul => a.checked_mul(b),
+                BinaryOp::Div => a.checked_div(b),
+                _ => a.checked_rem(b),
+            };
+            result.map(Value::UInt).ok_or_else(overflow)
+        }
+        (Value::Str(a), Value::Str(b)) if op == BinaryOp::Add => Ok(Value::Str(a + &b)),
+        (Value::Float(a), Value::Float(b)) => Ok(Value::Float(match op {
+            BinaryOp::Add => a + b,
+            BinaryOp::Sub => a - b,
+            BinaryOp::Mul => a * b,
+            BinaryOp::Div => a / b,
+            _ => a % b,
+        })),
+        (lhs, rhs) => Err(mismatch(&lhs, &rhs)),
+    }
+}
@@ -0,0 +1,91 @@
+//! Token classification for syntax highlighting, and delimiter pairs for
+//! folding and bracket matching.
+
+use crate::lexer::{Lexer, tokenize};
+use crate::line_map::Lines;
+use crate::span::Span;
+use crate::token::TokenKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighlightKind {
+    Keyword,
+    Ident,
+    Number,
+    String,
+    Comment,
+    Operator,
+    /// Brackets, `#`, `,`, `;` and `:`.
+    Punctuation,
+}
+
+impl HighlightKind {
+    pub fn of(kind: &TokenKind) -> Option<HighlightKind> {
+        Some(match kind {
+            TokenKind::Ident(_) => HighlightKind::Ident,
+            TokenKind::Int { .. } | TokenKind::Float { .. } => HighlightKind::Number,
+            TokenKind::Str(_) => HighlightKind::String,
+            TokenKind::Comment(_) => HighlightKind::Comment,
+            k if k.is_keyword() => HighlightKind::Keyword,
+            TokenKind::LParen
+            | TokenKind::RParen
+            | TokenKind::LBrace
+            | TokenKind::RBrace
+            | TokenKind::LBracket
+            | TokenKind::RBracket
+            | TokenKind::Hash
+            | TokenKind::Comma
+            | TokenKind::Semi
+            | TokenKind::Colon => HighlightKind::Punctuation,
+            TokenKind::Whitespace(_)
+            | TokenKind::Unknown(_)
+            | TokenKind::Newline
+            | TokenKind::Eof => return None,
+            _ => HighlightKind::Operator,
+        })
+    }
+}
+
+/// Classifies every token of `lines`, in source order. Whitespace and
+/// characters the lexer rejects are not included.
+pub fn highlight(lines: &Lines) -> Vec<(Span, HighlightKind)> {
+    let mut lexer = Lexer::new(lines.config()).with_trivia(true);
+    let mut tokens = Vec::new();
+    for line in lines {
+        lexer.lex_line(line, &mut tokens);
+    }
+    tokens
+        .iter()
+        .filter_map(|t| Some((t.span, HighlightKind::of(&t.kind)?)))
+        .collect()
+}
+
+/// The spans of every matched pair of `{}`, `()` and `[]` in `lines`, as
+/// `(open, close)`, ordered by the opening delimiter.
+///
+/// Pairs are matched on tokens alone, so the result is useful while the text
+/// does not parse. A closing delimiter closes the nearest open one of its
+/// kind, dropping any unclosed delimiters opened after that; a closing
+/// delimiter with nothing to close is skipped. Editors fold the pairs whose
+/// delimiters are on different lines.
+pub fn block_ranges(lines: &Lines) -> Vec<(Span, Span)> {
+    let mut open: Vec<(TokenKind, Span)> = Vec::new();
+    let mut pairs = Vec::new();
+    for token in tokenize(lines).tokens {
+        let opener = match token.kind {
+            TokenKind::LBrace | TokenKind::LParen | TokenKind::LBracket => {
+                open.push((token.kind, token.span));
+                continue;
+            }
+            TokenKind::RBrace => TokenKind::LBrace,
+            TokenKind::RParen => TokenKind::LParen,
+            TokenKind::RBracket => TokenKind::LBracket,
+            _ => continue,
+        };
+        if let Some(idx) = open.iter().rposition(|(kind, _)| *kind == opener) {
+            pairs.push((open[idx].1, token.span));
+            open.truncate(idx);
+        }
+    }
+    pairs.sort_by_key(|(open, _)| open.start);
+    pairs
+}
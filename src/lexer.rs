@@ -0,0 +1,369 @@
+//! Turns [`Lines`] into [`Token`]s.
+//!
+//! Lexing is done one line at a time: no token spans a line boundary, and the
+//! end of every line is reported as a virtual [`TokenKind::Newline`].
+
+use crate::cursor::Cursor;
+use crate::diagnostic::Diagnostic;
+use crate::line_map::{DirtyLines, Line, Lines, SourceConfig};
+use crate::span::{Loc, Span};
+use crate::token::{NumSuffix, Token, TokenKind};
+
+/// Tokens of a source file together with the problems found while lexing.
+#[derive(Debug, Clone, Default)]
+pub struct LexResult {
+    pub tokens: Vec<Token>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Lexes a whole file. The returned tokens always end with [`TokenKind::Eof`].
+pub fn tokenize(lines: &Lines) -> LexResult {
+    Lexer::new(lines.config()).tokenize(lines)
+}
+
+fn eat_text(cursor: &mut Cursor<'_>, mut pred: impl FnMut(char) -> bool) -> String {
+    let mut text = String::new();
+    while let Some(c) = cursor.peek().filter(|&c| pred(c)) {
+        text.push(c);
+        cursor.bump();
+    }
+    text
+}
+
+/// Like [`tokenize`], but lexes chunks of lines on separate threads and
+/// stitches the results together. Since no token crosses a line boundary the
+/// output is identical to [`tokenize`].
+///
+/// Only worth it for large files; small inputs are lexed on the calling
+/// thread.
+pub fn tokenize_parallel(lines: &Lines) -> LexResult {
+    const MIN_CHUNK: usize = 4096;
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = lines.len().div_ceil(threads).max(MIN_CHUNK);
+    if lines.len() <= chunk {
+        return tokenize(lines);
+    }
+    let config = lines.config();
+    let parts: Vec<(Vec<Token>, Vec<Diagnostic>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..lines.len())
+            .step_by(chunk)
+            .map(|start| {
+                scope.spawn(move || {
+                    let mut lexer = Lexer::new(config);
+                    let mut tokens = Vec::new();
+                    for line in lines.iter().skip(start).take(chunk) {
+                        lexer.lex_line(line, &mut tokens);
+                    }
+                    (tokens, lexer.diagnostics)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("lexer thread panicked"))
+            .collect()
+    });
+    let mut result = LexResult::default();
+    for (tokens, diagnostics) in parts {
+        result.tokens.extend(tokens);
+        result.diagnostics.extend(diagnostics);
+    }
+    let eof = Lexer::new(config).end_loc(lines);
+    result
+        .tokens
+        .push(Token::new(TokenKind::Eof, Span::point(eof)));
+    result
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Lexer {
+    config: SourceConfig,
+    trivia: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Lexer {
+    pub fn new(config: SourceConfig) -> Self {
+        Self {
+            config,
+            trivia: false,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// In trivia mode whitespace, comments and rejected characters are
+    /// emitted as [`TokenKind::Whitespace`], [`TokenKind::Comment`] and
+    /// [`TokenKind::Unknown`] tokens instead of being skipped, so the tokens
+    /// cover every character of the line.
+    pub fn with_trivia(mut self, trivia: bool) -> Self {
+        self.trivia = trivia;
+        self
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+
+    /// Lexes every line of `lines`, like [`tokenize`] but with this lexer's
+    /// settings.
+    pub fn tokenize(mut self, lines: &Lines) -> LexResult {
+        let mut tokens = Vec::new();
+        for line in lines {
+            self.lex_line(line, &mut tokens);
+        }
+        tokens.push(Token::new(TokenKind::Eof, Span::point(self.end_loc(lines))));
+        LexResult {
+            tokens,
+            diagnostics: self.diagnostics,
+        }
+    }
+
+    /// Lexes one line, appending its tokens (ending with a newline) to `out`.
+    pub fn lex_line(&mut self, line: Line<'_>, out: &mut Vec<Token>) {
+        let mut cursor = Cursor::new(line, self.config);
+        loop {
+            let start = cursor.loc();
+            let Some(c) = cursor.peek() else {
+                out.push(Token::new(TokenKind::Newline, Span::point(start)));
+                return;
+            };
+            let trivia = if c.is_whitespace() {
+                let text = eat_text(&mut cursor, char::is_whitespace);
+                Some(TokenKind::Whitespace(text))
+            } else if c == '/' && cursor.peek2() == Some('/') {
+                Some(TokenKind::Comment(eat_text(&mut cursor, |_| true)))
+            } else {
+                None
+            };
+            if let Some(kind) = trivia {
+                if self.trivia {
+                    out.push(Token::new(kind, Span::new(start, cursor.loc())));
+                }
+                continue;
+            }
+            let from = cursor.byte_col();
+            match self.lex_token(&mut cursor) {
+                Some(kind) => out.push(Token::new(kind, Span::new(start, cursor.loc()))),
+                None if self.trivia => {
+                    let text = line.content[from..cursor.byte_col()].to_string();
+                    out.push(Token::new(
+                        TokenKind::Unknown(text),
+                        Span::new(start, cursor.loc()),
+                    ));
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Brings `result` up to date after [`Lines::apply_edit`] reported
+    /// `dirty`: only the dirty lines are lexed again, tokens and diagnostics
+    /// of later lines are moved to their new line numbers.
+    pub fn relex_dirty(lines: &Lines, result: &mut LexResult, dirty: DirtyLines) {
+        let shift = |loc: &mut Loc| {
+            if loc.line >= dirty.old_end {
+                loc.line = loc.line + dirty.new_end - dirty.old_end;
+            }
+        };
+        let in_dirty = |span: &Span| (dirty.start..dirty.old_end).contains(&span.start.line);
+
+        result.tokens.pop_if(|t| t.kind == TokenKind::Eof);
+        let from = result
+            .tokens
+            .partition_point(|t| t.span.start.line < dirty.start);
+        let to = result
+            .tokens
+            .partition_point(|t| t.span.start.line < dirty.old_end);
+        let mut lexer = Lexer::new(lines.config());
+        let mut fresh = Vec::new();
+        for idx in dirty.start..dirty.new_end {
+            if let Some(line) = lines.get(idx) {
+                lexer.lex_line(line, &mut fresh);
+            }
+        }
+        for token in &mut result.tokens[to..] {
+            shift(&mut token.span.start);
+            shift(&mut token.span.end);
+        }
+        result.tokens.splice(from..to, fresh);
+        let eof = Span::point(lexer.end_loc(lines));
+        result.tokens.push(Token::new(TokenKind::Eof, eof));
+
+        result.diagnostics.retain(|d| !in_dirty(&d.span));
+        for d in &mut result.diagnostics {
+            shift(&mut d.span.start);
+            shift(&mut d.span.end);
+        }
+        result.diagnostics.extend(lexer.diagnostics);
+        result.diagnostics.sort_by_key(|d| d.span.start);
+    }
+
+    fn end_loc(&self, lines: &Lines) -> Loc {
+        match lines.iter().last() {
+            Some(line) => {
+                let mut cursor = Cursor::new(line, self.config);
+                cursor.eat_while(|_| true);
+                cursor.loc()
+            }
+            None => Default::default(),
+        }
+    }
+
+    fn lex_token(&mut self, cursor: &mut Cursor<'_>) -> Option<TokenKind> {
+        let start = cursor.loc();
+        let c = cursor.bump()?;
+        let kind = match c {
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            '{' => TokenKind::LBrace,
+            '}' => TokenKind::RBrace,
+            '[' => TokenKind::LBracket,
+            ']' => TokenKind::RBracket,
+            '#' => TokenKind::Hash,
+            ',' => TokenKind::Comma,
+            ';' => TokenKind::Semi,
+            ':' => self.pick(cursor, ':', TokenKind::ColonColon, TokenKind::Colon),
+            '+' => TokenKind::Plus,
+            '-' => self.pick(cursor, '>', TokenKind::Arrow, TokenKind::Minus),
+            '*' => TokenKind::Star,
+            '/' => TokenKind::Slash,
+            '%' => TokenKind::Percent,
+            '=' => self.pick(cursor, '=', TokenKind::EqEq, TokenKind::Eq),
+            '!' => self.pick(cursor, '=', TokenKind::NotEq, TokenKind::Bang),
+            '<' => self.pick(cursor, '=', TokenKind::Le, TokenKind::Lt),
+            '>' => self.pick(cursor, '=', TokenKind::Ge, TokenKind::Gt),
+            '&' if cursor.peek() == Some('&') => {
+                cursor.bump();
+                TokenKind::AndAnd
+            }
+            '|' if cursor.peek() == Some('|') => {
+                cursor.bump();
+                TokenKind::OrOr
+            }
+            '.' => self.pick(cursor, '.', TokenKind::DotDot, TokenKind::Dot),
+            '?' => self.pick(
+                cursor,
+                '?',
+                TokenKind::QuestionQuestion,
+                TokenKind::Question,
+            ),
+            '"' => return self.lex_string(cursor, start),
+            c if c.is_ascii_digit() => self.lex_number(cursor, c),
+            c if c == '_' || c.is_alphabetic() => {
+                let mut name = String::from(c);
+                while let Some(c) = cursor.peek().filter(|&c| c == '_' || c.is_alphanumeric()) {
+                    name.push(c);
+                    cursor.bump();
+                }
+                TokenKind::keyword(&name).unwrap_or(TokenKind::Ident(name))
+            }
+            c => {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        Span::new(start, cursor.loc()),
+                        format!("unexpected character `{c}`"),
+                    )
+                    .with_code("E0101"),
+                );
+                return None;
+            }
+        };
+        Some(kind)
+    }
+
+    fn pick(
+        &self,
+        cursor: &mut Cursor<'_>,
+        next: char,
+        yes: TokenKind,
+        no: TokenKind,
+    ) -> TokenKind {
+        if cursor.peek() == Some(next) {
+            cursor.bump();
+            yes
+        } else {
+            no
+        }
+    }
+
+    fn lex_number(&mut self, cursor: &mut Cursor<'_>, first: char) -> TokenKind {
+        let mut raw = String::from(first);
+        while let Some(c) = cursor.peek().filter(char::is_ascii_digit) {
+            raw.push(c);
+            cursor.bump();
+        }
+        // `1.5` is a float, but the `.` in `1.foo` or `1..` is not ours.
+        let is_float =
+            cursor.peek() == Some('.') && cursor.peek2().is_some_and(|c| c.is_ascii_digit());
+        if is_float {
+            raw.push('.');
+            cursor.bump();
+            while let Some(c) = cursor.peek().filter(char::is_ascii_digit) {
+                raw.push(c);
+                cursor.bump();
+            }
+        }
+        // Whether a suffix fits the literal (`10.5u`) is decided by the type
+        // checker; the lexer only records it.
+        let suffix = match cursor.peek() {
+            Some('u') => Some(NumSuffix::U),
+            Some('i') => Some(NumSuffix::I),
+            Some('f') => Some(NumSuffix::F),
+            _ => None,
+        };
+        let ident_follows = cursor
+            .peek2()
+            .is_some_and(|c| c == '_' || c.is_alphanumeric());
+        let suffix = suffix.filter(|_| !ident_follows);
+        if suffix.is_some() {
+            cursor.bump();
+        }
+        if is_float {
+            TokenKind::Float { raw, suffix }
+        } else {
+            TokenKind::Int { raw, suffix }
+        }
+    }
+
+    fn lex_string(&mut self, cursor: &mut Cursor<'_>, start: Loc) -> Option<TokenKind> {
+        let mut value = String::new();
+        loop {
+            let esc_start = cursor.loc();
+            match cursor.bump() {
+                Some('"') => return Some(TokenKind::Str(value)),
+                Some('\\') => match cursor.bump() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('r') => value.push('\r'),
+                    Some('0') => value.push('\0'),
+                    Some('\\') => value.push('\\'),
+                    Some('"') => value.push('"'),
+                    Some(c) => self.diagnostics.push(
+                        Diagnostic::error(
+                            Span::new(esc_start, cursor.loc()),
+                            format!("unknown escape `\\{c}`"),
+                        )
+                        .with_code("E0103"),
+                    ),
+                    None => {}
+                },
+                Some(c) => value.push(c),
+                None => {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            Span::new(start, cursor.loc()),
+                            "unterminated string literal",
+                        )
+                        .with_code("E0102")
+                        .with_note("string literals cannot span multiple lines"),
+                    );
+                    return Some(TokenKind::Str(value));
+                }
+            }
+        }
+    }
+}
@@ -1 +1,81 @@
+//! Shallows: a small, statically typed scripting language for game behaviour
+//! scripts (`.ss` files).
+//!
+//! # Evaluation order
+//!
+//! Expressions are evaluated left to right, each operand completely before
+//! the next, and embedders may rely on this to order the side effects of host
+//! calls:
+//!
+//! - a call's arguments, in order, before the call; defaults for missing
+//!   arguments after the given ones;
+//! - the left operand of a binary operator before the right one, except that
+//!   `&&`, `||` and `??` skip the right operand when the left one decides
+//!   the result;
+//! - an indexed value before its index;
+//! - the values of `return` and `yield`, and the elements of array, map and
+//!   struct literals, in source order, each map key before its value;
+//! - in an assignment, the indices on the left, in order, before the value.
+//!
+//! Optimisations must keep this order for anything with an observable effect.
+//!
+//! # Concurrency
+//!
+//! The frontend has no shared state: each pass takes what it reads as
+//! arguments and returns what it produces, with no globals, thread-locals,
+//! interners or id counters behind it. Independent compilations can run on
+//! different threads without locks, and what they produce can be sent
+//! between threads.
+//!
+//! # Unsafe code
+//!
+//! The crate is safe Rust, and `unsafe` code is denied. The one exception is
+//! the `mmap` feature: mapping a file is inherently unsafe, and enabling it
+//! is the embedder's opt-in.
 
+#![deny(unsafe_code)]
+
+pub mod analysis;
+pub mod ast1;
+pub mod ast2;
+pub mod codes;
+pub mod cst;
+pub mod cursor;
+pub mod diagnostic;
+pub mod draft;
+pub mod highlight;
+pub mod lexer;
+pub mod line_map;
+pub mod parser;
+pub mod pass1;
+pub mod plugin;
+pub mod resolve;
+pub mod scope;
+pub mod span;
+pub mod spec;
+pub mod telemetry;
+pub mod token;
+pub mod typeck;
+pub mod visit;
+
+pub use analysis::{Analysis, AnalysisHost, CancellationToken, Cancelled, PendingAnalysis};
+pub use codes::explain;
+pub use cursor::Cursor;
+pub use diagnostic::{Diagnostic, Diagnostics, Label, Severity};
+pub use highlight::{HighlightKind, block_ranges, highlight};
+pub use lexer::{LexResult, Lexer, tokenize, tokenize_parallel};
+pub use line_map::{DirtyLines, Line, LineReader, Lines, SourceConfig};
+pub use parser::{Fragment, ParseResult, Parser, parse, parse_expr, parse_stmt};
+pub use plugin::{Plugin, Plugins, Stage};
+pub use span::{Loc, Span};
+pub use token::{NumSuffix, Token, TokenKind};
+pub use visit::{Visitor, VisitorMut};
+
+// Batch compilers and the editor service move compilation results into
+// worker threads; keep them free of `Rc`/`RefCell` and friends.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ast1::Program>();
+    assert_send_sync::<ast2::Program>();
+    assert_send_sync::<Diagnostics>();
+};
@@ -0,0 +1,377 @@
+//! Source text split into lines.
+//!
+//! The lexer works line by line; a [`Lines`] holds the full text of one source
+//! file, the byte range of every line in it, and the settings that control how
+//! positions within lines are measured. Every line of the file is kept,
+//! including blank ones, so `Line::idx` and the `line` of every
+//! [`Loc`] always equal the zero-based line number in the original
+//! file.
+
+use std::fs;
+use std::io::{self, BufRead};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::span::{Loc, Span};
+
+/// Settings for measuring positions in source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceConfig {
+    /// Width of a tab stop, used for display columns. A tab advances the
+    /// display column to the next multiple of this value.
+    pub tab_width: usize,
+}
+
+impl Default for SourceConfig {
+    fn default() -> Self {
+        Self { tab_width: 4 }
+    }
+}
+
+/// One line of source, without its line terminator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Line<'a> {
+    /// Zero-based line number in the original file.
+    pub idx: usize,
+    pub content: &'a str,
+}
+
+/// The lines of one source file.
+#[derive(Debug, Clone, Default)]
+pub struct Lines {
+    path: Option<PathBuf>,
+    source: Source,
+    /// Byte range of each line's content within `source`.
+    ranges: Vec<Range<usize>>,
+    config: SourceConfig,
+}
+
+impl Lines {
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut lines = Self::from_string(fs::read_to_string(path)?);
+        lines.path = Some(path.to_path_buf());
+        Ok(lines)
+    }
+
+    /// Reads all of `reader`, e.g. stdin or an asset pipeline stream.
+    pub fn from_reader(mut reader: impl BufRead) -> io::Result<Self> {
+        let mut source = String::new();
+        reader.read_to_string(&mut source)?;
+        Ok(Self::from_string(source))
+    }
+
+    pub fn from_string(source: String) -> Self {
+        Self::with_source(Source::Owned(source))
+    }
+
+    /// Maps the file into memory instead of reading it. Lines borrow straight
+    /// from the mapping, so even very large generated scripts are never
+    /// copied.
+    ///
+    /// The file must not be modified while the returned `Lines` is alive.
+    #[cfg(feature = "mmap")]
+    #[allow(unsafe_code)]
+    pub fn from_path_mmap(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = fs::File::open(path)?;
+        // SAFETY: the mapping is read-only; callers promise not to modify the
+        // file while it is mapped (see above).
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        std::str::from_utf8(&map).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut lines = Self::with_source(Source::Mapped(std::sync::Arc::new(map)));
+        lines.path = Some(path.to_path_buf());
+        Ok(lines)
+    }
+
+    fn with_source(source: Source) -> Self {
+        let ranges = line_ranges(source.as_str());
+        Self {
+            path: None,
+            source,
+            ranges,
+            config: SourceConfig::default(),
+        }
+    }
+
+    /// Sets the path reported in diagnostics for sources that were not read
+    /// from a file.
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn with_config(mut self, config: SourceConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn config(&self) -> SourceConfig {
+        self.config
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The complete text the lines were read from.
+    pub fn source(&self) -> &str {
+        self.source.as_str()
+    }
+
+    /// The line with zero-based number `idx`.
+    pub fn get(&self, idx: usize) -> Option<Line<'_>> {
+        let range = self.ranges.get(idx)?;
+        Some(Line {
+            idx,
+            content: &self.source()[range.clone()],
+        })
+    }
+
+    /// Byte offset of the start of line `idx` within [`source`](Self::source).
+    pub fn line_start(&self, idx: usize) -> Option<usize> {
+        self.ranges.get(idx).map(|r| r.start)
+    }
+
+    /// Byte offset of `loc` within [`source`](Self::source). Columns are
+    /// counted in characters, so this walks the line; `None` if `loc` lies
+    /// outside the text.
+    pub fn byte_offset(&self, loc: Loc) -> Option<usize> {
+        let range = self.ranges.get(loc.line)?;
+        let content = &self.source()[range.clone()];
+        let within = match content.char_indices().nth(loc.col) {
+            Some((i, _)) => i,
+            None if content.chars().count() == loc.col => content.len(),
+            None => return None,
+        };
+        Some(range.start + within)
+    }
+
+    /// Replaces the text covered by `span` with `new_text` and reports which
+    /// lines changed, for use with
+    /// [`Lexer::relex_dirty`](crate::Lexer::relex_dirty).
+    ///
+    /// `span` may end at the start of the line just past the last one, to
+    /// append at the end of the file. Returns `None` (leaving the text
+    /// untouched) if `span` does not lie within the text.
+    pub fn apply_edit(&mut self, span: Span, new_text: &str) -> Option<DirtyLines> {
+        let start = self.edit_offset(span.start)?;
+        let end = self.edit_offset(span.end)?;
+        if start > end {
+            return None;
+        }
+        let old_len = self.len();
+        let mut source = std::mem::take(&mut self.source).into_string();
+        source.replace_range(start..end, new_text);
+        self.ranges = line_ranges(&source);
+        self.source = Source::Owned(source);
+
+        let first = span.start.line.min(old_len);
+        let old_end = (span.end.line + 1).min(old_len).max(first);
+        // Lines after the edited region are unchanged, only renumbered.
+        let new_end = self.len() - (old_len - old_end);
+        Some(DirtyLines {
+            start: first,
+            old_end,
+            new_end,
+        })
+    }
+
+    fn edit_offset(&self, loc: Loc) -> Option<usize> {
+        if loc.line == self.len() && loc.col == 0 {
+            return Some(self.source().len());
+        }
+        self.byte_offset(loc)
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            lines: self,
+            next: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+}
+
+/// The lines touched by an edit: `start..old_end` before the edit became
+/// `start..new_end` after it. Lines from `old_end` on moved by
+/// `new_end - old_end` but are otherwise unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirtyLines {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+/// Storage for the text behind a [`Lines`].
+#[derive(Debug, Clone)]
+enum Source {
+    Owned(String),
+    /// A memory-mapped file, checked to be UTF-8 when it was mapped.
+    #[cfg(feature = "mmap")]
+    Mapped(std::sync::Arc<memmap2::Mmap>),
+}
+
+impl Source {
+    #[cfg_attr(feature = "mmap", allow(unsafe_code))]
+    fn as_str(&self) -> &str {
+        match self {
+            Source::Owned(s) => s,
+            // SAFETY: validated as UTF-8 in `Lines::from_path_mmap`.
+            #[cfg(feature = "mmap")]
+            Source::Mapped(map) => unsafe { std::str::from_utf8_unchecked(map) },
+        }
+    }
+
+    fn into_string(self) -> String {
+        match self {
+            Source::Owned(s) => s,
+            #[cfg(feature = "mmap")]
+            Source::Mapped(_) => self.as_str().to_string(),
+        }
+    }
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Source::Owned(String::new())
+    }
+}
+
+/// Splits `source` at `\n`, `\r\n` and lone `\r`. Terminators are not part
+/// of any line, so Windows and classic Mac files produce the same lines and
+/// columns as Unix ones. A terminator at the very end of the text does not
+/// start another line.
+fn line_ranges(source: &str) -> Vec<Range<usize>> {
+    let bytes = source.as_bytes();
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' => {
+                ranges.push(start..i);
+                start = i + 1;
+            }
+            b'\r' => {
+                ranges.push(start..i);
+                if bytes.get(i + 1) == Some(&b'\n') {
+                    i += 1;
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if start < source.len() {
+        ranges.push(start..source.len());
+    }
+    ranges
+}
+
+/// Reads lines from a [`BufRead`] one at a time, with the same terminator
+/// handling as [`Lines`]: `\n`, `\r\n` and lone `\r` all end a line and are
+/// stripped.
+///
+/// Useful for line-oriented input such as a REPL reading stdin, where the
+/// whole source is not available up front.
+pub struct LineReader<R> {
+    reader: R,
+    /// A `\r` ended the previous line; skip a directly following `\n`.
+    after_cr: bool,
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            after_cr: false,
+        }
+    }
+
+    /// Reads the next line, or `None` at end of input.
+    pub fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = Vec::new();
+        let mut read_any = false;
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                break;
+            }
+            if std::mem::take(&mut self.after_cr) && buf[0] == b'\n' {
+                self.reader.consume(1);
+                continue;
+            }
+            read_any = true;
+            match buf.iter().position(|&b| b == b'\n' || b == b'\r') {
+                Some(end) => {
+                    line.extend_from_slice(&buf[..end]);
+                    self.after_cr = buf[end] == b'\r';
+                    self.reader.consume(end + 1);
+                    return utf8(line).map(Some);
+                }
+                None => {
+                    let n = buf.len();
+                    line.extend_from_slice(buf);
+                    self.reader.consume(n);
+                }
+            }
+        }
+        if read_any {
+            utf8(line).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+fn utf8(bytes: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+impl<R: BufRead> Iterator for LineReader<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        self.read_line().transpose()
+    }
+}
+
+pub struct Iter<'a> {
+    lines: &'a Lines,
+    next: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Line<'a>;
+
+    fn next(&mut self) -> Option<Line<'a>> {
+        let line = self.lines.get(self.next)?;
+        self.next += 1;
+        Some(line)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.lines.len() - self.next;
+        (n, Some(n))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl<'a> IntoIterator for &'a Lines {
+    type Item = Line<'a>;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
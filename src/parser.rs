@@ -0,0 +1,1516 @@
+//! Recursive-descent parser producing [`ast1`](crate::ast1).
+//!
+//! Grammar (statements end in `;`, line breaks are insignificant):
+//!
+//! ```text
+//! program  = stmt* EOF
+//! stmt     = attr* "let" "mut"? pattern (":" type)? "=" expr ";"
+//!          | attr* "const" IDENT (":" type)? "=" expr ";"
+//!          | attr* "fn" IDENT "(" params? ")" ("->" type)? block
+//!          | attr* "struct" IDENT "{" fields? "}"
+//!          | attr* "enum" IDENT "{" variants? "}"
+//!          | IDENT ("[" expr "]" | "." IDENT)* "=" expr ";"
+//!          | "return" exprs? ";"
+//!          | "yield" exprs? ";"
+//!          | "if" expr block ("elif" expr block)* ("else" block)?
+//!          | "while" expr block
+//!          | "for" IDENT "in" range block
+//!          | expr ";"
+//! attr     = "#" "[" IDENT ("(" exprs? ")")? "]"
+//! params   = param ("," param)* ","?
+//! param    = IDENT ":" type ("=" expr)?
+//! fields   = IDENT ":" type ("," IDENT ":" type)* ","?
+//! variants = IDENT ("=" INT)? ("," IDENT ("=" INT)?)* ","?
+//! block    = "{" stmt* "}"
+//! exprs    = expr ("," expr)* ","?
+//! pattern  = IDENT ("," IDENT)* ","?
+//! type     = (IDENT | "(" ")" | "(" type ("," type)+ ","? ")" | "[" type "]"
+//!          | "{" type ":" type "}") "?"*
+//! range    = expr ".." expr
+//!
+//! expr     = logic
+//! logic    = compare (("&&" | "||") compare)*
+//! compare  = coalesce (("==" | "!=" | "<" | "<=" | ">" | ">=") coalesce)?
+//! coalesce = sum ("??" sum)*
+//! sum      = product (("+" | "-") product)*
+//! product  = cast (("*" | "/" | "%") cast)*
+//! cast     = unary ("as" type)*
+//! unary    = ("-" | "!") unary | postfix
+//! postfix  = primary ("[" expr "]" | "." IDENT)*
+//! primary  = INT | FLOAT | STRING | "true" | "false" | "none"
+//!          | IDENT ("(" exprs? ")")?
+//!          | IDENT "::" IDENT
+//!          | IDENT "{" (IDENT ":" expr ("," IDENT ":" expr)* ","?)? "}"
+//!          | "(" expr ")"
+//!          | "[" exprs? "]"
+//!          | "{" (expr ":" expr ("," expr ":" expr)* ","?)? "}"
+//!          | "if" expr block ("elif" expr block)* ("else" block)?
+//! ```
+//!
+//! `if` at the start of a statement is an [`IfStmt`]; anywhere else it is an
+//! [`IfExpr`] whose branches `yield` its value. In the condition of `if`,
+//! `elif` and `while` and the range of `for`, a `{` starts the block, so a
+//! map or struct literal there must be parenthesized.
+//!
+//! So precedence, from tightest: call, indexing and field access, unary,
+//! `as`, `* / %`, `+ -`, `??`, comparisons, `&& ||`. Binary operators are
+//! left-associative; `&&` and `||` share a level, and comparisons do not
+//! chain (`a < b < c` is an error).
+//!
+//! Every comma-separated list accepts a trailing comma, as formatters and
+//! generated scripts tend to emit them.
+
+use crate::ast1::*;
+use crate::cst::{Event, SyntaxKind};
+use crate::diagnostic::{Diagnostic, Diagnostics};
+use crate::lexer::tokenize;
+use crate::line_map::Lines;
+use crate::span::Span;
+use crate::token::{Token, TokenKind};
+
+type PResult<T> = Result<T, Box<Diagnostic>>;
+
+/// A best-effort program together with everything reported while lexing
+/// and parsing it.
+///
+/// The program is always present. Source that failed to parse shows up as
+/// [`Stmt::Error`] and [`Expr::Error`] nodes, so tools such as an editor
+/// service can still work with the rest of the file.
+#[derive(Debug, Clone)]
+pub struct ParseResult {
+    pub program: Program,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseResult {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    /// The program if it parsed without errors, otherwise the diagnostics.
+    pub fn into_result(self) -> Result<Program, Vec<Diagnostic>> {
+        if self.has_errors() {
+            Err(self.diagnostics)
+        } else {
+            Ok(self.program)
+        }
+    }
+}
+
+/// Parses a whole program. Syntax errors do not stop the parse: the parser
+/// skips to the next statement and carries on, so every error in the file is
+/// reported.
+pub fn parse(lines: &Lines) -> ParseResult {
+    let lexed = tokenize(lines);
+    let mut diagnostics = Diagnostics::new();
+    diagnostics.extend(lexed.diagnostics);
+    let mut parser = Parser::new(lexed.tokens).with_diagnostics(diagnostics);
+    let program = parser.parse_program();
+    ParseResult {
+        program,
+        diagnostics: parser.into_diagnostics().into_vec(),
+    }
+}
+
+/// A parsed piece of a program: its root node and the arena holding it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fragment<T> {
+    pub arena: AstArena,
+    pub root: T,
+}
+
+/// Parses `source` as a single expression, e.g. a REPL line or a fuzz
+/// input. Anything after the expression is an error.
+pub fn parse_expr(source: &str) -> Result<Fragment<ExprId>, Vec<Diagnostic>> {
+    parse_fragment(source, Parser::parse_expr)
+}
+
+/// Parses `source` as a single statement, including its `;`. Anything after
+/// the statement is an error.
+pub fn parse_stmt(source: &str) -> Result<Fragment<StmtId>, Vec<Diagnostic>> {
+    parse_fragment(source, Parser::parse_stmt)
+}
+
+fn parse_fragment<T>(
+    source: &str,
+    f: impl FnOnce(&mut Parser) -> PResult<T>,
+) -> Result<Fragment<T>, Vec<Diagnostic>> {
+    let lexed = tokenize(&Lines::from_string(source.to_string()));
+    let mut diagnostics = Diagnostics::new();
+    diagnostics.extend(lexed.diagnostics);
+    let mut parser = Parser::new(lexed.tokens).with_diagnostics(diagnostics);
+    let result = f(&mut parser).and_then(|node| {
+        parser.expect(&TokenKind::Eof)?;
+        Ok(node)
+    });
+    match result {
+        Ok(root) if !parser.diagnostics.has_errors() => Ok(Fragment {
+            arena: parser.arena,
+            root,
+        }),
+        Ok(_) => Err(parser.into_diagnostics().into_vec()),
+        Err(d) => {
+            parser.diagnostics.push(*d);
+            Err(parser.into_diagnostics().into_vec())
+        }
+    }
+}
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    diagnostics: Diagnostics,
+    /// Nodes parsed so far.
+    arena: AstArena,
+    /// Current nesting of expressions and blocks, see [`Parser::with_max_depth`].
+    depth: usize,
+    max_depth: usize,
+    /// Tree-building events, recorded only when building a CST.
+    events: Option<Vec<Event>>,
+    /// Whether a `{` in expression position starts a map literal; false in
+    /// conditions, where it starts the block. See [`Parser::parse_cond`].
+    brace_literals: bool,
+}
+
+impl Parser {
+    /// Nesting limit used unless [`with_max_depth`](Self::with_max_depth)
+    /// says otherwise.
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+    /// Creates a parser over lexer output. Newlines and trivia are dropped;
+    /// the token list must end with [`TokenKind::Eof`].
+    pub fn new(tokens: Vec<Token>) -> Self {
+        let tokens: Vec<Token> = tokens
+            .into_iter()
+            .filter(|t| t.kind != TokenKind::Newline && !t.kind.is_trivia())
+            .collect();
+        assert!(
+            tokens.last().is_some_and(|t| t.kind == TokenKind::Eof),
+            "token stream must end with Eof"
+        );
+        Self {
+            tokens,
+            pos: 0,
+            diagnostics: Diagnostics::new(),
+            arena: AstArena::new(),
+            depth: 0,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            events: None,
+            brace_literals: true,
+        }
+    }
+
+    /// Reports into `diagnostics` instead of a fresh sink, e.g. to keep
+    /// lexer errors and an error limit.
+    pub fn with_diagnostics(mut self, diagnostics: Diagnostics) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    /// Limits how deeply expressions and blocks may nest. The parser is
+    /// recursive, so without a limit input like `((((...))))` could overflow
+    /// the native stack; past the limit it reports an error instead.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Records the events [`cst::parse`](crate::cst::parse) builds its tree
+    /// from.
+    pub(crate) fn with_events(mut self) -> Self {
+        self.events = Some(Vec::new());
+        self
+    }
+
+    pub(crate) fn take_events(&mut self) -> Vec<Event> {
+        self.events.take().unwrap_or_default()
+    }
+
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    pub fn into_diagnostics(self) -> Diagnostics {
+        self.diagnostics
+    }
+
+    /// Parses statements up to the end of input. Statements with syntax
+    /// errors are reported and become [`Stmt::Error`].
+    pub fn parse_program(&mut self) -> Program {
+        let mut stmts = Vec::new();
+        while !self.at(&TokenKind::Eof) {
+            stmts.push(self.parse_stmt_recovering());
+        }
+        Program {
+            arena: std::mem::take(&mut self.arena),
+            stmts,
+        }
+    }
+
+    // ---- error recovery ----
+
+    /// Parses a statement; on error, reports it and skips to where the next
+    /// statement probably starts.
+    fn parse_stmt_recovering(&mut self) -> StmtId {
+        let before = self.pos;
+        let m = self.mark();
+        let arena_mark = self.arena.mark();
+        match self.parse_stmt() {
+            Ok(stmt) => stmt,
+            Err(d) => {
+                // Drop the pieces parsed before the error.
+                self.arena.truncate(arena_mark);
+                self.recover(*d);
+                if self.pos == before {
+                    // Nothing was consumed (e.g. a stray `}`); skip the
+                    // offending token so parsing makes progress.
+                    self.bump();
+                }
+                self.node(m, SyntaxKind::Error);
+                let span = self.span_since(before);
+                self.arena.alloc_stmt(Stmt::Error(ErrorNode { span }))
+            }
+        }
+    }
+
+    /// Reports `d`, synchronizes, and poisons the skipped range.
+    fn recover(&mut self, d: Diagnostic) {
+        let from = d.span;
+        self.diagnostics.push(d);
+        self.synchronize();
+        let to = self.tokens[self.pos.saturating_sub(1)].span;
+        let skipped = if to < from { from } else { from.to(to) };
+        self.diagnostics.poison(skipped);
+    }
+
+    /// The span of the tokens consumed since position `before`, or an empty
+    /// span just after the previous token if there are none.
+    fn span_since(&self, before: usize) -> Span {
+        match (before.checked_sub(1), self.pos > before) {
+            (_, true) => self.tokens[before].span.to(self.tokens[self.pos - 1].span),
+            (Some(prev), false) => Span::point(self.tokens[prev].span.end),
+            (None, false) => Span::point(Default::default()),
+        }
+    }
+
+    /// Skips tokens up to a synchronization point: just past a `;`, or
+    /// before a `}` or a keyword that starts a statement. Nested `{ }` are
+    /// skipped as a whole.
+    fn synchronize(&mut self) {
+        loop {
+            match self.peek().kind {
+                TokenKind::Eof | TokenKind::RBrace => return,
+                TokenKind::Let
+                | TokenKind::Const
+                | TokenKind::Fn
+                | TokenKind::Struct
+                | TokenKind::Enum
+                | TokenKind::Hash
+                | TokenKind::If
+                | TokenKind::While
+                | TokenKind::For
+                | TokenKind::Return
+                | TokenKind::Yield => return,
+                TokenKind::Semi => {
+                    self.bump();
+                    return;
+                }
+                TokenKind::LBrace => self.skip_braces(),
+                _ => {
+                    self.bump();
+                }
+            }
+        }
+    }
+
+    fn skip_braces(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.bump().kind {
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                TokenKind::Eof => return,
+                _ => {}
+            }
+        }
+    }
+
+    /// Expects the `;` ending a statement. A missing `;` before a token on a
+    /// later line is reported but otherwise treated as present, since it is
+    /// by far the most common slip and the next line is usually fine.
+    fn expect_semi(&mut self) -> PResult<()> {
+        if self.eat(&TokenKind::Semi).is_some() {
+            return Ok(());
+        }
+        let err = self.expect(&TokenKind::Semi).unwrap_err();
+        let prev_line = self.tokens[self.pos.saturating_sub(1)].span.end.line;
+        if self.peek().span.start.line > prev_line && self.peek().kind != TokenKind::Eof {
+            self.diagnostics.push(*err);
+            Ok(())
+        } else {
+            Err(err)
+        }
+    }
+
+    /// Runs `f` one nesting level deeper, or fails if that would exceed the
+    /// limit.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> PResult<T>) -> PResult<T> {
+        self.scoped(|p| {
+            p.deeper()?;
+            f(p)
+        })
+    }
+
+    /// Runs `f`, then restores the nesting level to what it was before, so
+    /// `f` can go [`deeper`](Self::deeper) once per node it folds.
+    fn scoped<T>(&mut self, f: impl FnOnce(&mut Self) -> PResult<T>) -> PResult<T> {
+        let outer = self.depth;
+        let result = f(self);
+        self.depth = outer;
+        result
+    }
+
+    /// Goes one nesting level deeper, or fails if that would exceed the
+    /// limit. Loops that fold a chain like `a + b + c` into nested nodes
+    /// count each link, since later passes walk the chain recursively.
+    fn deeper(&mut self) -> PResult<()> {
+        if self.depth >= self.max_depth {
+            return Err(
+                Diagnostic::error(self.peek().span, "expression too deeply nested")
+                    .with_code("E0208")
+                    .with_note(format!(
+                        "at most {} levels of nesting are allowed",
+                        self.max_depth
+                    ))
+                    .into(),
+            );
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// An expression followed by a block, as in `if`, `while` and `for`.
+    /// A `{` there starts the block, so map literals need parentheses.
+    fn parse_cond(&mut self) -> PResult<ExprId> {
+        self.with_braces(false, Self::parse_expr)
+    }
+
+    /// Runs `f` with map literals allowed or not, as inside delimiters or a
+    /// condition.
+    fn with_braces<T>(
+        &mut self,
+        allowed: bool,
+        f: impl FnOnce(&mut Self) -> PResult<T>,
+    ) -> PResult<T> {
+        let outer = std::mem::replace(&mut self.brace_literals, allowed);
+        let result = f(self);
+        self.brace_literals = outer;
+        result
+    }
+
+    /// Whether the tokens ahead are `IDENT ("[" ... "]" | "." IDENT)* "="`,
+    /// the start of an assignment. Brackets are matched without parsing what
+    /// is inside them.
+    fn at_assignment(&self) -> bool {
+        let mut n = 1;
+        loop {
+            match self.peek_nth(n).kind {
+                TokenKind::LBracket => {}
+                TokenKind::Dot if matches!(self.peek_nth(n + 1).kind, TokenKind::Ident(_)) => {
+                    n += 2;
+                    continue;
+                }
+                _ => break,
+            }
+            let mut depth = 0usize;
+            loop {
+                match self.peek_nth(n).kind {
+                    TokenKind::LBracket => depth += 1,
+                    TokenKind::RBracket => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    TokenKind::Eof => return false,
+                    _ => {}
+                }
+                n += 1;
+            }
+            n += 1;
+        }
+        self.peek_nth(n).kind == TokenKind::Eq
+    }
+
+    // ---- concrete syntax ----
+
+    /// Marks where a CST node may start; see [`node`](Self::node).
+    fn mark(&mut self) -> usize {
+        match &mut self.events {
+            Some(events) => {
+                events.push(Event::TOMBSTONE);
+                events.len() - 1
+            }
+            None => 0,
+        }
+    }
+
+    /// Wraps everything recorded since `mark` in a node of `kind`. Marks
+    /// stay valid while inner nodes are added, so the same mark can wrap a
+    /// node repeatedly, as for left-associative operators.
+    ///
+    /// The event log is only appended to: the first node at a mark fills in
+    /// the start recorded there, and each further one starts at the end and
+    /// is linked as the forward parent of the node it wraps.
+    fn node(&mut self, mark: usize, kind: SyntaxKind) {
+        let Some(events) = &mut self.events else {
+            return;
+        };
+        let mut start = mark;
+        while let Event::Start {
+            forward_parent: Some(distance),
+            ..
+        } = events[start]
+        {
+            start += distance;
+        }
+        let end = events.len();
+        match &mut events[start] {
+            Event::Start {
+                kind: slot @ None, ..
+            } => *slot = Some(kind),
+            Event::Start { forward_parent, .. } => {
+                *forward_parent = Some(end - start);
+                events.push(Event::Start {
+                    kind: Some(kind),
+                    forward_parent: None,
+                });
+            }
+            _ => unreachable!("a mark points at a start"),
+        }
+        events.push(Event::Finish);
+    }
+
+    // ---- token helpers ----
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn peek_nth(&self, n: usize) -> &Token {
+        let last = self.tokens.len() - 1;
+        &self.tokens[(self.pos + n).min(last)]
+    }
+
+    fn at(&self, kind: &TokenKind) -> bool {
+        &self.peek().kind == kind
+    }
+
+    fn bump(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+            if let Some(events) = &mut self.events {
+                events.push(Event::Token);
+            }
+        }
+        token
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> Option<Token> {
+        self.at(kind).then(|| self.bump())
+    }
+
+    fn expect(&mut self, kind: &TokenKind) -> PResult<Token> {
+        if self.at(kind) {
+            return Ok(self.bump());
+        }
+        let found = self.peek();
+        Err(
+            Diagnostic::error(found.span, format!("expected {kind}, found {}", found.kind))
+                .with_code("E0201")
+                .into(),
+        )
+    }
+
+    fn expect_ident(&mut self, what: &str, code: &'static str) -> PResult<Ident> {
+        let token = self.peek();
+        match &token.kind {
+            TokenKind::Ident(name) => {
+                let ident = Ident {
+                    name: name.clone(),
+                    span: token.span,
+                };
+                self.bump();
+                Ok(ident)
+            }
+            other => Err(
+                Diagnostic::error(token.span, format!("expected {what}, found {other}"))
+                    .with_code(code)
+                    .into(),
+            ),
+        }
+    }
+
+    // ---- statements ----
+
+    fn parse_stmt(&mut self) -> PResult<StmtId> {
+        let start = self.pos;
+        let m = self.mark();
+        match &self.peek().kind {
+            TokenKind::Let
+            | TokenKind::Const
+            | TokenKind::Fn
+            | TokenKind::Struct
+            | TokenKind::Enum
+            | TokenKind::Hash => {
+                let attrs = self.parse_attrs()?;
+                match &self.peek().kind {
+                    TokenKind::Let => self.parse_let(m, start, attrs),
+                    TokenKind::Const => self.parse_const(m, start, attrs),
+                    TokenKind::Fn => self.parse_fn(m, start, attrs),
+                    TokenKind::Struct => self.parse_struct(m, start, attrs),
+                    TokenKind::Enum => self.parse_enum(m, start, attrs),
+                    kind => Err(Diagnostic::error(
+                        self.peek().span,
+                        format!("expected declaration after attributes, found {kind}"),
+                    )
+                    .with_code("E0209")
+                    .with_label(attrs[0].span, "attributes start here")
+                    .into()),
+                }
+            }
+            TokenKind::Return => {
+                self.bump();
+                let values = self.parse_values()?;
+                self.expect_semi()?;
+                self.node(m, SyntaxKind::ReturnStmt);
+                Ok(self.arena.alloc_stmt(Stmt::Return(ReturnStmt {
+                    values,
+                    span: self.span_since(start),
+                })))
+            }
+            TokenKind::Yield => {
+                self.bump();
+                let values = self.parse_values()?;
+                self.expect_semi()?;
+                self.node(m, SyntaxKind::YieldStmt);
+                Ok(self.arena.alloc_stmt(Stmt::Yield(YieldStmt {
+                    values,
+                    span: self.span_since(start),
+                })))
+            }
+            TokenKind::If => {
+                let stmt = self.parse_if()?;
+                self.node(m, SyntaxKind::IfStmt);
+                Ok(self.arena.alloc_stmt(Stmt::If(stmt)))
+            }
+            TokenKind::While => {
+                self.bump();
+                let cond = self.parse_cond()?;
+                let body = self.parse_block()?;
+                self.node(m, SyntaxKind::WhileStmt);
+                Ok(self.arena.alloc_stmt(Stmt::While(WhileStmt {
+                    cond,
+                    body,
+                    span: self.span_since(start),
+                })))
+            }
+            TokenKind::For => {
+                self.bump();
+                let var = self.expect_ident("loop variable", "E0201")?;
+                self.expect(&TokenKind::In)?;
+                let range = self.parse_range()?;
+                let body = self.parse_block()?;
+                self.node(m, SyntaxKind::ForStmt);
+                Ok(self.arena.alloc_stmt(Stmt::For(ForStmt {
+                    var,
+                    range,
+                    body,
+                    span: self.span_since(start),
+                })))
+            }
+            TokenKind::Ident(_) if self.at_assignment() => {
+                let target = self.expect_ident("identifier", "E0201")?;
+                let mut path = Vec::new();
+                loop {
+                    if self.eat(&TokenKind::LBracket).is_some() {
+                        path.push(Projection::Index(self.parse_expr()?));
+                        self.expect(&TokenKind::RBracket)?;
+                    } else if self.eat(&TokenKind::Dot).is_some() {
+                        path.push(Projection::Field(self.expect_ident("field name", "E0201")?));
+                    } else {
+                        break;
+                    }
+                }
+                self.expect(&TokenKind::Eq)?;
+                let value = self.parse_expr()?;
+                self.expect_semi()?;
+                self.node(m, SyntaxKind::AssignStmt);
+                Ok(self.arena.alloc_stmt(Stmt::Assign(AssignStmt {
+                    target,
+                    path,
+                    value,
+                    span: self.span_since(start),
+                })))
+            }
+            kind if can_begin_expr(kind) => {
+                let expr = self.parse_expr()?;
+                self.expect_semi()?;
+                self.node(m, SyntaxKind::ExprStmt);
+                Ok(self.arena.alloc_stmt(Stmt::Expr(ExprStmt {
+                    expr,
+                    span: self.span_since(start),
+                })))
+            }
+            kind => Err(Diagnostic::error(
+                self.peek().span,
+                format!("expected statement, found {kind}"),
+            )
+            .with_code("E0203")
+            .into()),
+        }
+    }
+
+    /// Attributes in front of a declaration, possibly none.
+    fn parse_attrs(&mut self) -> PResult<Vec<Attribute>> {
+        let mut attrs = Vec::new();
+        while self.at(&TokenKind::Hash) {
+            let m = self.mark();
+            let start = self.pos;
+            self.bump();
+            self.expect(&TokenKind::LBracket)?;
+            let name = self.expect_ident("attribute name", "E0201")?;
+            let args = if self.at(&TokenKind::LParen) {
+                self.parse_args()?
+            } else {
+                Vec::new()
+            };
+            self.expect(&TokenKind::RBracket)?;
+            self.node(m, SyntaxKind::Attribute);
+            attrs.push(Attribute {
+                name,
+                args,
+                span: self.span_since(start),
+            });
+        }
+        Ok(attrs)
+    }
+
+    /// `let` and what follows. `m` and `start` are where its attributes
+    /// began.
+    fn parse_let(&mut self, m: usize, start: usize, attrs: Vec<Attribute>) -> PResult<StmtId> {
+        self.expect(&TokenKind::Let)?;
+        let mutable = self.eat(&TokenKind::Mut).is_some();
+        let pattern = self.parse_pattern()?;
+        let ty = if self.eat(&TokenKind::Colon).is_some() {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        self.expect(&TokenKind::Eq)?;
+        // Keep the binding even if its initializer is broken, so later
+        // passes know the name exists.
+        let before = self.pos;
+        let value_mark = self.mark();
+        let arena_mark = self.arena.mark();
+        let value = match self.parse_expr() {
+            Ok(value) => {
+                self.expect_semi()?;
+                value
+            }
+            Err(d) => {
+                self.arena.truncate(arena_mark);
+                self.recover(*d);
+                self.node(value_mark, SyntaxKind::Error);
+                let span = self.span_since(before);
+                self.arena.alloc_expr(Expr::Error(ErrorNode { span }))
+            }
+        };
+        self.node(m, SyntaxKind::LetStmt);
+        Ok(self.arena.alloc_stmt(Stmt::Let(LetStmt {
+            attrs,
+            mutable,
+            pattern,
+            ty,
+            value,
+            span: self.span_since(start),
+        })))
+    }
+
+    /// `const` and what follows. `m` and `start` are where its attributes
+    /// began.
+    fn parse_const(&mut self, m: usize, start: usize, attrs: Vec<Attribute>) -> PResult<StmtId> {
+        self.expect(&TokenKind::Const)?;
+        let name = self.expect_ident("constant name", "E0201")?;
+        let ty = if self.eat(&TokenKind::Colon).is_some() {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        self.expect(&TokenKind::Eq)?;
+        let value = self.parse_expr()?;
+        self.expect_semi()?;
+        self.node(m, SyntaxKind::ConstDecl);
+        Ok(self.arena.alloc_stmt(Stmt::Const(ConstDecl {
+            attrs,
+            name,
+            ty,
+            value,
+            span: self.span_since(start),
+        })))
+    }
+
+    /// `fn` and what follows. `m` and `start` are where its attributes
+    /// began.
+    fn parse_fn(&mut self, m: usize, start: usize, attrs: Vec<Attribute>) -> PResult<StmtId> {
+        self.expect(&TokenKind::Fn)?;
+        let name = self.expect_ident("function name", "E0201")?;
+        self.expect(&TokenKind::LParen)?;
+        let mut params = Vec::new();
+        while !self.at(&TokenKind::RParen) {
+            let m = self.mark();
+            let param_start = self.pos;
+            let name = self.expect_ident("parameter name", "E0201")?;
+            self.expect(&TokenKind::Colon)?;
+            let ty = self.parse_type()?;
+            let default = if self.eat(&TokenKind::Eq).is_some() {
+                Some(self.parse_expr()?)
+            } else {
+                None
+            };
+            self.node(m, SyntaxKind::Param);
+            params.push(Param {
+                name,
+                ty,
+                default,
+                span: self.span_since(param_start),
+            });
+            if self.eat(&TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+        self.expect(&TokenKind::RParen)?;
+        let ret = if self.eat(&TokenKind::Arrow).is_some() {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        let body = self.parse_block()?;
+        self.node(m, SyntaxKind::FnDecl);
+        Ok(self.arena.alloc_stmt(Stmt::Fn(FnDecl {
+            attrs,
+            name,
+            params,
+            ret,
+            body,
+            span: self.span_since(start),
+        })))
+    }
+
+    /// `struct` and what follows. `m` and `start` are where its attributes
+    /// began.
+    fn parse_struct(&mut self, m: usize, start: usize, attrs: Vec<Attribute>) -> PResult<StmtId> {
+        self.expect(&TokenKind::Struct)?;
+        let name = self.expect_ident("struct name", "E0201")?;
+        self.expect(&TokenKind::LBrace)?;
+        let fields = self.braced(|p| {
+            let mut fields = Vec::new();
+            while !p.at(&TokenKind::RBrace) {
+                let m = p.mark();
+                let field_start = p.pos;
+                let name = p.expect_ident("field name", "E0201")?;
+                p.expect(&TokenKind::Colon)?;
+                let ty = p.parse_type()?;
+                p.node(m, SyntaxKind::FieldDecl);
+                fields.push(FieldDecl {
+                    name,
+                    ty,
+                    span: p.span_since(field_start),
+                });
+                if p.eat(&TokenKind::Comma).is_none() {
+                    break;
+                }
+            }
+            p.expect(&TokenKind::RBrace)?;
+            Ok(fields)
+        })?;
+        self.node(m, SyntaxKind::StructDecl);
+        Ok(self.arena.alloc_stmt(Stmt::Struct(StructDecl {
+            attrs,
+            name,
+            fields,
+            span: self.span_since(start),
+        })))
+    }
+
+    /// `enum` and what follows. `m` and `start` are where its attributes
+    /// began.
+    fn parse_enum(&mut self, m: usize, start: usize, attrs: Vec<Attribute>) -> PResult<StmtId> {
+        self.expect(&TokenKind::Enum)?;
+        let name = self.expect_ident("enum name", "E0201")?;
+        self.expect(&TokenKind::LBrace)?;
+        let variants = self.braced(|p| {
+            let mut variants = Vec::new();
+            while !p.at(&TokenKind::RBrace) {
+                let m = p.mark();
+                let variant_start = p.pos;
+                let name = p.expect_ident("variant name", "E0201")?;
+                let value = if p.eat(&TokenKind::Eq).is_some() {
+                    let token = p.peek().clone();
+                    let TokenKind::Int { raw, suffix } = token.kind else {
+                        return Err(Diagnostic::error(
+                            token.span,
+                            format!("expected integer literal, found {}", token.kind),
+                        )
+                        .with_code("E0201")
+                        .into());
+                    };
+                    p.bump();
+                    Some(IntLit {
+                        raw,
+                        suffix,
+                        span: token.span,
+                    })
+                } else {
+                    None
+                };
+                p.node(m, SyntaxKind::Variant);
+                variants.push(Variant {
+                    name,
+                    value,
+                    span: p.span_since(variant_start),
+                });
+                if p.eat(&TokenKind::Comma).is_none() {
+                    break;
+                }
+            }
+            p.expect(&TokenKind::RBrace)?;
+            Ok(variants)
+        })?;
+        self.node(m, SyntaxKind::EnumDecl);
+        Ok(self.arena.alloc_stmt(Stmt::Enum(EnumDecl {
+            attrs,
+            name,
+            variants,
+            span: self.span_since(start),
+        })))
+    }
+
+    fn parse_if(&mut self) -> PResult<IfStmt> {
+        let start = self.pos;
+        self.expect(&TokenKind::If)?;
+        let cond = self.parse_cond()?;
+        let then_block = self.parse_block()?;
+        let mut elifs = Vec::new();
+        loop {
+            let m = self.mark();
+            let elif = self.pos;
+            if self.eat(&TokenKind::Elif).is_none() {
+                break;
+            }
+            let cond = self.parse_cond()?;
+            let block = self.parse_block()?;
+            self.node(m, SyntaxKind::ElifBranch);
+            elifs.push(ElifBranch {
+                cond,
+                block,
+                span: self.span_since(elif),
+            });
+        }
+        let m = self.mark();
+        let else_block = match self.eat(&TokenKind::Else) {
+            Some(_) => {
+                let block = self.parse_block()?;
+                self.node(m, SyntaxKind::ElseBranch);
+                Some(block)
+            }
+            None => None,
+        };
+        Ok(IfStmt {
+            cond,
+            then_block,
+            elifs,
+            else_block,
+            span: self.span_since(start),
+        })
+    }
+
+    fn parse_range(&mut self) -> PResult<ExprId> {
+        let m = self.mark();
+        let start = self.pos;
+        let lo = self.parse_cond()?;
+        self.expect(&TokenKind::DotDot)?;
+        let hi = self.parse_cond()?;
+        self.node(m, SyntaxKind::RangeExpr);
+        Ok(self.arena.alloc_expr(Expr::Range(RangeExpr {
+            start: lo,
+            end: hi,
+            span: self.span_since(start),
+        })))
+    }
+
+    /// `{ stmt* }`. Statements may use map literals even when the block
+    /// belongs to an `if` expression inside a condition.
+    fn parse_block(&mut self) -> PResult<Block> {
+        self.with_braces(true, Self::parse_block_inner)
+    }
+
+    fn parse_block_inner(&mut self) -> PResult<Block> {
+        self.nested(|p| {
+            let m = p.mark();
+            let start = p.pos;
+            let open = p.expect(&TokenKind::LBrace)?.span;
+            let mut stmts = Vec::new();
+            while !p.at(&TokenKind::RBrace) {
+                if p.at(&TokenKind::Eof) {
+                    p.diagnostics.push(
+                        Diagnostic::error(p.peek().span, "expected `}`, found end of file")
+                            .with_code("E0201")
+                            .with_label(open, "block opened here"),
+                    );
+                    p.node(m, SyntaxKind::Block);
+                    return Ok(Block {
+                        stmts,
+                        span: p.span_since(start),
+                    });
+                }
+                stmts.push(p.parse_stmt_recovering());
+            }
+            p.bump();
+            p.node(m, SyntaxKind::Block);
+            Ok(Block {
+                stmts,
+                span: p.span_since(start),
+            })
+        })
+    }
+
+    /// Values of `return`/`yield`, possibly none.
+    fn parse_values(&mut self) -> PResult<Vec<ExprId>> {
+        let mut values = Vec::new();
+        if self.at(&TokenKind::Semi) {
+            return Ok(values);
+        }
+        values.push(self.parse_expr()?);
+        while self.eat(&TokenKind::Comma).is_some() && !self.at(&TokenKind::Semi) {
+            values.push(self.parse_expr()?);
+        }
+        Ok(values)
+    }
+
+    /// `x` or `x, y, ...`; a comma-separated list destructures a tuple. A
+    /// trailing comma is allowed, so `x,` is just `x`.
+    fn parse_pattern(&mut self) -> PResult<Pattern> {
+        let m = self.mark();
+        let first = self.expect_ident("pattern", "E0206")?;
+        let mut span = first.span;
+        let mut elems = vec![Pattern::Ident(first)];
+        while self.eat(&TokenKind::Comma).is_some()
+            && !self.at(&TokenKind::Eq)
+            && !self.at(&TokenKind::Colon)
+        {
+            let ident = self.expect_ident("pattern", "E0206")?;
+            span = span.to(ident.span);
+            elems.push(Pattern::Ident(ident));
+        }
+        self.node(m, SyntaxKind::Pattern);
+        if elems.len() == 1 {
+            return Ok(elems.pop().unwrap());
+        }
+        Ok(Pattern::Tuple(TuplePattern { elems, span }))
+    }
+
+    /// `name`, `(type, type, ...)` with at least two elements, `[type]` or
+    /// `{type: type}`.
+    fn parse_type(&mut self) -> PResult<TypeRef> {
+        self.nested(|p| {
+            let m = p.mark();
+            let mut ty = p.parse_base_type()?;
+            while p.at(&TokenKind::Question) {
+                p.deeper()?;
+                let question = p.bump();
+                p.node(m, SyntaxKind::OptionalType);
+                let span = ty.span().to(question.span);
+                ty = TypeRef::Optional(OptionalType {
+                    inner: Box::new(ty),
+                    span,
+                });
+            }
+            Ok(ty)
+        })
+    }
+
+    /// A type without `?` suffixes.
+    fn parse_base_type(&mut self) -> PResult<TypeRef> {
+        let m = self.mark();
+        if let Some(open) = self.eat(&TokenKind::LBrace) {
+            let (key, value, close) = self.braced(|p| {
+                let key = p.parse_type()?;
+                p.expect(&TokenKind::Colon)?;
+                let value = p.parse_type()?;
+                let close = p.expect(&TokenKind::RBrace)?;
+                Ok((key, value, close))
+            })?;
+            self.node(m, SyntaxKind::MapType);
+            return Ok(TypeRef::Map(MapType {
+                key: Box::new(key),
+                value: Box::new(value),
+                span: open.span.to(close.span),
+            }));
+        }
+        if let Some(open) = self.eat(&TokenKind::LBracket) {
+            let elem = self.parse_type()?;
+            let close = self.expect(&TokenKind::RBracket)?;
+            self.node(m, SyntaxKind::ArrayType);
+            return Ok(TypeRef::Array(ArrayType {
+                elem: Box::new(elem),
+                span: open.span.to(close.span),
+            }));
+        }
+        let Some(open) = self.eat(&TokenKind::LParen) else {
+            let name = self.expect_ident("type", "E0205")?;
+            self.node(m, SyntaxKind::NamedType);
+            return Ok(TypeRef::Named(name));
+        };
+        let mut elems = Vec::new();
+        if !self.at(&TokenKind::RParen) {
+            elems.push(self.parse_type()?);
+            while self.eat(&TokenKind::Comma).is_some() && !self.at(&TokenKind::RParen) {
+                elems.push(self.parse_type()?);
+            }
+        }
+        let close = self.expect(&TokenKind::RParen)?;
+        let span = open.span.to(close.span);
+        self.node(m, SyntaxKind::TupleType);
+        match &elems[..] {
+            [] => Ok(TypeRef::Unit(span)),
+            [single] => {
+                self.diagnostics.push(
+                    Diagnostic::error(span, "tuple types need at least two elements, found 1")
+                        .with_code("E0207")
+                        .with_note(
+                            "a single value needs no parentheses: write the type on its own",
+                        ),
+                );
+                Ok(single.clone())
+            }
+            _ => Ok(TypeRef::Tuple(TupleType { elems, span })),
+        }
+    }
+
+    // ---- expressions ----
+
+    pub fn parse_expr(&mut self) -> PResult<ExprId> {
+        self.parse_binary(Prec::Logic)
+    }
+
+    /// Precedence climbing: parses operands of at least `min` precedence and
+    /// folds operators of precedence `min` or tighter, left-associatively.
+    fn parse_binary(&mut self, min: Prec) -> PResult<ExprId> {
+        self.scoped(|p| {
+            let m = p.mark();
+            let start = p.pos;
+            let mut lhs = p.parse_cast()?;
+            while let Some(op) = binary_op(&p.peek().kind) {
+                let prec = Prec::of(op);
+                if prec < min {
+                    break;
+                }
+                p.deeper()?;
+                let op_span = p.bump().span;
+                let rhs = p.parse_binary(prec.next())?;
+                if op.is_comparison()
+                    && let Some(next) = binary_op(&p.peek().kind).filter(|op| op.is_comparison())
+                {
+                    return Err(Diagnostic::error(
+                        p.peek().span,
+                        "comparison operators cannot be chained",
+                    )
+                    .with_code("E0204")
+                    .with_label(op_span, format!("`{}` already compared here", op.as_str()))
+                    .with_note(format!(
+                        "split the comparison with `&&`, e.g. `a {} b && b {} c`",
+                        op.as_str(),
+                        next.as_str()
+                    ))
+                    .into());
+                }
+                p.node(m, SyntaxKind::BinaryExpr);
+                lhs = p.arena.alloc_expr(Expr::Binary(BinaryExpr {
+                    op,
+                    lhs,
+                    rhs,
+                    span: p.span_since(start),
+                }));
+            }
+            Ok(lhs)
+        })
+    }
+
+    fn parse_cast(&mut self) -> PResult<ExprId> {
+        self.scoped(|p| {
+            let m = p.mark();
+            let start = p.pos;
+            let mut expr = p.parse_unary()?;
+            while p.at(&TokenKind::As) {
+                p.deeper()?;
+                p.bump();
+                let ty = p.parse_type()?;
+                p.node(m, SyntaxKind::CastExpr);
+                expr = p.arena.alloc_expr(Expr::Cast(CastExpr {
+                    expr,
+                    ty,
+                    span: p.span_since(start),
+                }));
+            }
+            Ok(expr)
+        })
+    }
+
+    fn parse_unary(&mut self) -> PResult<ExprId> {
+        self.nested(|p| {
+            let m = p.mark();
+            let start = p.pos;
+            let op = match p.peek().kind {
+                TokenKind::Minus => UnaryOp::Neg,
+                TokenKind::Bang => UnaryOp::Not,
+                _ => return p.parse_primary(),
+            };
+            p.bump();
+            let operand = p.parse_unary()?;
+            p.node(m, SyntaxKind::UnaryExpr);
+            Ok(p.arena.alloc_expr(Expr::Unary(UnaryExpr {
+                op,
+                operand,
+                span: p.span_since(start),
+            })))
+        })
+    }
+
+    fn parse_primary(&mut self) -> PResult<ExprId> {
+        let m = self.mark();
+        if self.at(&TokenKind::If) {
+            let expr = self.parse_if()?;
+            self.node(m, SyntaxKind::IfExpr);
+            return Ok(self.arena.alloc_expr(Expr::If(expr.into())));
+        }
+        if !can_begin_expr(&self.peek().kind) {
+            let found = self.peek();
+            return Err(Diagnostic::error(
+                found.span,
+                format!("expected expression, found {}", found.kind),
+            )
+            .with_code("E0202")
+            .into());
+        }
+        if self.at(&TokenKind::LBrace) && !self.brace_literals {
+            return Err(
+                Diagnostic::error(self.peek().span, "expected expression, found `{`")
+                    .with_code("E0202")
+                    .with_note("a map or struct literal in a condition must be in parentheses")
+                    .into(),
+            );
+        }
+        let start = self.pos;
+        let token = self.bump();
+        let span = token.span;
+        let (expr, kind) = match token.kind {
+            TokenKind::Int { raw, suffix } => {
+                (Expr::Int(IntLit { raw, suffix, span }), SyntaxKind::Literal)
+            }
+            TokenKind::Float { raw, suffix } => (
+                Expr::Float(FloatLit { raw, suffix, span }),
+                SyntaxKind::Literal,
+            ),
+            TokenKind::Str(value) => (Expr::Str(StrLit { value, span }), SyntaxKind::Literal),
+            TokenKind::True => (
+                Expr::Bool(BoolLit { value: true, span }),
+                SyntaxKind::Literal,
+            ),
+            TokenKind::False => (
+                Expr::Bool(BoolLit { value: false, span }),
+                SyntaxKind::Literal,
+            ),
+            TokenKind::None => (Expr::None(NoneLit { span }), SyntaxKind::Literal),
+            TokenKind::Ident(name) => {
+                let ident = Ident { name, span };
+                if self.at(&TokenKind::LParen) {
+                    let args = self.parse_args()?;
+                    let call = CallExpr {
+                        callee: ident,
+                        args,
+                        span: self.span_since(start),
+                    };
+                    (Expr::Call(call), SyntaxKind::CallExpr)
+                } else if self.eat(&TokenKind::ColonColon).is_some() {
+                    let variant = self.expect_ident("variant name", "E0201")?;
+                    let path = VariantExpr {
+                        enum_name: ident,
+                        variant,
+                        span: self.span_since(start),
+                    };
+                    (Expr::Variant(path), SyntaxKind::VariantExpr)
+                } else if self.at(&TokenKind::LBrace) && self.brace_literals {
+                    self.bump();
+                    let fields = self.parse_field_inits()?;
+                    let lit = StructExpr {
+                        name: ident,
+                        fields,
+                        span: self.span_since(start),
+                    };
+                    (Expr::Struct(lit), SyntaxKind::StructExpr)
+                } else {
+                    (Expr::Ident(ident), SyntaxKind::NameRef)
+                }
+            }
+            TokenKind::LParen => {
+                let inner = self.with_braces(true, Self::parse_expr)?;
+                self.expect(&TokenKind::RParen)?;
+                (
+                    Expr::Paren(ParenExpr {
+                        inner,
+                        span: self.span_since(start),
+                    }),
+                    SyntaxKind::ParenExpr,
+                )
+            }
+            TokenKind::LBrace => {
+                let entries = self.parse_map_entries()?;
+                (
+                    Expr::Map(MapExpr {
+                        entries,
+                        span: self.span_since(start),
+                    }),
+                    SyntaxKind::MapExpr,
+                )
+            }
+            TokenKind::LBracket => {
+                let elems = self.parse_list(TokenKind::RBracket)?;
+                (
+                    Expr::Array(ArrayExpr {
+                        elems,
+                        span: self.span_since(start),
+                    }),
+                    SyntaxKind::ArrayExpr,
+                )
+            }
+            _ => unreachable!("checked by can_begin_expr"),
+        };
+        self.node(m, kind);
+        let mut expr = self.arena.alloc_expr(expr);
+        // Runs inside `parse_unary`'s nesting level, which is restored after.
+        loop {
+            if self.at(&TokenKind::LBracket) || self.at(&TokenKind::Dot) {
+                self.deeper()?;
+            }
+            if self.eat(&TokenKind::LBracket).is_some() {
+                let index = self.with_braces(true, Self::parse_expr)?;
+                self.expect(&TokenKind::RBracket)?;
+                self.node(m, SyntaxKind::IndexExpr);
+                expr = self.arena.alloc_expr(Expr::Index(IndexExpr {
+                    base: expr,
+                    index,
+                    span: self.span_since(start),
+                }));
+            } else if self.eat(&TokenKind::Dot).is_some() {
+                let field = self.expect_ident("field name", "E0201")?;
+                self.node(m, SyntaxKind::FieldExpr);
+                expr = self.arena.alloc_expr(Expr::Field(FieldExpr {
+                    base: expr,
+                    field,
+                    span: self.span_since(start),
+                }));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// `(expr, ...)`, as for calls and attributes.
+    fn parse_args(&mut self) -> PResult<Vec<ExprId>> {
+        self.expect(&TokenKind::LParen)?;
+        self.parse_list(TokenKind::RParen)
+    }
+
+    /// `expr, ...` up to and including `close`, the opening delimiter
+    /// already consumed.
+    fn parse_list(&mut self, close: TokenKind) -> PResult<Vec<ExprId>> {
+        self.with_braces(true, |p| {
+            let mut elems = Vec::new();
+            if !p.at(&close) {
+                elems.push(p.parse_expr()?);
+                while p.eat(&TokenKind::Comma).is_some() && !p.at(&close) {
+                    elems.push(p.parse_expr()?);
+                }
+            }
+            p.expect(&close)?;
+            Ok(elems)
+        })
+    }
+
+    /// `key: value, ...}`, the `{` already consumed.
+    fn parse_map_entries(&mut self) -> PResult<Vec<MapEntry>> {
+        self.braced(|p| {
+            p.with_braces(true, |p| {
+                let mut entries = Vec::new();
+                while !p.at(&TokenKind::RBrace) {
+                    let m = p.mark();
+                    let start = p.pos;
+                    let key = p.parse_expr()?;
+                    p.expect(&TokenKind::Colon)?;
+                    let value = p.parse_expr()?;
+                    p.node(m, SyntaxKind::MapEntry);
+                    entries.push(MapEntry {
+                        key,
+                        value,
+                        span: p.span_since(start),
+                    });
+                    if p.eat(&TokenKind::Comma).is_none() {
+                        break;
+                    }
+                }
+                p.expect(&TokenKind::RBrace)?;
+                Ok(entries)
+            })
+        })
+    }
+
+    /// `field: value, ...}`, the `{` already consumed.
+    fn parse_field_inits(&mut self) -> PResult<Vec<FieldInit>> {
+        self.braced(|p| {
+            p.with_braces(true, |p| {
+                let mut fields = Vec::new();
+                while !p.at(&TokenKind::RBrace) {
+                    let m = p.mark();
+                    let start = p.pos;
+                    let name = p.expect_ident("field name", "E0201")?;
+                    p.expect(&TokenKind::Colon)?;
+                    let value = p.parse_expr()?;
+                    p.node(m, SyntaxKind::FieldInit);
+                    fields.push(FieldInit {
+                        name,
+                        value,
+                        span: p.span_since(start),
+                    });
+                    if p.eat(&TokenKind::Comma).is_none() {
+                        break;
+                    }
+                }
+                p.expect(&TokenKind::RBrace)?;
+                Ok(fields)
+            })
+        })
+    }
+
+    /// Runs `f` on what follows a `{` that `f` is to close. If it fails,
+    /// skips past the `}`, so recovery does not take it for the end of a
+    /// block.
+    fn braced<T>(&mut self, f: impl FnOnce(&mut Self) -> PResult<T>) -> PResult<T> {
+        let result = f(self);
+        if result.is_err() {
+            self.skip_braced_rest();
+        }
+        result
+    }
+
+    /// Skips up to and including the `}` closing the braces the parser is
+    /// in. Stops early at a `;`, which cannot be inside a literal,
+    /// declaration or type in braces.
+    fn skip_braced_rest(&mut self) {
+        let mut depth = 1usize;
+        loop {
+            match self.peek().kind {
+                TokenKind::Semi | TokenKind::Eof => return,
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.bump();
+                        return;
+                    }
+                }
+                _ => {}
+            }
+            self.bump();
+        }
+    }
+}
+
+/// Binary operator precedence levels, loosest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Prec {
+    Logic,
+    Compare,
+    Coalesce,
+    Sum,
+    Product,
+    /// Above every binary operator; only used as the "next" of `Product`.
+    Unary,
+}
+
+impl Prec {
+    fn of(op: BinaryOp) -> Prec {
+        match op {
+            BinaryOp::And | BinaryOp::Or => Prec::Logic,
+            BinaryOp::Add | BinaryOp::Sub => Prec::Sum,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => Prec::Product,
+            BinaryOp::Coalesce => Prec::Coalesce,
+            _ => Prec::Compare,
+        }
+    }
+
+    fn next(self) -> Prec {
+        match self {
+            Prec::Logic => Prec::Compare,
+            Prec::Compare => Prec::Coalesce,
+            Prec::Coalesce => Prec::Sum,
+            Prec::Sum => Prec::Product,
+            Prec::Product | Prec::Unary => Prec::Unary,
+        }
+    }
+}
+
+pub(crate) fn binary_op(kind: &TokenKind) -> Option<BinaryOp> {
+    Some(match kind {
+        TokenKind::Plus => BinaryOp::Add,
+        TokenKind::Minus => BinaryOp::Sub,
+        TokenKind::Star => BinaryOp::Mul,
+        TokenKind::Slash => BinaryOp::Div,
+        TokenKind::Percent => BinaryOp::Rem,
+        TokenKind::EqEq => BinaryOp::Eq,
+        TokenKind::NotEq => BinaryOp::Ne,
+        TokenKind::Lt => BinaryOp::Lt,
+        TokenKind::Le => BinaryOp::Le,
+        TokenKind::Gt => BinaryOp::Gt,
+        TokenKind::Ge => BinaryOp::Ge,
+        TokenKind::AndAnd => BinaryOp::And,
+        TokenKind::OrOr => BinaryOp::Or,
+        TokenKind::QuestionQuestion => BinaryOp::Coalesce,
+        _ => return None,
+    })
+}
+
+fn can_begin_expr(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Int { .. }
+            | TokenKind::Float { .. }
+            | TokenKind::Str(_)
+            | TokenKind::True
+            | TokenKind::False
+            | TokenKind::None
+            | TokenKind::Ident(_)
+            | TokenKind::LParen
+            | TokenKind::LBracket
+            | TokenKind::LBrace
+            | TokenKind::Minus
+            | TokenKind::Bang
+            | TokenKind::If
+    )
+}
@@ -0,0 +1,702 @@
+//! Pass 1: structural rules checked on [`ast1`](crate::ast1) before
+//! lowering.
+//!
+//! These are rules the grammar accepts but the language does not, and that
+//! need no name resolution or types to decide. Error nodes are skipped; their
+//! problems have already been reported.
+//!
+//! Script functions, structs and enums are the exception that proves the
+//! rule: they can only be declared at the top level, and a script function
+//! takes precedence over everything else a call could mean, so calls, struct
+//! literals and enum variants can be checked against their declarations here.
+//! Constants are top-level only too, which is what lets their initializers
+//! be checked for using nothing but literals and earlier constants.
+//!
+//! Control flow needs neither: a function with a return type must return on
+//! every path, every branch of an `if` used as a value must yield on every
+//! path, and statements no path reaches are reported.
+//!
+//! Locals are left to [`resolve`](crate::resolve), which knows what every
+//! name refers to: it reports assignments to locals that are not `mut` and
+//! locals that are never read.
+
+use std::collections::HashMap;
+
+use crate::ast1::*;
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+use crate::visit::{self, Visitor};
+
+/// Checks `program` and returns everything found.
+pub fn check(program: &Program) -> Vec<Diagnostic> {
+    let mut pass = Pass1 {
+        diagnostics: Vec::new(),
+        fns: HashMap::new(),
+        structs: HashMap::new(),
+        enums: HashMap::new(),
+        consts: HashMap::new(),
+        all_consts: HashMap::new(),
+        block_depth: 0,
+        current_fn: None,
+    };
+    for &stmt in program.stmts.iter().rev() {
+        if let Stmt::Const(c) = &program.arena[stmt] {
+            pass.all_consts.insert(c.name.name.as_str(), c);
+        }
+    }
+    for &stmt in &program.stmts {
+        match &program.arena[stmt] {
+            Stmt::Fn(f) => {
+                if let Some(first) = pass.fns.get(f.name.name.as_str()) {
+                    pass.diagnostics.push(
+                        Diagnostic::error(
+                            f.name.span,
+                            format!("function `{}` is declared twice", f.name.name),
+                        )
+                        .with_code("E0305")
+                        .with_label(first.name.span, "first declared here"),
+                    );
+                    continue;
+                }
+                pass.fns.insert(f.name.name.as_str(), f);
+            }
+            Stmt::Const(c) => {
+                pass.check_constant(
+                    &program.arena,
+                    c.value,
+                    format!(
+                        "constant `{}` must be initialized with a constant expression",
+                        c.name.name
+                    ),
+                );
+                if let Some(first) = pass.consts.get(c.name.name.as_str()) {
+                    pass.diagnostics.push(
+                        Diagnostic::error(
+                            c.name.span,
+                            format!("constant `{}` is declared twice", c.name.name),
+                        )
+                        .with_code("E0311")
+                        .with_label(first.name.span, "first declared here"),
+                    );
+                    continue;
+                }
+                pass.consts.insert(c.name.name.as_str(), c);
+            }
+            Stmt::Struct(s) if pass.check_new_type("struct", &s.name) => {
+                pass.structs.insert(s.name.name.as_str(), s);
+            }
+            Stmt::Enum(e) if pass.check_new_type("enum", &e.name) => {
+                pass.enums.insert(e.name.name.as_str(), e);
+            }
+            _ => {}
+        }
+    }
+    pass.check_unreachable(&program.arena, &program.stmts);
+    pass.visit_program(program);
+    pass.diagnostics
+}
+
+struct Pass1<'a> {
+    diagnostics: Vec<Diagnostic>,
+    /// Top-level functions by name, the first declaration of each.
+    fns: HashMap<&'a str, &'a FnDecl>,
+    /// Top-level structs by name, the first declaration of each.
+    structs: HashMap<&'a str, &'a StructDecl>,
+    /// Top-level enums by name, the first declaration of each.
+    enums: HashMap<&'a str, &'a EnumDecl>,
+    /// Top-level constants by name, the first declaration of each. Only
+    /// those declared so far while the top level is being collected.
+    consts: HashMap<&'a str, &'a ConstDecl>,
+    /// All top-level constants by name, the first declaration of each, to
+    /// tell a constant used before its declaration from other names.
+    all_consts: HashMap<&'a str, &'a ConstDecl>,
+    /// Blocks entered so far; zero at the top level.
+    block_depth: usize,
+    /// The name of the function being visited and how many values it
+    /// returns.
+    current_fn: Option<(String, usize)>,
+}
+
+impl Visitor for Pass1<'_> {
+    fn visit_stmt(&mut self, ast: &AstArena, stmt: StmtId) {
+        let (name, what) = match &ast[stmt] {
+            Stmt::Const(c) => (&c.name, "constants"),
+            Stmt::Fn(f) => {
+                self.check_params(ast, f);
+                self.check_returns(ast, f);
+                (&f.name, "functions")
+            }
+            Stmt::Struct(s) => {
+                self.check_field_decls(s);
+                (&s.name, "structs")
+            }
+            Stmt::Enum(e) => {
+                self.check_variant_decls(e);
+                (&e.name, "enums")
+            }
+            Stmt::Return(s) => {
+                self.check_return(s);
+                return visit::walk_stmt(self, ast, stmt);
+            }
+            Stmt::Let(s) => {
+                self.check_unpack(ast, s);
+                return visit::walk_stmt(self, ast, stmt);
+            }
+            Stmt::Expr(s) => {
+                self.check_discarded(ast, s.expr);
+                return visit::walk_stmt(self, ast, stmt);
+            }
+            _ => return visit::walk_stmt(self, ast, stmt),
+        };
+        if self.block_depth > 0 {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    name.span,
+                    format!("{what} must be declared at the top level"),
+                )
+                .with_code("E0303"),
+            );
+        }
+        let Stmt::Fn(f) = &ast[stmt] else {
+            return visit::walk_stmt(self, ast, stmt);
+        };
+        let outer = self
+            .current_fn
+            .replace((f.name.name.clone(), f.return_count()));
+        visit::walk_stmt(self, ast, stmt);
+        self.current_fn = outer;
+    }
+
+    fn visit_block(&mut self, ast: &AstArena, block: &Block) {
+        self.block_depth += 1;
+        self.check_unreachable(ast, &block.stmts);
+        visit::walk_block(self, ast, block);
+        self.block_depth -= 1;
+    }
+
+    fn visit_expr(&mut self, ast: &AstArena, expr: ExprId) {
+        match &ast[expr] {
+            Expr::If(e) if e.else_block.is_none() => {
+                self.diagnostics.push(
+                    Diagnostic::error(e.span, "`if` used as a value must have an `else` branch")
+                        .with_code("E0301")
+                        .with_note(
+                            "without `else` there is no value when every condition is false",
+                        ),
+                );
+            }
+            Expr::If(e) => self.check_yields(ast, e),
+            Expr::Call(call) => {
+                if let Some(f) = self.fns.get(call.callee.name.as_str())
+                    && !(f.min_args()..=f.params.len()).contains(&call.args.len())
+                {
+                    let expected = match (f.min_args(), f.params.len()) {
+                        (_, 1) => "1 argument".to_string(),
+                        (min, max) if min == max => format!("{max} arguments"),
+                        (min, max) => format!("{min} to {max} arguments"),
+                    };
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            call.span,
+                            format!(
+                                "`{}` takes {expected}, but {} were given",
+                                call.callee.name,
+                                call.args.len()
+                            ),
+                        )
+                        .with_code("E0304")
+                        .with_label(f.name.span, "declared here"),
+                    );
+                }
+            }
+            Expr::Cast(e) => {
+                let numeric = match &e.ty {
+                    TypeRef::Named(t) => matches!(t.name.as_str(), "int" | "uint" | "float"),
+                    _ => false,
+                };
+                if !numeric {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            e.ty.span(),
+                            "casts can only convert to `int`, `uint` or `float`",
+                        )
+                        .with_code("E0313"),
+                    );
+                }
+            }
+            Expr::Struct(lit) => {
+                if let Some(decl) = self.structs.get(lit.name.name.as_str()) {
+                    self.check_field_inits(decl, lit);
+                }
+            }
+            Expr::Variant(e) => {
+                if let Some(decl) = self.enums.get(e.enum_name.name.as_str())
+                    && !decl.variants.iter().any(|v| v.name.name == e.variant.name)
+                {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            e.variant.span,
+                            format!(
+                                "enum `{}` has no variant `{}`",
+                                decl.name.name, e.variant.name
+                            ),
+                        )
+                        .with_code("E0310")
+                        .with_label(decl.name.span, "declared here"),
+                    );
+                }
+            }
+            _ => {}
+        }
+        visit::walk_expr(self, ast, expr);
+    }
+
+    fn visit_attribute(&mut self, ast: &AstArena, attr: &Attribute) {
+        let name = &attr.name;
+        let takes_message = match name.name.as_str() {
+            "export" | "inline" => false,
+            "deprecated" => true,
+            other => {
+                self.diagnostics.push(
+                    Diagnostic::warning(name.span, format!("unknown attribute `{other}`"))
+                        .with_code("W0301")
+                        .with_note("known attributes: `export`, `inline`, `deprecated`"),
+                );
+                return;
+            }
+        };
+        let well_formed = match attr.args.as_slice() {
+            [] => true,
+            [message] => takes_message && matches!(ast[*message], Expr::Str(_)),
+            _ => false,
+        };
+        if !well_formed {
+            let usage = if takes_message {
+                format!("`#[{}]` or `#[{}(\"message\")]`", name.name, name.name)
+            } else {
+                format!("`#[{}]`", name.name)
+            };
+            self.diagnostics.push(
+                Diagnostic::error(name.span, format!("malformed `{}` attribute", name.name))
+                    .with_code("E0302")
+                    .with_note(format!("expected {usage}")),
+            );
+        }
+    }
+}
+
+impl Pass1<'_> {
+    /// Parameters with defaults come last, and defaults are constant
+    /// expressions.
+    fn check_params(&mut self, ast: &AstArena, f: &FnDecl) {
+        let mut first_default = None;
+        for param in &f.params {
+            match (param.default, first_default) {
+                (Some(default), _) => {
+                    first_default.get_or_insert(&param.name);
+                    self.check_constant(
+                        ast,
+                        default,
+                        format!(
+                            "the default of `{}` must be a constant expression",
+                            param.name.name
+                        ),
+                    );
+                }
+                (None, Some(first)) => {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            param.span,
+                            format!("parameter `{}` needs a default", param.name.name),
+                        )
+                        .with_code("E0314")
+                        .with_label(first.span, format!("`{}` has a default", first.name)),
+                    );
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    /// A function with a return type returns on every path.
+    fn check_returns(&mut self, ast: &AstArena, f: &FnDecl) {
+        if f.return_count() == 0 {
+            return;
+        }
+        let Some((span, note)) = fallthrough(ast, &f.body, false) else {
+            return;
+        };
+        let mut diagnostic = Diagnostic::error(
+            span,
+            format!("`{}` can end without returning a value", f.name.name),
+        )
+        .with_code("E0317");
+        if let Some(ret) = &f.ret {
+            diagnostic = diagnostic.with_label(ret.span(), "return type declared here");
+        }
+        if let Some(note) = note {
+            diagnostic = diagnostic.with_note(note);
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Every branch of an `if` used as a value yields on every path.
+    fn check_yields(&mut self, ast: &AstArena, e: &IfExpr) {
+        let branches = std::iter::once(&e.then_block)
+            .chain(e.elifs.iter().map(|elif| &elif.block))
+            .chain(&e.else_block);
+        for block in branches {
+            let Some((span, note)) = fallthrough(ast, block, true) else {
+                continue;
+            };
+            let mut diagnostic =
+                Diagnostic::error(span, "this branch can end without `yield`").with_code("E0318");
+            if let Some(note) = note {
+                diagnostic = diagnostic.with_note(note);
+            }
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Statements after one that no path gets past never run. Declarations
+    /// are exempt: they take effect wherever they are.
+    fn check_unreachable(&mut self, ast: &AstArena, stmts: &[StmtId]) {
+        let Some(end) = stmts.iter().position(|&stmt| ends(ast, stmt, true)) else {
+            return;
+        };
+        let mut unreachable = stmts[end + 1..]
+            .iter()
+            .map(|&stmt| &ast[stmt])
+            .filter(|stmt| {
+                !matches!(
+                    stmt,
+                    Stmt::Fn(_) | Stmt::Struct(_) | Stmt::Enum(_) | Stmt::Const(_) | Stmt::Error(_)
+                )
+            });
+        let Some(first) = unreachable.next() else {
+            return;
+        };
+        let span = unreachable
+            .next_back()
+            .map_or(first.span(), |last| first.span().to(last.span()));
+        let label = match &ast[stmts[end]] {
+            Stmt::Return(_) => "`return` leaves the function here",
+            Stmt::Yield(_) => "`yield` ends the block here",
+            Stmt::If(_) => "every branch of this `if` ends with `return` or `yield`",
+            _ => "this loop only ends with `return`",
+        };
+        self.diagnostics.push(
+            Diagnostic::warning(span, "unreachable code")
+                .with_code("W0304")
+                .with_label(ast[stmts[end]].span(), label),
+        );
+    }
+
+    /// An expression statement that is not a call computes a value only to
+    /// drop it.
+    fn check_discarded(&mut self, ast: &AstArena, expr: ExprId) {
+        let mut inner = expr;
+        while let Expr::Paren(e) = &ast[inner] {
+            inner = e.inner;
+        }
+        if matches!(ast[inner], Expr::Call(_) | Expr::Error(_)) {
+            return;
+        }
+        self.diagnostics.push(
+            Diagnostic::warning(ast[expr].span(), "unused value")
+                .with_code("W0303")
+                .with_note("bind it with `let _ = ...` if it is meant to be discarded"),
+        );
+    }
+
+    /// A `return` in a function gives as many values as the function
+    /// declares. The top level may return anything.
+    fn check_return(&mut self, s: &ReturnStmt) {
+        let Some((name, count)) = &self.current_fn else {
+            return;
+        };
+        if s.values.len() != *count {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    s.span,
+                    format!(
+                        "`{name}` returns {}, but this `return` gives {}",
+                        values(*count),
+                        s.values.len()
+                    ),
+                )
+                .with_code("E0315"),
+            );
+        }
+    }
+
+    /// `let a, b = f();` unpacks as many values as `f` returns.
+    fn check_unpack(&mut self, ast: &AstArena, s: &LetStmt) {
+        let (Pattern::Tuple(pattern), Expr::Call(call)) = (&s.pattern, &ast[s.value]) else {
+            return;
+        };
+        let Some(f) = self.fns.get(call.callee.name.as_str()) else {
+            return;
+        };
+        let count = f.return_count();
+        if pattern.elems.len() != count {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    pattern.span,
+                    format!(
+                        "`{}` returns {}, but {} are unpacked",
+                        f.name.name,
+                        values(count),
+                        pattern.elems.len()
+                    ),
+                )
+                .with_code("E0315")
+                .with_label(
+                    f.ret.as_ref().map_or(f.name.span, TypeRef::span),
+                    "declared here",
+                ),
+            );
+        }
+    }
+
+    /// Reports `message` at `expr` if part of it is not a constant
+    /// expression.
+    fn check_constant(&mut self, ast: &AstArena, expr: ExprId, message: String) {
+        let Some(part) = self.non_constant(ast, expr) else {
+            return;
+        };
+        let diagnostic = Diagnostic::error(ast[expr].span(), message).with_code("E0312");
+        let diagnostic = match &ast[part] {
+            Expr::Ident(ident) if self.all_consts.contains_key(ident.name.as_str()) => {
+                diagnostic
+                    .with_label(ident.span, format!("`{}` is declared later", ident.name))
+                    .with_label(self.all_consts[ident.name.as_str()].name.span, "declared here")
+                    .with_note("a constant can only use constants declared before it")
+            }
+            other => diagnostic.with_label(other.span(), "not constant").with_note(
+                "constant expressions use only literals, enum variants, constants and operators",
+            ),
+        };
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// The first part of `expr` that is not a constant expression, if any.
+    fn non_constant(&self, ast: &AstArena, expr: ExprId) -> Option<ExprId> {
+        match &ast[expr] {
+            Expr::Int(_)
+            | Expr::Float(_)
+            | Expr::Str(_)
+            | Expr::Bool(_)
+            | Expr::None(_)
+            | Expr::Variant(_)
+            | Expr::Error(_) => None,
+            Expr::Ident(ident) if self.consts.contains_key(ident.name.as_str()) => None,
+            Expr::Paren(e) => self.non_constant(ast, e.inner),
+            Expr::Unary(e) => self.non_constant(ast, e.operand),
+            Expr::Cast(e) => self.non_constant(ast, e.expr),
+            Expr::Binary(e) => self
+                .non_constant(ast, e.lhs)
+                .or_else(|| self.non_constant(ast, e.rhs)),
+            _ => Some(expr),
+        }
+    }
+
+    /// Reports `name` if a struct or enum of that name was declared before.
+    /// Returns whether it is new.
+    fn check_new_type(&mut self, kind: &str, name: &Ident) -> bool {
+        let first = match self.structs.get(name.name.as_str()) {
+            Some(s) => &s.name,
+            None => match self.enums.get(name.name.as_str()) {
+                Some(e) => &e.name,
+                None => return true,
+            },
+        };
+        self.diagnostics.push(
+            Diagnostic::error(
+                name.span,
+                format!("{kind} `{}` is declared twice", name.name),
+            )
+            .with_code("E0306")
+            .with_label(first.span, "first declared here"),
+        );
+        false
+    }
+
+    /// Variant names and backing values must be unique, and the values must
+    /// fit in an `int`.
+    fn check_variant_decls(&mut self, decl: &EnumDecl) {
+        let mut names: HashMap<&str, &Ident> = HashMap::new();
+        let mut values: HashMap<i64, &Ident> = HashMap::new();
+        // Only the first variant counting on past the limit is reported.
+        let mut previous_fits = true;
+        for (variant, value) in decl.variants.iter().zip(decl.backing_values()) {
+            let name = &variant.name;
+            if let Some(first) = names.insert(&name.name, name) {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        name.span,
+                        format!("variant `{}` is declared twice", name.name),
+                    )
+                    .with_code("E0309")
+                    .with_label(first.span, "first declared here"),
+                );
+                continue;
+            }
+            let Some(value) = value else {
+                if variant.value.is_some() || previous_fits {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            variant.span,
+                            format!("the value of variant `{}` does not fit in `int`", name.name),
+                        )
+                        .with_code("E0309"),
+                    );
+                }
+                previous_fits = false;
+                continue;
+            };
+            previous_fits = true;
+            if let Some(first) = values.insert(value, name) {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        variant.span,
+                        format!(
+                            "variants `{}` and `{}` have the same value {value}",
+                            first.name, name.name
+                        ),
+                    )
+                    .with_code("E0309")
+                    .with_label(first.span, format!("`{}` declared here", first.name)),
+                );
+            }
+        }
+    }
+
+    fn check_field_decls(&mut self, decl: &StructDecl) {
+        let mut seen: HashMap<&str, &Ident> = HashMap::new();
+        for field in &decl.fields {
+            if let Some(first) = seen.insert(&field.name.name, &field.name) {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        field.name.span,
+                        format!("field `{}` is declared twice", field.name.name),
+                    )
+                    .with_code("E0307")
+                    .with_label(first.span, "first declared here"),
+                );
+            }
+        }
+    }
+
+    /// Checks that `lit` gives each field of `decl` exactly once.
+    fn check_field_inits(&mut self, decl: &StructDecl, lit: &StructExpr) {
+        let mut seen: HashMap<&str, &Ident> = HashMap::new();
+        for field in &lit.fields {
+            let name = &field.name;
+            if !decl.fields.iter().any(|f| f.name.name == name.name) {
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        name.span,
+                        format!("struct `{}` has no field `{}`", decl.name.name, name.name),
+                    )
+                    .with_code("E0308")
+                    .with_label(decl.name.span, "declared here"),
+                );
+            } else if let Some(first) = seen.insert(&name.name, name) {
+                self.diagnostics.push(
+                    Diagnostic::error(name.span, format!("field `{}` is given twice", name.name))
+                        .with_code("E0307")
+                        .with_label(first.span, "first given here"),
+                );
+            }
+        }
+        let missing: Vec<String> = decl
+            .fields
+            .iter()
+            .filter(|f| !seen.contains_key(f.name.name.as_str()))
+            .map(|f| format!("`{}`", f.name.name))
+            .collect();
+        if !missing.is_empty() {
+            let fields = if missing.len() == 1 {
+                "field"
+            } else {
+                "fields"
+            };
+            self.diagnostics.push(
+                Diagnostic::error(
+                    lit.name.span,
+                    format!(
+                        "missing {fields} {} in `{}`",
+                        missing.join(", "),
+                        decl.name.name
+                    ),
+                )
+                .with_code("E0308")
+                .with_label(decl.name.span, "declared here"),
+            );
+        }
+    }
+}
+
+/// Where running `block` can reach its end, if it can, with a note on why.
+/// The span is of the innermost branch that gets there, or of an `if`
+/// without `else` that lets it through.
+///
+/// A path ends at a `return`, at a `yield` if `yields`, and in a
+/// `while true` loop, which only a `return` leaves.
+fn fallthrough(
+    ast: &AstArena,
+    block: &Block,
+    yields: bool,
+) -> Option<(Span, Option<&'static str>)> {
+    if block.stmts.iter().any(|&stmt| ends(ast, stmt, yields)) {
+        return None;
+    }
+    let Some(Stmt::If(s)) = block.stmts.last().map(|&stmt| &ast[stmt]) else {
+        return Some((block.span, None));
+    };
+    let Some(else_block) = &s.else_block else {
+        return Some((
+            s.span,
+            Some("when no condition holds, none of the branches runs"),
+        ));
+    };
+    std::iter::once(&s.then_block)
+        .chain(s.elifs.iter().map(|elif| &elif.block))
+        .chain([else_block])
+        .find_map(|block| fallthrough(ast, block, yields))
+}
+
+/// Whether no path through `stmt` gets past it.
+fn ends(ast: &AstArena, stmt: StmtId, yields: bool) -> bool {
+    match &ast[stmt] {
+        Stmt::Return(_) => true,
+        Stmt::Yield(_) => yields,
+        Stmt::If(s) => {
+            s.else_block.is_some()
+                && std::iter::once(&s.then_block)
+                    .chain(s.elifs.iter().map(|elif| &elif.block))
+                    .chain(&s.else_block)
+                    .all(|block| fallthrough(ast, block, yields).is_none())
+        }
+        Stmt::While(s) => is_true(ast, s.cond),
+        _ => false,
+    }
+}
+
+fn is_true(ast: &AstArena, expr: ExprId) -> bool {
+    match &ast[expr] {
+        Expr::Bool(lit) => lit.value,
+        Expr::Paren(e) => is_true(ast, e.inner),
+        _ => false,
+    }
+}
+
+/// `count` values, in words.
+fn values(count: usize) -> String {
+    match count {
+        0 => "no values".to_string(),
+        1 => "1 value".to_string(),
+        n => format!("{n} values"),
+    }
+}
@@ -0,0 +1,103 @@
+//! Passes supplied by the embedder.
+//!
+//! A [`Plugin`] runs at a fixed [`Stage`] of every compile and sees the
+//! whole program. Validation passes report what they find through the
+//! compile's diagnostics, exactly like the built-in ones; lowering passes
+//! rewrite the tree in place, usually with a
+//! [`VisitorMut`](crate::visit::VisitorMut), and later passes see the result.
+//!
+//! Before name resolution, plugins work on the [`ast1`](crate::ast1) tree,
+//! where names are only names. After it, they get the
+//! [`ast2`](crate::ast2) program, whose symbol table says what each name
+//! refers to, but can no longer rewrite it.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::ast1::Program;
+use crate::ast2;
+use crate::diagnostic::Diagnostics;
+
+/// Where in the pipeline a plugin runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// After parsing, before [`pass1`](crate::pass1). Rewrites made here are
+    /// checked by the built-in passes.
+    AfterParse,
+    /// After [`pass1`](crate::pass1), before name resolution, which sees
+    /// rewrites made here.
+    AfterPass1,
+    /// After [name resolution](crate::resolve), before type checking.
+    /// Plugins here only check; see [`Plugin::check_resolved`].
+    AfterResolve,
+}
+
+/// A custom pass. Plugins are shared between compiles running on different
+/// threads, so they keep no per-compile state of their own.
+pub trait Plugin: Send + Sync {
+    /// Identifies the plugin in debug output.
+    fn name(&self) -> &str;
+
+    /// Runs at [`Stage::AfterParse`] and [`Stage::AfterPass1`]. Does
+    /// nothing unless overridden.
+    fn run(&self, _program: &mut Program, _diagnostics: &mut Diagnostics) {}
+
+    /// Runs at [`Stage::AfterResolve`]. Does nothing unless overridden.
+    fn check_resolved(&self, _program: &ast2::Program, _diagnostics: &mut Diagnostics) {}
+}
+
+/// The plugins of a compile, in the order they were registered.
+#[derive(Clone, Default)]
+pub struct Plugins {
+    plugins: Vec<(Stage, Arc<dyn Plugin>)>,
+}
+
+impl Plugins {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, stage: Stage, plugin: impl Plugin + 'static) -> Self {
+        self.register(stage, plugin);
+        self
+    }
+
+    /// Adds `plugin` after those already registered for `stage`.
+    pub fn register(&mut self, stage: Stage, plugin: impl Plugin + 'static) {
+        self.plugins.push((stage, Arc::new(plugin)));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// Runs the plugins registered for `stage`, one before name resolution.
+    pub fn run(&self, stage: Stage, program: &mut Program, diagnostics: &mut Diagnostics) {
+        for (_, plugin) in self.plugins.iter().filter(|(s, _)| *s == stage) {
+            plugin.run(program, diagnostics);
+        }
+    }
+
+    /// Runs the plugins registered for [`Stage::AfterResolve`].
+    pub fn check_resolved(&self, program: &ast2::Program, diagnostics: &mut Diagnostics) {
+        for (_, plugin) in self
+            .plugins
+            .iter()
+            .filter(|(s, _)| *s == Stage::AfterResolve)
+        {
+            plugin.check_resolved(program, diagnostics);
+        }
+    }
+}
+
+impl fmt::Debug for Plugins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(
+                self.plugins
+                    .iter()
+                    .map(|(stage, plugin)| (stage, plugin.name())),
+            )
+            .finish()
+    }
+}
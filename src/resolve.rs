@@ -0,0 +1,771 @@
+//! Name resolution: lowers [`ast1`](crate::ast1) into [`ast2`](crate::ast2).
+//!
+//! Names are looked up the way draft mode looks them up at runtime. A value
+//! is a local (innermost block outwards, then the function's parameters), a
+//! constant or a global of the host. A call goes to a script function, a
+//! host function or a built-in. A type is a primitive or a struct or enum of
+//! the script. Functions, structs, enums and constants can be used before
+//! they are declared; locals only after. A function body sees its
+//! parameters, the constants and the globals, not the locals of the top
+//! level. The values are bound in a [`ScopeTree`], which the program keeps
+//! for what needs to know the names visible at a position.
+//!
+//! A local may shadow a name from an enclosing scope, including a
+//! constant, a global or a parameter. Declaring a name again in the same
+//! scope is allowed too, but warned about, since it is more often a
+//! forgotten `mut` than intended.
+//!
+//! Since every use of a local is found here, so are the locals that cannot
+//! be assigned, and those that are never read. Names starting with `_` are
+//! exempt from the latter, and so are `#[export]`ed locals, which the host
+//! reads.
+//!
+//! What the host provides is only known to the embedder. Given
+//! [`HostNames`], names found nowhere are errors; without, they are taken
+//! to be the host's, and only unknown types are reported. Either way,
+//! globals and constants cannot be assigned.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast1::{self, Attribute, Block, ExprId, Ident, StmtId, TypeRef};
+use crate::ast2::*;
+use crate::diagnostic::Diagnostic;
+use crate::draft::BUILTINS;
+use crate::scope::{ScopeKind, ScopeTree, SymbolId, SymbolInfo, SymbolKind, SymbolTable};
+use crate::span::{Loc, Span};
+
+/// The globals and functions a host provides, by name.
+#[derive(Debug, Clone, Default)]
+pub struct HostNames {
+    globals: HashSet<String>,
+    functions: HashSet<String>,
+}
+
+impl HostNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_global(mut self, name: impl Into<String>) -> Self {
+        self.globals.insert(name.into());
+        self
+    }
+
+    pub fn with_function(mut self, name: impl Into<String>) -> Self {
+        self.functions.insert(name.into());
+        self
+    }
+}
+
+/// Resolves every name in `program`, which should have passed
+/// [`pass1`](crate::pass1). `host` is what the host provides, if known.
+pub fn resolve(program: &ast1::Program, host: Option<&HostNames>) -> (Program, Vec<Diagnostic>) {
+    let ast = &program.arena;
+    let mut resolver = Resolver {
+        ast,
+        host,
+        arena: AstArena::default(),
+        symbols: SymbolTable::new(),
+        exprs: vec![None; ast.expr_count()],
+        stmts: vec![None; ast.stmt_count()],
+        diagnostics: Vec::new(),
+        decls: HashMap::new(),
+        fns: HashMap::new(),
+        types: HashMap::new(),
+        outside: HashMap::new(),
+        scopes: ScopeTree::new(),
+        read: HashSet::new(),
+        top_level_lets: HashMap::new(),
+        used_from_fns: HashSet::new(),
+    };
+    resolver.declare_top_level(&program.stmts);
+    let span = match (program.stmts.first(), program.stmts.last()) {
+        (Some(&first), Some(&last)) => ast[first].span().to(ast[last].span()),
+        _ => Span::default(),
+    };
+    resolver.scopes.enter(ScopeKind::TopLevel, span);
+    for &stmt in &program.stmts {
+        resolver.stmt(stmt);
+    }
+    resolver.scopes.exit();
+    resolver.report_unused();
+
+    let Resolver {
+        mut arena,
+        symbols,
+        scopes,
+        exprs,
+        stmts,
+        diagnostics,
+        ..
+    } = resolver;
+    // Every node is reached from the top level; the fallbacks only cover
+    // what error recovery may leave behind.
+    arena.exprs = exprs
+        .into_iter()
+        .zip(ast.exprs())
+        .map(|(expr, (_, old))| expr.unwrap_or(Expr::Error(ast1::ErrorNode { span: old.span() })))
+        .collect();
+    arena.stmts = stmts
+        .into_iter()
+        .zip(ast.stmts())
+        .map(|(stmt, (_, old))| stmt.unwrap_or(Stmt::Error(ast1::ErrorNode { span: old.span() })))
+        .collect();
+    let program = Program {
+        arena,
+        symbols,
+        scopes,
+        stmts: program.stmts.clone(),
+    };
+    (program, diagnostics)
+}
+
+struct Resolver<'a> {
+    ast: &'a ast1::AstArena,
+    host: Option<&'a HostNames>,
+    arena: AstArena,
+    symbols: SymbolTable,
+    /// The lowered nodes, by their ids.
+    exprs: Vec<Option<Expr>>,
+    stmts: Vec<Option<Stmt>>,
+    diagnostics: Vec<Diagnostic>,
+    /// The symbols of the top-level declarations.
+    decls: HashMap<StmtId, SymbolId>,
+    /// Script functions and types by name, the first declaration of each.
+    fns: HashMap<&'a str, SymbolId>,
+    types: HashMap<&'a str, SymbolId>,
+    /// The host's names and the built-ins used so far, and the names that
+    /// did not resolve: one symbol for each.
+    outside: HashMap<(&'a str, SymbolKind), SymbolId>,
+    /// The values in scope: constants in the global scope, locals and
+    /// parameters below.
+    scopes: ScopeTree,
+    /// The symbols read so far, and the `#[export]`ed locals, which the
+    /// host reads.
+    read: HashSet<SymbolId>,
+    /// The names the top-level `let`s bind, where each is first bound.
+    /// Functions cannot see them.
+    top_level_lets: HashMap<&'a str, Span>,
+    /// The names of top-level locals a function tried to use, which count
+    /// as read so that the error is not followed by a warning.
+    used_from_fns: HashSet<&'a str>,
+}
+
+impl<'a> Resolver<'a> {
+    fn declare_top_level(&mut self, stmts: &[StmtId]) {
+        for &stmt in stmts {
+            let (name, kind) = match &self.ast[stmt] {
+                ast1::Stmt::Let(s) => {
+                    self.top_level_let(&s.pattern);
+                    continue;
+                }
+                ast1::Stmt::Fn(f) => (&f.name, SymbolKind::Fn),
+                ast1::Stmt::Const(c) => (&c.name, SymbolKind::Const),
+                ast1::Stmt::Struct(s) => (&s.name, SymbolKind::Struct),
+                ast1::Stmt::Enum(e) => (&e.name, SymbolKind::Enum),
+                _ => continue,
+            };
+            let symbol = self.declare(name, kind);
+            self.decls.insert(stmt, symbol);
+            // Later declarations of a name were reported by pass1.
+            match kind {
+                SymbolKind::Fn => {
+                    self.fns.entry(&name.name).or_insert(symbol);
+                }
+                SymbolKind::Const => {
+                    if self.scopes.lookup(&name.name).is_none() {
+                        self.scopes.declare(&name.name, symbol, Loc::default());
+                    }
+                }
+                _ => {
+                    self.types.entry(&name.name).or_insert(symbol);
+                }
+            }
+        }
+    }
+
+    fn top_level_let(&mut self, pattern: &'a ast1::Pattern) {
+        match pattern {
+            ast1::Pattern::Ident(name) => {
+                self.top_level_lets.entry(&name.name).or_insert(name.span);
+            }
+            ast1::Pattern::Tuple(p) => {
+                for elem in &p.elems {
+                    self.top_level_let(elem);
+                }
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Ident, kind: SymbolKind) -> SymbolId {
+        self.symbols.alloc(SymbolInfo {
+            name: name.name.clone(),
+            kind,
+            span: Some(name.span),
+        })
+    }
+
+    /// Declares `name` in the current scope, visible from `from` on.
+    fn bind(&mut self, name: &'a Ident, kind: SymbolKind, from: Loc) -> NameRef {
+        if let Some(earlier) = self.scopes.get(self.scopes.current(), &name.name) {
+            let mut diagnostic = Diagnostic::warning(
+                name.span,
+                format!("`{}` is already declared in this scope", name.name),
+            )
+            .with_code("W0401")
+            .with_note("to change the value, declare it with `let mut` and assign to it");
+            if let Some(span) = self.symbols[earlier].span {
+                diagnostic = diagnostic.with_label(span, "first declared here");
+            }
+            self.diagnostics.push(diagnostic);
+        }
+        let symbol = self.declare(name, kind);
+        self.scopes.declare(&name.name, symbol, from);
+        NameRef {
+            symbol,
+            span: name.span,
+        }
+    }
+
+    /// The one symbol for `name` among the host's names, the built-ins or
+    /// the unresolved names.
+    fn outside(&mut self, name: &'a str, kind: SymbolKind) -> SymbolId {
+        if let Some(&symbol) = self.outside.get(&(name, kind)) {
+            return symbol;
+        }
+        let symbol = self.symbols.alloc(SymbolInfo {
+            name: name.to_string(),
+            kind,
+            span: None,
+        });
+        self.outside.insert((name, kind), symbol);
+        symbol
+    }
+
+    fn unresolved(&mut self, name: &'a Ident, what: &str, code: &'static str) -> SymbolId {
+        self.diagnostics.push(
+            Diagnostic::error(name.span, format!("unknown {what} `{}`", name.name)).with_code(code),
+        );
+        self.outside(&name.name, SymbolKind::Unresolved)
+    }
+
+    fn value(&mut self, name: &'a Ident) -> NameRef {
+        let hidden = self
+            .top_level_lets
+            .get(name.name.as_str())
+            .copied()
+            .filter(|_| self.in_fn());
+        let global = self
+            .host
+            .is_some_and(|host| host.globals.contains(&name.name));
+        let symbol = match (self.scopes.lookup(&name.name), hidden) {
+            (Some(symbol), _) => symbol,
+            // Without the host's names, this would pass for a global.
+            (None, Some(local)) if !global => {
+                self.used_from_fns.insert(&name.name);
+                self.diagnostics.push(
+                    Diagnostic::error(name.span, format!("unknown name `{}`", name.name))
+                        .with_code("E0401")
+                        .with_label(local, "a top-level local, which functions cannot see")
+                        .with_note("pass it in as a parameter, or declare it as a `const`"),
+                );
+                self.outside(&name.name, SymbolKind::Unresolved)
+            }
+            (None, _) if self.host.is_some() && !global => self.unresolved(name, "name", "E0401"),
+            (None, _) => self.outside(&name.name, SymbolKind::Global),
+        };
+        NameRef {
+            symbol,
+            span: name.span,
+        }
+    }
+
+    /// Whether the current scope is in a function, parameters included.
+    fn in_fn(&self) -> bool {
+        let mut scope = self.scopes.current();
+        loop {
+            match self.scopes[scope].kind {
+                ScopeKind::Function => return true,
+                ScopeKind::Block => match self.scopes[scope].parent {
+                    Some(parent) => scope = parent,
+                    None => return false,
+                },
+                ScopeKind::Global | ScopeKind::TopLevel => return false,
+            }
+        }
+    }
+
+    /// Only `let mut` locals can be assigned; constants, the host's globals,
+    /// parameters and loop variables are read-only.
+    fn check_assign(&mut self, target: &Ident, symbol: SymbolId) {
+        let name = &target.name;
+        let diagnostic = match self.symbols[symbol].kind {
+            SymbolKind::Const | SymbolKind::Global => {
+                let what = if self.symbols[symbol].kind == SymbolKind::Const {
+                    "constant"
+                } else {
+                    "global"
+                };
+                Diagnostic::error(target.span, format!("cannot assign to {what} `{name}`"))
+                    .with_code("E0404")
+                    .with_note(format!(
+                        "copy it into a local first: `let mut {name} = {name};`"
+                    ))
+            }
+            SymbolKind::Local { mutable: false } => Diagnostic::error(
+                target.span,
+                format!("cannot assign to `{name}`, which is not `mut`"),
+            )
+            .with_code("E0316")
+            .with_note(format!("declare it with `let mut {name}` to assign to it")),
+            SymbolKind::Param => {
+                Diagnostic::error(target.span, format!("cannot assign to parameter `{name}`"))
+                    .with_code("E0316")
+                    .with_note("copy it into a `let mut` to change it")
+            }
+            SymbolKind::LoopVar => Diagnostic::error(
+                target.span,
+                format!("cannot assign to loop variable `{name}`"),
+            )
+            .with_code("E0316"),
+            _ => return,
+        };
+        let diagnostic = match self.symbols[symbol].span {
+            Some(span) => diagnostic.with_label(span, "declared here"),
+            None => diagnostic,
+        };
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Warns about the locals, parameters and loop variables that are never
+    /// read, in source order. Names starting with `_` are exempt.
+    fn report_unused(&mut self) {
+        for (_, scope) in self.scopes.iter() {
+            if scope.kind == ScopeKind::TopLevel {
+                for (name, symbol) in scope.bindings() {
+                    if self.used_from_fns.contains(name) {
+                        self.read.insert(symbol);
+                    }
+                }
+            }
+        }
+        let mut unused: Vec<(Span, &str, &str)> = self
+            .symbols
+            .iter()
+            .filter(|(id, info)| !self.read.contains(id) && !info.name.starts_with('_'))
+            .filter_map(|(_, info)| {
+                let what = match info.kind {
+                    SymbolKind::Local { .. } => "variable",
+                    SymbolKind::Param => "parameter",
+                    SymbolKind::LoopVar => "loop variable",
+                    _ => return None,
+                };
+                Some((info.span?, info.name.as_str(), what))
+            })
+            .collect();
+        unused.sort();
+        for (span, name, what) in unused {
+            self.diagnostics.push(
+                Diagnostic::warning(span, format!("unused {what} `{name}`"))
+                    .with_code("W0302")
+                    .with_note(format!(
+                        "if this is intentional, prefix it with an underscore: `_{name}`"
+                    )),
+            );
+        }
+    }
+
+    /// The function `name` calls. `optional` if it may be missing, as in
+    /// `try_call`.
+    fn function(&mut self, name: &'a Ident, optional: bool) -> NameRef {
+        let builtin = BUILTINS.contains(&name.name.as_str());
+        let symbol = if let Some(&symbol) = self.fns.get(name.name.as_str()) {
+            symbol
+        } else {
+            match self.host {
+                Some(host) if host.functions.contains(&name.name) => {
+                    self.outside(&name.name, SymbolKind::HostFn)
+                }
+                _ if builtin => self.outside(&name.name, SymbolKind::Builtin),
+                None => self.outside(&name.name, SymbolKind::HostFn),
+                Some(_) if optional => self.outside(&name.name, SymbolKind::Unresolved),
+                Some(_) => self.unresolved(name, "function", "E0402"),
+            }
+        };
+        NameRef {
+            symbol,
+            span: name.span,
+        }
+    }
+
+    /// The struct (or enum, by `kind`) called `name`.
+    fn type_name(&mut self, name: &'a Ident, kind: SymbolKind) -> NameRef {
+        let what = if kind == SymbolKind::Struct {
+            "struct"
+        } else {
+            "enum"
+        };
+        let symbol = match self.types.get(name.name.as_str()) {
+            Some(&symbol) if self.symbols[symbol].kind == kind => symbol,
+            _ => self.unresolved(name, what, "E0403"),
+        };
+        NameRef {
+            symbol,
+            span: name.span,
+        }
+    }
+
+    fn ty(&mut self, ty: &'a TypeRef) -> Type {
+        match ty {
+            TypeRef::Named(name) => match name.name.as_str() {
+                "int" => Type::Int,
+                "uint" => Type::UInt,
+                "float" => Type::Float,
+                "bool" => Type::Bool,
+                "str" => Type::Str,
+                other => match self.types.get(other) {
+                    Some(&symbol) if self.symbols[symbol].kind == SymbolKind::Struct => {
+                        Type::Struct(symbol)
+                    }
+                    Some(&symbol) => Type::Enum(symbol),
+                    None => {
+                        self.unresolved(name, "type", "E0403");
+                        Type::Error
+                    }
+                },
+            },
+            TypeRef::Unit(_) => Type::Unit,
+            TypeRef::Tuple(t) => Type::Tuple(t.elems.iter().map(|e| self.ty(e)).collect()),
+            TypeRef::Array(t) => Type::Array(Box::new(self.ty(&t.elem))),
+            TypeRef::Map(t) => Type::Map(Box::new(self.ty(&t.key)), Box::new(self.ty(&t.value))),
+            TypeRef::Optional(t) => Type::Optional(Box::new(self.ty(&t.inner))),
+        }
+    }
+
+    fn attrs(&mut self, attrs: &[Attribute]) {
+        for attr in attrs {
+            for &arg in &attr.args {
+                self.expr(arg);
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.scopes.enter(ScopeKind::Block, block.span);
+        for &stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+        self.scopes.exit();
+    }
+
+    fn pattern(&mut self, pattern: &'a ast1::Pattern, kind: SymbolKind, from: Loc) -> Pattern {
+        match pattern {
+            ast1::Pattern::Ident(name) => Pattern::Name(self.bind(name, kind, from)),
+            ast1::Pattern::Tuple(p) => Pattern::Tuple(
+                p.elems
+                    .iter()
+                    .map(|e| self.pattern(e, kind, from))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Counts the names in `pattern` as read, since the host reads them.
+    fn exported(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Name(name) => {
+                self.read.insert(name.symbol);
+            }
+            Pattern::Tuple(elems) => {
+                for elem in elems {
+                    self.exported(elem);
+                }
+            }
+        }
+    }
+
+    /// The symbol of a declaration: its top-level one, or a fresh one for a
+    /// declaration pass1 rejected for being inside a block.
+    fn decl(&mut self, stmt: StmtId, name: &Ident, kind: SymbolKind) -> SymbolId {
+        match self.decls.get(&stmt) {
+            Some(&symbol) => symbol,
+            None => self.declare(name, kind),
+        }
+    }
+
+    fn stmt(&mut self, id: StmtId) {
+        let lowered = match &self.ast[id] {
+            ast1::Stmt::Let(s) => {
+                self.attrs(&s.attrs);
+                self.expr(s.value);
+                let ty = s.ty.as_ref().map(|ty| (self.ty(ty), ty.span()));
+                let kind = SymbolKind::Local { mutable: s.mutable };
+                let pattern = self.pattern(&s.pattern, kind, s.span.end);
+                if s.attrs.iter().any(|a| a.name.name == "export") {
+                    self.exported(&pattern);
+                }
+                Stmt::Let(LetStmt {
+                    pattern,
+                    ty,
+                    value: s.value,
+                    span: s.span,
+                })
+            }
+            ast1::Stmt::Const(c) => {
+                self.attrs(&c.attrs);
+                let symbol = self.decl(id, &c.name, SymbolKind::Const);
+                let ty = c.ty.as_ref().map(|ty| self.ty(ty));
+                self.expr(c.value);
+                Stmt::Const(ConstDecl {
+                    symbol,
+                    ty,
+                    value: c.value,
+                    span: c.span,
+                })
+            }
+            ast1::Stmt::Fn(f) => {
+                self.attrs(&f.attrs);
+                let symbol = self.decl(id, &f.name, SymbolKind::Fn);
+                // Defaults are evaluated where the call is, so they see no
+                // locals, and the body sees only the parameters.
+                self.scopes.enter(ScopeKind::Function, f.span);
+                let mut params = Vec::new();
+                for param in &f.params {
+                    if let Some(default) = param.default {
+                        self.expr(default);
+                    }
+                    params.push((param, self.ty(&param.ty)));
+                }
+                let params = params
+                    .into_iter()
+                    .map(|(param, ty)| Param {
+                        symbol: self
+                            .bind(&param.name, SymbolKind::Param, f.body.span.start)
+                            .symbol,
+                        ty,
+                        default: param.default,
+                        span: param.span,
+                    })
+                    .collect();
+                let ret = f.ret.as_ref().map(|ty| self.ty(ty));
+                self.block(&f.body);
+                self.scopes.exit();
+                Stmt::Fn(FnDecl {
+                    symbol,
+                    params,
+                    ret,
+                    body: f.body.clone(),
+                    span: f.span,
+                })
+            }
+            ast1::Stmt::Struct(s) => {
+                self.attrs(&s.attrs);
+                let symbol = self.decl(id, &s.name, SymbolKind::Struct);
+                let fields = s
+                    .fields
+                    .iter()
+                    .map(|field| FieldDecl {
+                        name: field.name.clone(),
+                        ty: self.ty(&field.ty),
+                        span: field.span,
+                    })
+                    .collect();
+                Stmt::Struct(StructDecl {
+                    symbol,
+                    fields,
+                    span: s.span,
+                })
+            }
+            ast1::Stmt::Enum(e) => {
+                self.attrs(&e.attrs);
+                Stmt::Enum(EnumDecl {
+                    symbol: self.decl(id, &e.name, SymbolKind::Enum),
+                    variants: e.variants.clone(),
+                    span: e.span,
+                })
+            }
+            ast1::Stmt::Assign(s) => {
+                let target = self.value(&s.target);
+                self.check_assign(&s.target, target.symbol);
+                for step in &s.path {
+                    if let ast1::Projection::Index(index) = step {
+                        self.expr(*index);
+                    }
+                }
+                self.expr(s.value);
+                Stmt::Assign(AssignStmt {
+                    target,
+                    path: s.path.clone(),
+                    value: s.value,
+                    span: s.span,
+                })
+            }
+            ast1::Stmt::Return(s) => {
+                for &value in &s.values {
+                    self.expr(value);
+                }
+                Stmt::Return(s.clone())
+            }
+            ast1::Stmt::Yield(s) => {
+                for &value in &s.values {
+                    self.expr(value);
+                }
+                Stmt::Yield(s.clone())
+            }
+            ast1::Stmt::If(s) => {
+                self.branches(s.cond, &s.then_block, &s.elifs, s.else_block.as_ref());
+                Stmt::If(s.clone())
+            }
+            ast1::Stmt::While(s) => {
+                self.expr(s.cond);
+                self.block(&s.body);
+                Stmt::While(s.clone())
+            }
+            ast1::Stmt::For(s) => {
+                self.expr(s.range);
+                self.scopes.enter(ScopeKind::Block, s.span);
+                let var = self
+                    .bind(&s.var, SymbolKind::LoopVar, s.body.span.start)
+                    .symbol;
+                self.block(&s.body);
+                self.scopes.exit();
+                Stmt::For(ForStmt {
+                    var,
+                    range: s.range,
+                    body: s.body.clone(),
+                    span: s.span,
+                })
+            }
+            ast1::Stmt::Expr(s) => {
+                self.expr(s.expr);
+                Stmt::Expr(s.clone())
+            }
+            ast1::Stmt::Error(e) => Stmt::Error(*e),
+        };
+        self.stmts[id.index()] = Some(lowered);
+    }
+
+    fn branches(
+        &mut self,
+        cond: ExprId,
+        then_block: &Block,
+        elifs: &[ast1::ElifBranch],
+        else_block: Option<&Block>,
+    ) {
+        self.expr(cond);
+        self.block(then_block);
+        for elif in elifs {
+            self.expr(elif.cond);
+            self.block(&elif.block);
+        }
+        if let Some(block) = else_block {
+            self.block(block);
+        }
+    }
+
+    fn expr(&mut self, id: ExprId) {
+        let lowered = match &self.ast[id] {
+            ast1::Expr::Int(lit) => Expr::Int(lit.clone()),
+            ast1::Expr::Float(lit) => Expr::Float(lit.clone()),
+            ast1::Expr::Str(lit) => Expr::Str(lit.clone()),
+            ast1::Expr::Bool(lit) => Expr::Bool(lit.clone()),
+            ast1::Expr::None(lit) => Expr::None(lit.clone()),
+            ast1::Expr::Ident(name) => {
+                let name = self.value(name);
+                self.read.insert(name.symbol);
+                Expr::Name(name)
+            }
+            ast1::Expr::Call(call) => Expr::Call(self.call(call, false)),
+            ast1::Expr::Unary(e) => {
+                self.expr(e.operand);
+                Expr::Unary(e.clone())
+            }
+            ast1::Expr::Cast(e) => {
+                self.expr(e.expr);
+                Expr::Cast(CastExpr {
+                    expr: e.expr,
+                    ty: self.ty(&e.ty),
+                    span: e.span,
+                })
+            }
+            ast1::Expr::Binary(e) => {
+                self.expr(e.lhs);
+                self.expr(e.rhs);
+                Expr::Binary(e.clone())
+            }
+            ast1::Expr::Paren(e) => {
+                self.expr(e.inner);
+                Expr::Paren(e.clone())
+            }
+            ast1::Expr::If(e) => {
+                self.branches(e.cond, &e.then_block, &e.elifs, e.else_block.as_ref());
+                Expr::If(e.clone())
+            }
+            ast1::Expr::Range(e) => {
+                self.expr(e.start);
+                self.expr(e.end);
+                Expr::Range(e.clone())
+            }
+            ast1::Expr::Array(e) => {
+                for &elem in &e.elems {
+                    self.expr(elem);
+                }
+                Expr::Array(e.clone())
+            }
+            ast1::Expr::Map(e) => {
+                for entry in &e.entries {
+                    self.expr(entry.key);
+                    self.expr(entry.value);
+                }
+                Expr::Map(e.clone())
+            }
+            ast1::Expr::Index(e) => {
+                self.expr(e.base);
+                self.expr(e.index);
+                Expr::Index(e.clone())
+            }
+            ast1::Expr::Struct(e) => {
+                let name = self.type_name(&e.name, SymbolKind::Struct);
+                for field in &e.fields {
+                    self.expr(field.value);
+                }
+                Expr::Struct(StructExpr {
+                    name,
+                    fields: e.fields.clone(),
+                    span: e.span,
+                })
+            }
+            ast1::Expr::Field(e) => {
+                self.expr(e.base);
+                Expr::Field(e.clone())
+            }
+            ast1::Expr::Variant(e) => Expr::Variant(VariantExpr {
+                enum_name: self.type_name(&e.enum_name, SymbolKind::Enum),
+                variant: e.variant.clone(),
+                span: e.span,
+            }),
+            ast1::Expr::Error(e) => Expr::Error(*e),
+        };
+        self.exprs[id.index()] = Some(lowered);
+    }
+
+    fn call(&mut self, call: &'a ast1::CallExpr, optional: bool) -> CallExpr {
+        let callee = self.function(&call.callee, optional);
+        let try_call = self.symbols[callee.symbol].kind == SymbolKind::Builtin
+            && call.callee.name == "try_call";
+        for &arg in &call.args {
+            match &self.ast[arg] {
+                ast1::Expr::Call(inner) if try_call => {
+                    let inner = Expr::Call(self.call(inner, true));
+                    self.exprs[arg.index()] = Some(inner);
+                }
+                _ => self.expr(arg),
+            }
+        }
+        CallExpr {
+            callee,
+            args: call.args.clone(),
+            span: call.span,
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Symbols and the scopes that bind names to them.
+//!
+//! A [`SymbolTable`] holds one [`SymbolInfo`] per thing a name can refer to.
+//! A [`ScopeTree`] records which names are bound where: one [`Scope`] per
+//! function, block and loop, nested the way the source nests them. It is
+//! built while walking the program, with [`enter`](ScopeTree::enter),
+//! [`declare`](ScopeTree::declare) and [`exit`](ScopeTree::exit), and can
+//! be queried afterwards for the names visible at a position.
+//!
+//! Name lookup follows the language: a function body does not see the
+//! locals around its declaration, so lookup leaves a [`ScopeKind::Function`]
+//! scope for the [`ScopeKind::Global`] one, skipping everything between.
+
+use std::ops::Index;
+
+use crate::span::{Loc, Span};
+
+/// Identifies a [`SymbolInfo`] in a [`SymbolTable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymbolId(u32);
+
+impl SymbolId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Something a name can refer to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymbolInfo {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Where the script declares it; `None` for what the host and the
+    /// language provide, and for unresolved names.
+    pub span: Option<Span>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SymbolKind {
+    /// A `let` binding.
+    Local {
+        mutable: bool,
+    },
+    Param,
+    /// The variable of a `for` loop.
+    LoopVar,
+    Const,
+    /// A value provided by the host.
+    Global,
+    /// A function declared in the script.
+    Fn,
+    /// A function provided by the host.
+    HostFn,
+    /// A function every program can call, like `print`.
+    Builtin,
+    Struct,
+    Enum,
+    /// A name that refers to nothing. Its diagnostic has been reported,
+    /// except for the function of a `try_call`, which may be missing.
+    Unresolved,
+}
+
+/// The symbols of one program.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymbolTable {
+    symbols: Vec<SymbolInfo>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn alloc(&mut self, symbol: SymbolInfo) -> SymbolId {
+        let id = SymbolId(self.symbols.len() as u32);
+        self.symbols.push(symbol);
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (SymbolId, &SymbolInfo)> {
+        self.symbols
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (SymbolId(i as u32), s))
+    }
+}
+
+impl Index<SymbolId> for SymbolTable {
+    type Output = SymbolInfo;
+
+    fn index(&self, id: SymbolId) -> &SymbolInfo {
+        &self.symbols[id.index()]
+    }
+}
+
+/// Identifies a [`Scope`] in a [`ScopeTree`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScopeId(u32);
+
+impl ScopeId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScopeKind {
+    /// The root: what every part of the program sees, like constants.
+    Global,
+    /// The statements outside any function.
+    TopLevel,
+    /// A function, from its parameters to the end of its body.
+    Function,
+    /// A block, or the variable of a `for` loop.
+    Block,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scope {
+    pub kind: ScopeKind,
+    /// `None` for the global scope.
+    pub parent: Option<ScopeId>,
+    pub children: Vec<ScopeId>,
+    /// The source the scope covers. The global scope covers everything and
+    /// its span is not used.
+    pub span: Span,
+    bindings: Vec<Binding>,
+}
+
+impl Scope {
+    /// The names bound here, in the order they were declared.
+    pub fn bindings(&self) -> impl ExactSizeIterator<Item = (&str, SymbolId)> {
+        self.bindings.iter().map(|b| (b.name.as_str(), b.symbol))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Binding {
+    name: String,
+    symbol: SymbolId,
+    /// Where the name starts being visible.
+    from: Loc,
+}
+
+/// The scopes of one program, as a tree under the global scope.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScopeTree {
+    scopes: Vec<Scope>,
+    current: ScopeId,
+}
+
+impl Default for ScopeTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScopeTree {
+    /// A tree with only the global scope, which is the current one.
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![Scope {
+                kind: ScopeKind::Global,
+                parent: None,
+                children: Vec::new(),
+                span: Span::default(),
+                bindings: Vec::new(),
+            }],
+            current: Self::GLOBAL,
+        }
+    }
+
+    pub const GLOBAL: ScopeId = ScopeId(0);
+
+    pub fn current(&self) -> ScopeId {
+        self.current
+    }
+
+    /// Opens a scope inside the current one and makes it current.
+    pub fn enter(&mut self, kind: ScopeKind, span: Span) -> ScopeId {
+        let id = ScopeId(self.scopes.len() as u32);
+        self.scopes.push(Scope {
+            kind,
+            parent: Some(self.current),
+            children: Vec::new(),
+            span,
+            bindings: Vec::new(),
+        });
+        self.scopes[self.current.index()].children.push(id);
+        self.current = id;
+        id
+    }
+
+    /// Closes the current scope, making its parent current again.
+    ///
+    /// # Panics
+    ///
+    /// In the global scope.
+    pub fn exit(&mut self) {
+        self.current = self[self.current]
+            .parent
+            .expect("the global scope is never exited");
+    }
+
+    /// Binds `name` to `symbol` in the current scope, visible from `from`
+    /// on. A later binding of the same name shadows the earlier one.
+    pub fn declare(&mut self, name: impl Into<String>, symbol: SymbolId, from: Loc) {
+        let current = self.current.index();
+        self.scopes[current].bindings.push(Binding {
+            name: name.into(),
+            symbol,
+            from,
+        });
+    }
+
+    /// The symbol `name` is bound to in `scope` itself, ignoring the
+    /// scopes around it.
+    pub fn get(&self, scope: ScopeId, name: &str) -> Option<SymbolId> {
+        self[scope]
+            .bindings
+            .iter()
+            .rev()
+            .find(|b| b.name == name)
+            .map(|b| b.symbol)
+    }
+
+    /// The symbol `name` refers to in the current scope.
+    pub fn lookup(&self, name: &str) -> Option<SymbolId> {
+        self.chain(self.current)
+            .find_map(|scope| self.get(scope, name))
+    }
+
+    /// The innermost scope covering `loc`.
+    pub fn scope_at(&self, loc: Loc) -> ScopeId {
+        let mut scope = Self::GLOBAL;
+        while let Some(&child) = self[scope]
+            .children
+            .iter()
+            .find(|&&child| self[child].span.contains(loc))
+        {
+            scope = child;
+        }
+        scope
+    }
+
+    /// The names visible at `loc` and the symbols they refer to, innermost
+    /// first. Shadowed bindings are left out.
+    pub fn visible_at(&self, loc: Loc) -> Vec<(&str, SymbolId)> {
+        let mut visible: Vec<(&str, SymbolId)> = Vec::new();
+        for scope in self.chain(self.scope_at(loc)) {
+            for binding in self[scope].bindings.iter().rev() {
+                if binding.from <= loc && visible.iter().all(|&(name, _)| name != binding.name) {
+                    visible.push((&binding.name, binding.symbol));
+                }
+            }
+        }
+        visible
+    }
+
+    pub fn len(&self) -> usize {
+        self.scopes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scopes.is_empty()
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (ScopeId, &Scope)> {
+        self.scopes
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (ScopeId(i as u32), s))
+    }
+
+    /// `scope` and the scopes lookup continues in from there, innermost
+    /// first.
+    fn chain(&self, scope: ScopeId) -> impl Iterator<Item = ScopeId> + '_ {
+        std::iter::successors(Some(scope), |&scope| match self[scope].kind {
+            ScopeKind::Global => None,
+            ScopeKind::Function => Some(Self::GLOBAL),
+            _ => self[scope].parent,
+        })
+    }
+}
+
+impl Index<ScopeId> for ScopeTree {
+    type Output = Scope;
+
+    fn index(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.index()]
+    }
+}
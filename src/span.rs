@@ -0,0 +1,51 @@
+//! Positions and ranges in source text.
+
+/// A position in source text.
+///
+/// `col` counts characters from the start of the line; `display_col` is the
+/// column an editor shows, with tabs expanded to the configured tab width.
+/// All fields are zero-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Loc {
+    pub line: usize,
+    pub col: usize,
+    pub display_col: usize,
+}
+
+/// A half-open range `[start, end)` of source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: Loc,
+    pub end: Loc,
+}
+
+impl Span {
+    pub fn new(start: Loc, end: Loc) -> Self {
+        Self { start, end }
+    }
+
+    /// An empty span at `loc`.
+    pub fn point(loc: Loc) -> Self {
+        Self {
+            start: loc,
+            end: loc,
+        }
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    pub fn contains(&self, loc: Loc) -> bool {
+        if self.start == self.end {
+            return loc == self.start;
+        }
+        self.start <= loc && loc < self.end
+    }
+}
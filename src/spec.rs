@@ -0,0 +1,201 @@
+//! Language conformance tests.
+//!
+//! A spec test is an ordinary `.ss` file whose comments state what an
+//! implementation must report for it:
+//!
+//! ```text
+//! //~ SPEC 1
+//! let x = 1 $ 2; //~ ERROR E0101
+//! let y = "a\q";
+//! //~^ ERROR unknown escape
+//! //~ OUT 3
+//! ```
+//!
+//! - `//~ ERROR <text>` / `//~ WARN <text>` expect a diagnostic of that
+//!   severity starting on the same line whose code equals `<text>` or whose
+//!   message contains it. Each `^` after `~` moves the expectation one line up.
+//! - `//~ OUT <text>` expects the program to print `<text>`; all `OUT` lines
+//!   must appear in file order.
+//! - `//~ SPEC <n>` marks the language version the test was written against.
+//!   Implementations skip tests newer than what they support.
+//!
+//! Every error and warning an implementation reports must be expected;
+//! unexpected ones fail the test. Nothing here depends on this crate's
+//! pipeline, so other backends can be checked against the same corpus by
+//! building an [`Outcome`] from their own results.
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::line_map::Lines;
+
+/// The language version this crate implements.
+pub const SPEC_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpectKind {
+    Error,
+    Warning,
+    Out,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expectation {
+    pub kind: ExpectKind,
+    /// Zero-based line the expectation applies to. Unused for `OUT`.
+    pub line: usize,
+    pub text: String,
+}
+
+/// The expectations of one spec test.
+#[derive(Debug, Clone, Default)]
+pub struct SpecTest {
+    pub version: u32,
+    pub expectations: Vec<Expectation>,
+    /// Malformed directives, reported as failures when checked.
+    pub errors: Vec<String>,
+}
+
+impl SpecTest {
+    pub fn parse(lines: &Lines) -> Self {
+        let mut test = SpecTest {
+            version: 1,
+            ..Default::default()
+        };
+        for line in lines {
+            let Some(pos) = line.content.find("//~") else {
+                continue;
+            };
+            let rest = &line.content[pos + 3..];
+            let ups = rest.chars().take_while(|&c| c == '^').count();
+            let rest = rest[ups..].trim();
+            let (directive, text) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            let text = text.trim().to_string();
+            let Some(target) = line.idx.checked_sub(ups) else {
+                test.errors.push(format!(
+                    "line {}: `^` points before the first line",
+                    line.idx + 1
+                ));
+                continue;
+            };
+            let kind = match directive {
+                "ERROR" => ExpectKind::Error,
+                "WARN" => ExpectKind::Warning,
+                "OUT" => ExpectKind::Out,
+                "SPEC" => {
+                    match text.parse() {
+                        Ok(version) => test.version = version,
+                        Err(_) => test
+                            .errors
+                            .push(format!("line {}: bad version `{text}`", line.idx + 1)),
+                    }
+                    continue;
+                }
+                other => {
+                    test.errors.push(format!(
+                        "line {}: unknown directive `{other}`",
+                        line.idx + 1
+                    ));
+                    continue;
+                }
+            };
+            test.expectations.push(Expectation {
+                kind,
+                line: target,
+                text,
+            });
+        }
+        test
+    }
+
+    pub fn expects_output(&self) -> bool {
+        self.expectations.iter().any(|e| e.kind == ExpectKind::Out)
+    }
+
+    /// Compares what an implementation produced against the expectations.
+    pub fn check(&self, outcome: &Outcome) -> Verdict {
+        if self.version > outcome.spec_version {
+            return Verdict::Skipped(format!(
+                "written for spec version {}, implementation supports {}",
+                self.version, outcome.spec_version
+            ));
+        }
+        if self.expects_output() && outcome.output.is_none() {
+            return Verdict::Skipped("expects output, implementation did not run it".to_string());
+        }
+        let mut failures = self.errors.clone();
+
+        let mut reported: Vec<&Diagnostic> = outcome
+            .diagnostics
+            .iter()
+            .filter(|d| d.severity != Severity::Note)
+            .collect();
+        for e in &self.expectations {
+            let severity = match e.kind {
+                ExpectKind::Error => Severity::Error,
+                ExpectKind::Warning => Severity::Warning,
+                ExpectKind::Out => continue,
+            };
+            let found = reported.iter().position(|d| {
+                d.severity == severity
+                    && d.span.start.line == e.line
+                    && (d.code == Some(e.text.as_str()) || d.message.contains(&e.text))
+            });
+            match found {
+                Some(i) => {
+                    reported.remove(i);
+                }
+                None => failures.push(format!(
+                    "line {}: expected {} matching `{}`",
+                    e.line + 1,
+                    severity.as_str(),
+                    e.text
+                )),
+            }
+        }
+        for d in reported {
+            failures.push(format!(
+                "line {}: unexpected {}: {}",
+                d.span.start.line + 1,
+                d.severity.as_str(),
+                d.message
+            ));
+        }
+
+        if let Some(output) = &outcome.output {
+            let expected: Vec<&str> = self
+                .expectations
+                .iter()
+                .filter(|e| e.kind == ExpectKind::Out)
+                .map(|e| e.text.as_str())
+                .collect();
+            if output
+                .iter()
+                .map(String::as_str)
+                .ne(expected.iter().copied())
+            {
+                failures.push(format!("expected output {expected:?}, got {output:?}"));
+            }
+        }
+
+        if failures.is_empty() {
+            Verdict::Pass
+        } else {
+            Verdict::Fail(failures)
+        }
+    }
+}
+
+/// What an implementation produced for a spec test.
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub spec_version: u32,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Printed lines, or `None` if the program was not run.
+    pub output: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail(Vec<String>),
+    Skipped(String),
+}
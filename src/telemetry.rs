@@ -0,0 +1,77 @@
+//! Error reports for live games.
+//!
+//! A host that wants to know which scripts fail in the wild registers an
+//! [`ErrorSink`] with its [`AnalysisHost`](crate::AnalysisHost) and its
+//! draft [`Interpreter`](crate::draft::Interpreter)s. Each compile diagnostic
+//! and runtime trap then reaches the sink as an [`ErrorRecord`], ready to be
+//! counted or sent off. Nothing is reported unless a sink is registered.
+//!
+//! Records carry a hash of the message rather than the message itself:
+//! messages quote names and literals from the script, and aggregating on the
+//! hash groups identical failures without shipping script text. The hash is
+//! FNV-1a, so it is the same on every platform and in every build.
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::draft::Trap;
+
+/// Where an error was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorOrigin {
+    Compile,
+    Runtime,
+}
+
+/// One compile diagnostic or runtime trap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ErrorRecord<'a> {
+    /// The script's name as the host knows it: its path for compiles, the
+    /// name given to the interpreter for traps.
+    pub script: &'a str,
+    pub origin: ErrorOrigin,
+    /// Always [`Severity::Error`] for traps.
+    pub severity: Severity,
+    /// The diagnostic's code; traps have none.
+    pub code: Option<&'static str>,
+    pub message_hash: u64,
+    /// Zero-based line the error starts on.
+    pub line: usize,
+}
+
+impl<'a> ErrorRecord<'a> {
+    pub fn from_diagnostic(script: &'a str, diagnostic: &Diagnostic) -> Self {
+        Self {
+            script,
+            origin: ErrorOrigin::Compile,
+            severity: diagnostic.severity,
+            code: diagnostic.code,
+            message_hash: hash_message(&diagnostic.message),
+            line: diagnostic.span.start.line,
+        }
+    }
+
+    pub fn from_trap(script: &'a str, trap: &Trap) -> Self {
+        Self {
+            script,
+            origin: ErrorOrigin::Runtime,
+            severity: Severity::Error,
+            code: None,
+            message_hash: hash_message(&trap.message),
+            line: trap.span.start.line,
+        }
+    }
+}
+
+/// Receives error records. Compiles report from worker threads, so sinks
+/// must be shareable between threads.
+pub trait ErrorSink: Send + Sync {
+    fn record(&self, record: &ErrorRecord<'_>);
+}
+
+/// The 64-bit FNV-1a hash of `message`.
+pub fn hash_message(message: &str) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    message.bytes().fold(OFFSET, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
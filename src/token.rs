@@ -0,0 +1,251 @@
+//! Tokens produced by the lexer.
+
+use std::fmt;
+
+use crate::span::Span;
+
+/// A suffix on a numeric literal: `10u`, `5i`, `1.5f`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NumSuffix {
+    U,
+    I,
+    F,
+}
+
+impl NumSuffix {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NumSuffix::U => "u",
+            NumSuffix::I => "i",
+            NumSuffix::F => "f",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Ident(String),
+    /// Digits of an integer literal, without the suffix.
+    Int {
+        raw: String,
+        suffix: Option<NumSuffix>,
+    },
+    /// Text of a float literal (`1.5`), without the suffix.
+    Float {
+        raw: String,
+        suffix: Option<NumSuffix>,
+    },
+    /// A string literal with escapes already processed.
+    Str(String),
+
+    // Keywords.
+    Let,
+    Mut,
+    Const,
+    Fn,
+    Struct,
+    Enum,
+    If,
+    Elif,
+    Else,
+    While,
+    For,
+    In,
+    Return,
+    Yield,
+    As,
+    True,
+    False,
+    None,
+
+    // Punctuation.
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Hash,
+    Comma,
+    Semi,
+    Colon,
+    /// `::` between an enum and its variant.
+    ColonColon,
+    /// `->` before a return type.
+    Arrow,
+    /// `.` before a field name.
+    Dot,
+    /// `..` between the bounds of a range.
+    DotDot,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Bang,
+    Eq,
+    EqEq,
+    NotEq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    AndAnd,
+    OrOr,
+    /// `?` after an optional type.
+    Question,
+    /// `??` between an optional value and its default.
+    QuestionQuestion,
+
+    /// Whitespace within a line. Only produced in trivia mode.
+    Whitespace(String),
+    /// A `//` comment, including the slashes. Only produced in trivia mode.
+    Comment(String),
+    /// Text the lexer rejected; its error has been reported. Only produced
+    /// in trivia mode.
+    Unknown(String),
+
+    /// End of a source line. Lines are stored without terminators, so these
+    /// are virtual: the span is an empty span at the end of the line.
+    Newline,
+    Eof,
+}
+
+impl TokenKind {
+    pub fn keyword(s: &str) -> Option<TokenKind> {
+        Some(match s {
+            "let" => TokenKind::Let,
+            "mut" => TokenKind::Mut,
+            "const" => TokenKind::Const,
+            "fn" => TokenKind::Fn,
+            "struct" => TokenKind::Struct,
+            "enum" => TokenKind::Enum,
+            "if" => TokenKind::If,
+            "elif" => TokenKind::Elif,
+            "else" => TokenKind::Else,
+            "while" => TokenKind::While,
+            "for" => TokenKind::For,
+            "in" => TokenKind::In,
+            "return" => TokenKind::Return,
+            "yield" => TokenKind::Yield,
+            "as" => TokenKind::As,
+            "true" => TokenKind::True,
+            "false" => TokenKind::False,
+            "none" => TokenKind::None,
+            _ => return None,
+        })
+    }
+
+    /// Whitespace, comments and rejected text: everything the parser skips.
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::Whitespace(_) | TokenKind::Comment(_) | TokenKind::Unknown(_)
+        )
+    }
+
+    pub fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::Let
+                | TokenKind::Mut
+                | TokenKind::Const
+                | TokenKind::Fn
+                | TokenKind::Struct
+                | TokenKind::Enum
+                | TokenKind::If
+                | TokenKind::Elif
+                | TokenKind::Else
+                | TokenKind::While
+                | TokenKind::For
+                | TokenKind::In
+                | TokenKind::Return
+                | TokenKind::Yield
+                | TokenKind::As
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::None
+        )
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            TokenKind::Ident(name) => return write!(f, "identifier `{name}`"),
+            TokenKind::Int { raw, suffix } | TokenKind::Float { raw, suffix } => {
+                let suffix = suffix.map(NumSuffix::as_str).unwrap_or("");
+                return write!(f, "literal `{raw}{suffix}`");
+            }
+            TokenKind::Str(_) => "string literal",
+            TokenKind::Let => "`let`",
+            TokenKind::Mut => "`mut`",
+            TokenKind::Const => "`const`",
+            TokenKind::Fn => "`fn`",
+            TokenKind::Struct => "`struct`",
+            TokenKind::Enum => "`enum`",
+            TokenKind::If => "`if`",
+            TokenKind::Elif => "`elif`",
+            TokenKind::Else => "`else`",
+            TokenKind::While => "`while`",
+            TokenKind::For => "`for`",
+            TokenKind::In => "`in`",
+            TokenKind::Return => "`return`",
+            TokenKind::Yield => "`yield`",
+            TokenKind::As => "`as`",
+            TokenKind::True => "`true`",
+            TokenKind::False => "`false`",
+            TokenKind::None => "`none`",
+            TokenKind::LParen => "`(`",
+            TokenKind::RParen => "`)`",
+            TokenKind::LBrace => "`{`",
+            TokenKind::RBrace => "`}`",
+            TokenKind::LBracket => "`[`",
+            TokenKind::RBracket => "`]`",
+            TokenKind::Hash => "`#`",
+            TokenKind::Comma => "`,`",
+            TokenKind::Semi => "`;`",
+            TokenKind::Colon => "`:`",
+            TokenKind::ColonColon => "`::`",
+            TokenKind::Arrow => "`->`",
+            TokenKind::Dot => "`.`",
+            TokenKind::DotDot => "`..`",
+            TokenKind::Plus => "`+`",
+            TokenKind::Minus => "`-`",
+            TokenKind::Star => "`*`",
+            TokenKind::Slash => "`/`",
+            TokenKind::Percent => "`%`",
+            TokenKind::Bang => "`!`",
+            TokenKind::Eq => "`=`",
+            TokenKind::EqEq => "`==`",
+            TokenKind::NotEq => "`!=`",
+            TokenKind::Lt => "`<`",
+            TokenKind::Le => "`<=`",
+            TokenKind::Gt => "`>`",
+            TokenKind::Ge => "`>=`",
+            TokenKind::AndAnd => "`&&`",
+            TokenKind::OrOr => "`||`",
+            TokenKind::Question => "`?`",
+            TokenKind::QuestionQuestion => "`??`",
+            TokenKind::Whitespace(_) => "whitespace",
+            TokenKind::Comment(_) => "comment",
+            TokenKind::Unknown(text) => return write!(f, "unexpected `{text}`"),
+            TokenKind::Newline => "end of line",
+            TokenKind::Eof => "end of file",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+impl Token {
+    pub fn new(kind: TokenKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
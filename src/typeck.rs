@@ -0,0 +1,1120 @@
+//! Type checking: the checks draft mode makes at runtime, made up front on
+//! [`ast2`](crate::ast2).
+//!
+//! Every expression gets a [`Type`], recorded in [`Types`], and every place
+//! that needs a particular type checks it: operands, conditions, `let` and
+//! parameter annotations, arguments, return values, assignments, fields and
+//! the branches of an `if` expression. The rules are draft mode's. Numbers
+//! of different types never mix without a cast, `+` also joins strings, and
+//! comparisons need operands of one type.
+//!
+//! That includes literals: a literal's type is fixed by how it is written,
+//! never by where it is used. `10` and `10i` are `int`s, `10u` is a `uint`,
+//! and `10f`, `10.0` and `10.0f` are `float`s; `10.5u` is an error, and so
+//! are an int literal too large for its type and a negated `uint` literal.
+//! Where a bare `10` is used as a `uint` or `float`, the diagnostic says
+//! which spelling fits.
+//!
+//! Annotations are optional: a local or constant declared without one has
+//! the type of its initializer, so `let speed = 10.0;` declares a `float`.
+//! Literals without a suffix are `int` or `float`, and names unpacked from a
+//! tuple take the types of its values, one name per value.
+//!
+//! Some types cannot be known here: the host's globals and functions come
+//! without types. These get [`Type::Error`], which fits everywhere, as do
+//! names and expressions that already had an error reported, so nothing is
+//! reported twice, and so does everything inferred from them.
+//!
+//! Where an annotation or parameter asks for an enum, an `int` fits too:
+//! hosts hand over enums as their backing values, and draft mode looks the
+//! variant up when the value arrives.
+
+use std::collections::HashMap;
+use std::ops::Index;
+
+use crate::ast1::{BinaryOp, Block, ExprId, FloatLit, Ident, IntLit, Projection, StmtId, UnaryOp};
+use crate::ast2::*;
+use crate::diagnostic::Diagnostic;
+use crate::scope::{SymbolId, SymbolKind, SymbolTable};
+use crate::span::Span;
+use crate::token::NumSuffix;
+
+/// The types the checker found.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Types {
+    exprs: Vec<Type>,
+    /// Locals, parameters, loop variables and constants.
+    symbols: HashMap<SymbolId, Type>,
+}
+
+impl Types {
+    /// The type of the value `name` refers to, if it is a local, parameter,
+    /// loop variable or constant.
+    pub fn symbol(&self, symbol: SymbolId) -> Option<&Type> {
+        self.symbols.get(&symbol)
+    }
+}
+
+impl Index<ExprId> for Types {
+    type Output = Type;
+
+    fn index(&self, id: ExprId) -> &Type {
+        &self.exprs[id.index()]
+    }
+}
+
+/// Checks the types in `program`, which should have been resolved without
+/// errors.
+pub fn check(program: &Program) -> (Types, Vec<Diagnostic>) {
+    let mut checker = Checker {
+        ast: &program.arena,
+        symbols: &program.symbols,
+        fns: HashMap::new(),
+        structs: HashMap::new(),
+        types: Types {
+            exprs: vec![Type::Error; program.arena.expr_count()],
+            symbols: HashMap::new(),
+        },
+        diagnostics: Vec::new(),
+        current_fn: None,
+        yields: Vec::new(),
+    };
+    for &stmt in &program.stmts {
+        match &program.arena[stmt] {
+            Stmt::Fn(f) => {
+                checker.fns.insert(f.symbol, f);
+                for param in &f.params {
+                    checker.types.symbols.insert(param.symbol, param.ty.clone());
+                }
+            }
+            Stmt::Struct(s) => {
+                checker.structs.insert(s.symbol, s);
+            }
+            _ => {}
+        }
+    }
+    // Functions can use constants declared after them, so constants are
+    // checked first, in order: pass1 makes sure their initializers only use
+    // earlier ones.
+    for &stmt in &program.stmts {
+        if let Stmt::Const(c) = &program.arena[stmt] {
+            let ty = checker.expr(c.value);
+            let ty = match &c.ty {
+                Some(annotated) => {
+                    checker.expect(annotated, &ty, c.value);
+                    annotated.clone()
+                }
+                None => ty,
+            };
+            checker.types.symbols.insert(c.symbol, ty);
+        }
+    }
+    for &stmt in &program.stmts {
+        checker.stmt(stmt);
+    }
+    (checker.types, checker.diagnostics)
+}
+
+struct Checker<'a> {
+    ast: &'a AstArena,
+    symbols: &'a SymbolTable,
+    fns: HashMap<SymbolId, &'a FnDecl>,
+    structs: HashMap<SymbolId, &'a StructDecl>,
+    types: Types,
+    diagnostics: Vec<Diagnostic>,
+    /// The function whose body is being checked.
+    current_fn: Option<&'a FnDecl>,
+    /// For each `if` expression being checked, innermost last, the type of
+    /// the first value yielded to it.
+    yields: Vec<Option<Type>>,
+}
+
+impl<'a> Checker<'a> {
+    fn stmt(&mut self, id: StmtId) {
+        let ast = self.ast;
+        match &ast[id] {
+            Stmt::Let(s) => self.check_let(s),
+            // Checked before everything else.
+            Stmt::Const(_) => {}
+            Stmt::Fn(f) => {
+                for param in &f.params {
+                    let Some(default) = param.default else {
+                        continue;
+                    };
+                    let ty = self.expr(default);
+                    if !fits(&param.ty, &ty) {
+                        let message = format!(
+                            "parameter `{}` of `{}` expects `{}`, found {}",
+                            self.symbols[param.symbol].name,
+                            self.symbols[f.symbol].name,
+                            self.name(&param.ty),
+                            self.found(&ty, default)
+                        );
+                        self.wrong_value(default, &param.ty, message, None);
+                    }
+                }
+                let outer = self.current_fn.replace(f);
+                let yields = std::mem::take(&mut self.yields);
+                self.block(&f.body);
+                self.yields = yields;
+                self.current_fn = outer;
+            }
+            Stmt::Struct(_) | Stmt::Enum(_) | Stmt::Error(_) => {}
+            Stmt::Assign(s) => self.assign(s),
+            Stmt::Return(s) => {
+                let values: Vec<Type> = s.values.iter().map(|&v| self.expr(v)).collect();
+                self.check_return(&s.values, &values);
+            }
+            Stmt::Yield(s) => {
+                let values: Vec<Type> = s.values.iter().map(|&v| self.expr(v)).collect();
+                self.check_yield(s.span, &s.values, values);
+            }
+            Stmt::If(s) => {
+                self.cond(s.cond);
+                self.block(&s.then_block);
+                for elif in &s.elifs {
+                    self.cond(elif.cond);
+                    self.block(&elif.block);
+                }
+                if let Some(block) = &s.else_block {
+                    self.block(block);
+                }
+            }
+            Stmt::While(s) => {
+                self.cond(s.cond);
+                self.block(&s.body);
+            }
+            Stmt::For(s) => {
+                let ty = self.range(s.range);
+                self.types.symbols.insert(s.var, ty);
+                self.block(&s.body);
+            }
+            Stmt::Expr(s) => match &ast[s.expr] {
+                // A call in statement position may return nothing.
+                Expr::Call(call) => {
+                    let ty = self.call(call);
+                    self.types.exprs[s.expr.index()] = ty;
+                }
+                _ => {
+                    self.expr(s.expr);
+                }
+            },
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        for &stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn check_let(&mut self, s: &LetStmt) {
+        let ty = self.expr(s.value);
+        let value_span = self.ast[s.value].span();
+        // pass1 compares the names unpacked from a call with what the
+        // function returns.
+        let unpacked_call = matches!(s.pattern, Pattern::Tuple(_))
+            && matches!(&self.ast[s.value], Expr::Call(call)
+                if self.symbols[call.callee.symbol].kind == SymbolKind::Fn);
+        let (bound, source) = match &s.ty {
+            Some((annotated, ty_span)) => {
+                let ty_span = *ty_span;
+                match arity(annotated, &ty) {
+                    Some((expected, found)) => {
+                        if !(unpacked_call && arity_of(&s.pattern) == expected) {
+                            self.diagnostics.push(
+                                Diagnostic::error(
+                                    value_span,
+                                    format!("expected {}, found {}", count(expected), count(found)),
+                                )
+                                .with_code("E0508")
+                                .with_label(ty_span, format!("{} expected here", count(expected))),
+                            );
+                        }
+                    }
+                    None => self.expect(annotated, &ty, s.value),
+                }
+                (annotated.clone(), Some((ty_span, "annotated")))
+            }
+            None if unpacked_call => (ty, None),
+            None => (ty, Some((value_span, "given"))),
+        };
+        self.bind(&s.pattern, bound, source);
+    }
+
+    /// Binds the names of `pattern` to the parts of a `ty` value. For a
+    /// tuple pattern, `source` is where the values come from, if a wrong
+    /// number of them should be reported.
+    fn bind(&mut self, pattern: &Pattern, ty: Type, source: Option<(Span, &str)>) {
+        let names = match pattern {
+            Pattern::Name(name) => {
+                self.types.symbols.insert(name.symbol, ty);
+                return;
+            }
+            Pattern::Tuple(names) => names,
+        };
+        let types = match ty {
+            Type::Tuple(types) if types.len() == names.len() => types,
+            Type::Error => vec![Type::Error; names.len()],
+            ty => {
+                if let Some((span, verb)) = source {
+                    let found = match &ty {
+                        Type::Tuple(types) => count(types.len()),
+                        ty => format!("a single `{}`", self.name(ty)),
+                    };
+                    let message = format!("cannot destructure {found} into {} names", names.len());
+                    self.diagnostics.push(
+                        Diagnostic::error(pattern.span(), message)
+                            .with_code("E0508")
+                            .with_label(span, format!("{found} {verb} here")),
+                    );
+                }
+                vec![Type::Error; names.len()]
+            }
+        };
+        for (name, ty) in names.iter().zip(types) {
+            self.bind(name, ty, None);
+        }
+    }
+
+    fn assign(&mut self, s: &AssignStmt) {
+        let target = &self.symbols[s.target.symbol].name;
+        let mut ty = self
+            .types
+            .symbol(s.target.symbol)
+            .cloned()
+            .unwrap_or(Type::Error);
+        for step in &s.path {
+            ty = match step {
+                Projection::Index(index) => self.index(ty, *index),
+                Projection::Field(field) => self.field(ty, field),
+            };
+        }
+        let value = self.expr(s.value);
+        if fits(&ty, &value) {
+            return;
+        }
+        let part = match s.path.last() {
+            None => String::new(),
+            Some(Projection::Field(field)) => format!("field `{}` of ", field.name),
+            Some(Projection::Index(_)) => "an element of ".to_string(),
+        };
+        let message = format!(
+            "cannot assign {} to {part}`{target}`, which holds `{}`",
+            self.found(&value, s.value),
+            self.name(&ty)
+        );
+        self.wrong_value(s.value, &ty, message, None);
+    }
+
+    /// A `return` in a function gives values of the declared types. Their
+    /// number is checked by pass1; the top level may return anything.
+    fn check_return(&mut self, exprs: &[ExprId], values: &[Type]) {
+        let Some(f) = self.current_fn else {
+            return;
+        };
+        let declared = match &f.ret {
+            Some(Type::Tuple(types)) => types.as_slice(),
+            Some(Type::Unit) | None => &[],
+            Some(ty) => std::slice::from_ref(ty),
+        };
+        if declared.len() != values.len() {
+            return;
+        }
+        for ((expected, found), &expr) in declared.iter().zip(values).zip(exprs) {
+            if !fits(expected, found) {
+                let message = format!(
+                    "`{}` returns `{}`, but this value is {}",
+                    self.symbols[f.symbol].name,
+                    self.name(expected),
+                    self.found(found, expr)
+                );
+                self.wrong_value(expr, expected, message, None);
+            }
+        }
+    }
+
+    /// Every `yield` to one `if` expression gives values of one type.
+    fn check_yield(&mut self, span: Span, exprs: &[ExprId], values: Vec<Type>) {
+        let ty = match values.len() {
+            0 => {
+                if !self.yields.is_empty() {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            span,
+                            "this `yield` gives `()`, but the `if` needs a value",
+                        )
+                        .with_code("E0504"),
+                    );
+                }
+                return;
+            }
+            1 => values.into_iter().next().unwrap_or(Type::Error),
+            _ => Type::Tuple(values),
+        };
+        let Some(frame) = self.yields.last_mut() else {
+            return;
+        };
+        let Some(first) = frame.clone() else {
+            *frame = Some(ty);
+            return;
+        };
+        if !same(&first, &ty) {
+            let message = format!(
+                "the branches of this `if` yield different types: `{}` and `{}`",
+                self.name(&first),
+                self.name(&ty)
+            );
+            let span = exprs
+                .iter()
+                .map(|&e| self.ast[e].span())
+                .reduce(Span::to)
+                .unwrap_or(span);
+            self.mismatch(span, message);
+        }
+    }
+
+    /// The type of the value of `id`. A call that returns nothing is
+    /// reported, except as a statement.
+    fn expr(&mut self, id: ExprId) -> Type {
+        let ast = self.ast;
+        let ty = match &ast[id] {
+            Expr::Int(lit) => self.int_lit(lit, false),
+            Expr::Float(lit) => match lit.suffix {
+                None | Some(NumSuffix::F) => Type::Float,
+                Some(suffix) => {
+                    let message = format!("float literal cannot have suffix `{}`", suffix.as_str());
+                    let whole = lit.raw.split('.').next().unwrap_or(&lit.raw);
+                    self.diagnostics.push(
+                        Diagnostic::error(lit.span, message)
+                            .with_code("E0509")
+                            .with_note(format!(
+                                "only whole numbers take `{}`; write `{}` for a `float`, or `{whole}{}`",
+                                suffix.as_str(),
+                                lit.raw,
+                                suffix.as_str()
+                            )),
+                    );
+                    Type::Error
+                }
+            },
+            Expr::Str(_) => Type::Str,
+            Expr::Bool(_) => Type::Bool,
+            Expr::None(_) => Type::Optional(Box::new(Type::Error)),
+            Expr::Name(name) => self
+                .types
+                .symbol(name.symbol)
+                .cloned()
+                .unwrap_or(Type::Error),
+            Expr::Call(call) => match self.call(call) {
+                Type::Unit => {
+                    let message = format!(
+                        "`{}` returns `()`, which is not a value",
+                        self.symbols[call.callee.symbol].name
+                    );
+                    self.diagnostics
+                        .push(Diagnostic::error(call.span, message).with_code("E0504"));
+                    Type::Error
+                }
+                ty => ty,
+            },
+            Expr::Unary(e) => {
+                let operand = match &ast[e.operand] {
+                    // Negated as it is read, so that `-9223372036854775808`
+                    // fits.
+                    Expr::Int(lit) if e.op == UnaryOp::Neg => {
+                        let ty = self.int_lit(lit, true);
+                        self.types.exprs[e.operand.index()] = ty.clone();
+                        ty
+                    }
+                    _ => self.expr(e.operand),
+                };
+                match (e.op, &operand) {
+                    (_, Type::Error) => Type::Error,
+                    (UnaryOp::Neg, Type::Int | Type::Float) | (UnaryOp::Not, Type::Bool) => operand,
+                    (UnaryOp::Neg, Type::UInt) if self.uint_lit(e.operand) => {
+                        self.diagnostics.push(
+                            Diagnostic::error(e.span, "a `uint` literal cannot be negative")
+                                .with_code("E0510")
+                                .with_note("drop the `u` suffix for an `int`"),
+                        );
+                        Type::Error
+                    }
+                    (op, _) => {
+                        let message = format!(
+                            "cannot apply `{}` to `{}`",
+                            op.as_str(),
+                            self.name(&operand)
+                        );
+                        self.diagnostics
+                            .push(Diagnostic::error(e.span, message).with_code("E0502"));
+                        Type::Error
+                    }
+                }
+            }
+            Expr::Cast(e) => {
+                let from = self.expr(e.expr);
+                let numeric = matches!(
+                    from,
+                    Type::Int | Type::UInt | Type::Float | Type::Enum(_) | Type::Error
+                );
+                if !numeric {
+                    let message = format!(
+                        "cannot cast `{}` to `{}`",
+                        self.name(&from),
+                        self.name(&e.ty)
+                    );
+                    self.diagnostics
+                        .push(Diagnostic::error(e.span, message).with_code("E0505"));
+                }
+                e.ty.clone()
+            }
+            Expr::Binary(e) => self.binary(e.op, e.lhs, e.rhs, e.span),
+            Expr::Paren(e) => self.expr(e.inner),
+            Expr::If(e) => {
+                self.cond(e.cond);
+                self.yields.push(None);
+                self.block(&e.then_block);
+                for elif in &e.elifs {
+                    self.cond(elif.cond);
+                    self.block(&elif.block);
+                }
+                if let Some(block) = &e.else_block {
+                    self.block(block);
+                }
+                self.yields.pop().flatten().unwrap_or(Type::Error)
+            }
+            // Only a `for` header has a range, and checks it itself.
+            Expr::Range(_) => Type::Error,
+            Expr::Array(e) => {
+                let mut elem: Option<Type> = None;
+                let mut saw_none = false;
+                for &expr in &e.elems {
+                    let ty = self.expr(expr);
+                    match &elem {
+                        _ if is_none(&ty) => saw_none = true,
+                        Some(first) if !same(first, &ty) => {
+                            let message = format!(
+                                "array elements must have one type: expected `{}`, found `{}`",
+                                self.name(first),
+                                self.name(&ty)
+                            );
+                            self.mismatch(ast[expr].span(), message);
+                        }
+                        Some(first) if *first != Type::Error => {}
+                        _ => elem = Some(ty),
+                    }
+                }
+                Type::Array(Box::new(with_none(elem, saw_none)))
+            }
+            Expr::Map(e) => {
+                let mut key: Option<Type> = None;
+                let mut value: Option<Type> = None;
+                let mut saw_none = false;
+                for entry in &e.entries {
+                    let ty = self.expr(entry.key);
+                    if !matches!(ty, Type::Int | Type::Str | Type::Error) {
+                        let message = format!(
+                            "map keys must be `str` or `int`, found `{}`",
+                            self.name(&ty)
+                        );
+                        self.wrong_kind(ast[entry.key].span(), message);
+                    } else if let Some(first) = key.as_ref().filter(|k| !same(k, &ty)) {
+                        let message = format!(
+                            "map keys must have one type: expected `{}`, found `{}`",
+                            self.name(first),
+                            self.name(&ty)
+                        );
+                        self.mismatch(ast[entry.key].span(), message);
+                    } else if key.as_ref().is_none_or(|k| *k == Type::Error) {
+                        key = Some(ty);
+                    }
+                    let ty = self.expr(entry.value);
+                    match &value {
+                        _ if is_none(&ty) => saw_none = true,
+                        Some(first) if !same(first, &ty) => {
+                            let message = format!(
+                                "map values must have one type: expected `{}`, found `{}`",
+                                self.name(first),
+                                self.name(&ty)
+                            );
+                            self.mismatch(ast[entry.value].span(), message);
+                        }
+                        Some(first) if *first != Type::Error => {}
+                        _ => value = Some(ty),
+                    }
+                }
+                Type::Map(
+                    Box::new(key.unwrap_or(Type::Error)),
+                    Box::new(with_none(value, saw_none)),
+                )
+            }
+            Expr::Index(e) => {
+                let base = self.expr(e.base);
+                self.index(base, e.index)
+            }
+            Expr::Struct(e) => self.struct_lit(e),
+            Expr::Field(e) => {
+                let base = self.expr(e.base);
+                self.field(base, &e.field)
+            }
+            Expr::Variant(e) => match self.symbols[e.enum_name.symbol].kind {
+                SymbolKind::Enum => Type::Enum(e.enum_name.symbol),
+                _ => Type::Error,
+            },
+            Expr::Error(_) => Type::Error,
+        };
+        self.types.exprs[id.index()] = ty.clone();
+        ty
+    }
+
+    /// The type of what `call` returns: [`Type::Unit`] if nothing.
+    fn call(&mut self, call: &CallExpr) -> Type {
+        let symbol = &self.symbols[call.callee.symbol];
+        match symbol.kind {
+            SymbolKind::Fn => {
+                let Some(&f) = self.fns.get(&call.callee.symbol) else {
+                    return Type::Error;
+                };
+                for (&arg, param) in call.args.iter().zip(&f.params) {
+                    let ty = self.expr(arg);
+                    if !fits(&param.ty, &ty) {
+                        let message = format!(
+                            "parameter `{}` of `{}` expects `{}`, found {}",
+                            self.symbols[param.symbol].name,
+                            symbol.name,
+                            self.name(&param.ty),
+                            self.found(&ty, arg)
+                        );
+                        self.wrong_value(arg, &param.ty, message, Some(param.span));
+                    }
+                }
+                // Arguments beyond the parameters were reported by pass1.
+                for &arg in call.args.iter().skip(f.params.len()) {
+                    self.expr(arg);
+                }
+                match &f.ret {
+                    None => Type::Unit,
+                    Some(ty) => ty.clone(),
+                }
+            }
+            SymbolKind::Builtin => self.builtin(call),
+            _ => {
+                for &arg in &call.args {
+                    self.expr(arg);
+                }
+                Type::Error
+            }
+        }
+    }
+
+    fn builtin(&mut self, call: &CallExpr) -> Type {
+        let name = self.symbols[call.callee.symbol].name.as_str();
+        if name == "try_call" {
+            return self.try_call(call);
+        }
+        let args: Vec<Type> = call.args.iter().map(|&arg| self.expr(arg)).collect();
+        let (params, ret): (&[Type], Type) = match name {
+            "print" => return Type::Unit,
+            "is_some" => {
+                if args.len() != 1 {
+                    self.arg_count(call, 1);
+                }
+                return Type::Bool;
+            }
+            "len" => {
+                match args.as_slice() {
+                    [Type::Str | Type::Array(_) | Type::Map(..) | Type::Error] => {}
+                    [other] => {
+                        let message = format!(
+                            "`len` expects a string, array or map, found `{}`",
+                            self.name(other)
+                        );
+                        self.builtin_error(call.span, message);
+                    }
+                    _ => self.arg_count(call, 1),
+                }
+                return Type::Int;
+            }
+            "contains" | "starts_with" => (&[Type::Str, Type::Str], Type::Bool),
+            "substring" => (&[Type::Str, Type::Int, Type::Int], Type::Str),
+            _ => return Type::Error,
+        };
+        if args.len() != params.len() {
+            self.arg_count(call, params.len());
+        } else if !params.iter().zip(&args).all(|(p, a)| fits(p, a)) {
+            let show = |types: &[Type]| {
+                types
+                    .iter()
+                    .map(|t| self.name(t))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            let message = format!(
+                "`{name}` expects `({})`, found `({})`",
+                show(params),
+                show(&args)
+            );
+            self.builtin_error(call.span, message);
+        }
+        ret
+    }
+
+    /// The type of an int literal, after checking that its value fits,
+    /// `negated` if it is the operand of a unary `-`.
+    fn int_lit(&mut self, lit: &IntLit, negated: bool) -> Type {
+        let (ty, max) = match lit.suffix {
+            None | Some(NumSuffix::I) if negated => (Type::Int, i64::MIN.unsigned_abs()),
+            None | Some(NumSuffix::I) => (Type::Int, i64::MAX as u64),
+            Some(NumSuffix::U) => (Type::UInt, u64::MAX),
+            Some(NumSuffix::F) => return Type::Float,
+        };
+        let value: Option<u64> = lit.raw.parse().ok();
+        if value.is_none_or(|v| v > max) {
+            let name = self.name(&ty);
+            let mut note = match ty {
+                Type::Int if negated => format!("`{name}` holds down to {}", i64::MIN),
+                _ => format!("`{name}` holds up to {max}"),
+            };
+            if value.is_some() && !negated {
+                note.push_str(&format!("; write `{}u` for a `uint`", lit.raw));
+            }
+            self.diagnostics.push(
+                Diagnostic::error(lit.span, format!("integer literal too large for `{name}`"))
+                    .with_code("E0510")
+                    .with_note(note),
+            );
+        }
+        ty
+    }
+
+    /// Whether `expr` is an int literal with the suffix `u`, looking
+    /// through parentheses.
+    fn uint_lit(&self, expr: ExprId) -> bool {
+        match &self.ast[expr] {
+            Expr::Paren(e) => self.uint_lit(e.inner),
+            Expr::Int(lit) => lit.suffix == Some(NumSuffix::U),
+            _ => false,
+        }
+    }
+
+    /// `try_call(f(x))` gives what `f` returns, or `none` if `f` is missing.
+    fn try_call(&mut self, call: &CallExpr) -> Type {
+        let ast = self.ast;
+        let [arg] = call.args.as_slice() else {
+            for &arg in &call.args {
+                self.expr(arg);
+            }
+            self.arg_count(call, 1);
+            return Type::Error;
+        };
+        let Expr::Call(inner) = &ast[*arg] else {
+            self.expr(*arg);
+            self.builtin_error(ast[*arg].span(), "`try_call` expects a call".to_string());
+            return Type::Error;
+        };
+        let ty = match self.call(inner) {
+            Type::Unit => Type::Unit,
+            ty @ (Type::Optional(_) | Type::Error) => ty,
+            ty => Type::Optional(Box::new(ty)),
+        };
+        self.types.exprs[arg.index()] = ty.clone();
+        ty
+    }
+
+    fn binary(&mut self, op: BinaryOp, lhs: ExprId, rhs: ExprId, span: Span) -> Type {
+        let left = self.expr(lhs);
+        let right = self.expr(rhs);
+        match op {
+            BinaryOp::And | BinaryOp::Or => {
+                for (ty, expr) in [(&left, lhs), (&right, rhs)] {
+                    if !fits(&Type::Bool, ty) {
+                        let message = format!(
+                            "operands of `{}` must be `bool`, found `{}`",
+                            op.as_str(),
+                            self.name(ty)
+                        );
+                        self.wrong_kind(self.ast[expr].span(), message);
+                    }
+                }
+                return Type::Bool;
+            }
+            BinaryOp::Coalesce => {
+                let inner = match left {
+                    Type::Optional(inner) => *inner,
+                    ty => ty,
+                };
+                if !fits(&inner, &right) {
+                    let message = format!(
+                        "the default of `??` must be `{}`, found `{}`",
+                        self.name(&inner),
+                        self.name(&right)
+                    );
+                    self.mismatch(self.ast[rhs].span(), message);
+                }
+                return match inner {
+                    Type::Error => right,
+                    inner => inner,
+                };
+            }
+            _ => {}
+        }
+        if left == Type::Error || right == Type::Error {
+            return if op.is_comparison() {
+                Type::Bool
+            } else {
+                Type::Error
+            };
+        }
+        let ok = match op {
+            BinaryOp::Eq | BinaryOp::Ne => comparable(&left, &right),
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                left == right && matches!(left, Type::Int | Type::UInt | Type::Float)
+            }
+            BinaryOp::Add if left == Type::Str => right == Type::Str,
+            _ => left == right && matches!(left, Type::Int | Type::UInt | Type::Float),
+        };
+        if !ok {
+            let message = format!(
+                "cannot apply `{}` to `{}` and `{}`",
+                op.as_str(),
+                self.name(&left),
+                self.name(&right)
+            );
+            let mut diagnostic = Diagnostic::error(span, message).with_code("E0502");
+            let note = self
+                .literal_note(&left, rhs)
+                .or_else(|| self.literal_note(&right, lhs));
+            if let Some(note) = note {
+                diagnostic = diagnostic.with_note(note);
+            }
+            self.diagnostics.push(diagnostic);
+            return if op.is_comparison() {
+                Type::Bool
+            } else {
+                Type::Error
+            };
+        }
+        if op.is_comparison() { Type::Bool } else { left }
+    }
+
+    fn cond(&mut self, cond: ExprId) {
+        let ty = self.expr(cond);
+        if !fits(&Type::Bool, &ty) {
+            let message = format!("condition must be `bool`, found `{}`", self.name(&ty));
+            self.wrong_kind(self.ast[cond].span(), message);
+        }
+    }
+
+    /// The bounds of a `for` range, both `int` or both `uint`, and the type
+    /// of the loop variable.
+    fn range(&mut self, range: ExprId) -> Type {
+        let Expr::Range(e) = &self.ast[range] else {
+            return self.expr(range);
+        };
+        let start = self.expr(e.start);
+        let end = self.expr(e.end);
+        match (&start, &end) {
+            (Type::Int, Type::Int) | (Type::UInt, Type::UInt) => start,
+            (Type::Error, Type::Int | Type::UInt | Type::Error) => end,
+            (Type::Int | Type::UInt, Type::Error) => start,
+            _ => {
+                let message = format!(
+                    "range bounds must both be `int` or both `uint`, found `{}` and `{}`",
+                    self.name(&start),
+                    self.name(&end)
+                );
+                self.wrong_kind(e.span, message);
+                Type::Error
+            }
+        }
+    }
+
+    /// The type of an element of a `base` value at `index`.
+    fn index(&mut self, base: Type, index: ExprId) -> Type {
+        let ty = self.expr(index);
+        let span = self.ast[index].span();
+        match base {
+            Type::Array(elem) => {
+                if !matches!(ty, Type::Int | Type::UInt | Type::Error) {
+                    let message = format!(
+                        "array index must be `int` or `uint`, found `{}`",
+                        self.name(&ty)
+                    );
+                    self.wrong_kind(span, message);
+                }
+                *elem
+            }
+            Type::Map(key, value) => {
+                if !fits(&key, &ty) {
+                    let message = format!(
+                        "this map's keys are `{}`, found `{}`",
+                        self.name(&key),
+                        self.name(&ty)
+                    );
+                    self.mismatch(span, message);
+                }
+                *value
+            }
+            Type::Error => Type::Error,
+            other => {
+                let message = format!("cannot index into `{}`", self.name(&other));
+                self.diagnostics
+                    .push(Diagnostic::error(span, message).with_code("E0506"));
+                Type::Error
+            }
+        }
+    }
+
+    /// The type of the field `field` of a `base` value.
+    fn field(&mut self, base: Type, field: &Ident) -> Type {
+        let message = match base {
+            Type::Struct(symbol) => {
+                let Some(decl) = self.structs.get(&symbol) else {
+                    return Type::Error;
+                };
+                if let Some(f) = decl.fields.iter().find(|f| f.name.name == field.name) {
+                    return f.ty.clone();
+                }
+                format!(
+                    "struct `{}` has no field `{}`",
+                    self.symbols[symbol].name, field.name
+                )
+            }
+            Type::Error => return Type::Error,
+            other => format!(
+                "cannot access field `{}` of `{}`",
+                field.name,
+                self.name(&other)
+            ),
+        };
+        self.diagnostics
+            .push(Diagnostic::error(field.span, message).with_code("E0506"));
+        Type::Error
+    }
+
+    /// A struct literal. Which fields it gives was checked by pass1.
+    fn struct_lit(&mut self, e: &StructExpr) -> Type {
+        let decl = self.structs.get(&e.name.symbol).copied();
+        for init in &e.fields {
+            let ty = self.expr(init.value);
+            let Some(field) =
+                decl.and_then(|d| d.fields.iter().find(|f| f.name.name == init.name.name))
+            else {
+                continue;
+            };
+            if !fits(&field.ty, &ty) {
+                let message = format!(
+                    "field `{}` of `{}` expects `{}`, found {}",
+                    init.name.name,
+                    self.symbols[e.name.symbol].name,
+                    self.name(&field.ty),
+                    self.found(&ty, init.value)
+                );
+                self.wrong_value(init.value, &field.ty, message, Some(field.span));
+            }
+        }
+        match decl {
+            Some(_) => Type::Struct(e.name.symbol),
+            None => Type::Error,
+        }
+    }
+
+    /// Reports `expr`, of type `found`, unless it fits `expected`.
+    fn expect(&mut self, expected: &Type, found: &Type, expr: ExprId) {
+        if !fits(expected, found) {
+            let message = format!(
+                "expected `{}`, found {}",
+                self.name(expected),
+                self.found(found, expr)
+            );
+            self.wrong_value(expr, expected, message, None);
+        }
+    }
+
+    /// Reports `expr` for not fitting `expected`, with the declaration
+    /// that asks for `expected` if there is one. A literal that would fit
+    /// if written differently gets a note saying how.
+    fn wrong_value(
+        &mut self,
+        expr: ExprId,
+        expected: &Type,
+        message: String,
+        declared: Option<Span>,
+    ) {
+        let mut diagnostic = Diagnostic::error(self.ast[expr].span(), message).with_code("E0501");
+        if let Some(span) = declared {
+            diagnostic = diagnostic.with_label(span, "declared here");
+        }
+        if let Some(note) = self.literal_note(expected, expr) {
+            diagnostic = diagnostic.with_note(note);
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// How to write the int literal `expr` as a value of type `expected`,
+    /// if it is one without a suffix and `expected` is a number.
+    fn literal_note(&self, expected: &Type, expr: ExprId) -> Option<String> {
+        let Expr::Int(lit) = self.literal(expr)? else {
+            return None;
+        };
+        let expected = match expected {
+            Type::Optional(inner) => inner,
+            ty => ty,
+        };
+        match expected {
+            Type::UInt => Some(format!("write `{}u` for a `uint` literal", lit.raw)),
+            Type::Float => Some(format!("write `{}.0` for a `float` literal", lit.raw)),
+            _ => None,
+        }
+    }
+
+    /// The number literal without a suffix that `expr` is, looking through
+    /// parentheses.
+    fn literal(&self, expr: ExprId) -> Option<&'a Expr> {
+        match &self.ast[expr] {
+            Expr::Paren(e) => self.literal(e.inner),
+            e @ Expr::Int(IntLit { suffix: None, .. })
+            | e @ Expr::Float(FloatLit { suffix: None, .. }) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// `ty`, the type of `expr`, for a message: "`int`", or "`int` literal"
+    /// if `expr` is a literal whose type comes from having no suffix.
+    fn found(&self, ty: &Type, expr: ExprId) -> String {
+        match self.literal(expr) {
+            Some(_) => format!("`{}` literal", self.name(ty)),
+            None => format!("`{}`", self.name(ty)),
+        }
+    }
+
+    fn mismatch(&mut self, span: Span, message: String) {
+        self.diagnostics
+            .push(Diagnostic::error(span, message).with_code("E0501"));
+    }
+
+    fn wrong_kind(&mut self, span: Span, message: String) {
+        self.diagnostics
+            .push(Diagnostic::error(span, message).with_code("E0503"));
+    }
+
+    fn builtin_error(&mut self, span: Span, message: String) {
+        self.diagnostics
+            .push(Diagnostic::error(span, message).with_code("E0507"));
+    }
+
+    fn arg_count(&mut self, call: &CallExpr, expected: usize) {
+        let s = if expected == 1 { "" } else { "s" };
+        let message = format!(
+            "`{}` takes {expected} argument{s}, but {} were given",
+            self.symbols[call.callee.symbol].name,
+            call.args.len()
+        );
+        self.builtin_error(call.span, message);
+    }
+
+    /// `ty` as written in annotations.
+    fn name(&self, ty: &Type) -> String {
+        match ty {
+            Type::Int => "int".to_string(),
+            Type::UInt => "uint".to_string(),
+            Type::Float => "float".to_string(),
+            Type::Bool => "bool".to_string(),
+            Type::Str => "str".to_string(),
+            Type::Unit => "()".to_string(),
+            Type::Struct(symbol) | Type::Enum(symbol) => self.symbols[*symbol].name.clone(),
+            Type::Tuple(types) => {
+                let types: Vec<String> = types.iter().map(|t| self.name(t)).collect();
+                format!("({})", types.join(", "))
+            }
+            Type::Array(elem) => format!("[{}]", self.name(elem)),
+            Type::Map(key, value) => format!("{{{}: {}}}", self.name(key), self.name(value)),
+            Type::Optional(inner) if **inner == Type::Error => "none".to_string(),
+            Type::Optional(inner) => format!("{}?", self.name(inner)),
+            Type::Error => "_".to_string(),
+        }
+    }
+}
+
+/// Whether a value of type `found` can go where `expected` is asked for.
+fn fits(expected: &Type, found: &Type) -> bool {
+    match (expected, found) {
+        (Type::Error, _) | (_, Type::Error) => true,
+        (Type::Optional(e), Type::Optional(f)) => fits(e, f),
+        (Type::Optional(e), f) => fits(e, f),
+        (Type::Enum(_), Type::Int) => true,
+        _ => same(expected, found),
+    }
+}
+
+/// Whether `a` and `b` are the same type, taking [`Type::Error`] to be any.
+fn same(a: &Type, b: &Type) -> bool {
+    match (a, b) {
+        (Type::Error, _) | (_, Type::Error) => true,
+        (Type::Optional(a), Type::Optional(b)) | (Type::Array(a), Type::Array(b)) => same(a, b),
+        (Type::Map(ak, av), Type::Map(bk, bv)) => same(ak, bk) && same(av, bv),
+        (Type::Tuple(a), Type::Tuple(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b))
+        }
+        _ => a == b,
+    }
+}
+
+/// Whether `==` can compare `a` and `b`: values of one type, where either
+/// may be `none`.
+fn comparable(a: &Type, b: &Type) -> bool {
+    let strip = |ty: &'_ Type| -> Type {
+        match ty {
+            Type::Optional(inner) => (**inner).clone(),
+            ty => ty.clone(),
+        }
+    };
+    same(&strip(a), &strip(b))
+}
+
+/// The number of values an `expected` and a `found` value have, if they
+/// differ and either is a tuple.
+fn arity(expected: &Type, found: &Type) -> Option<(usize, usize)> {
+    let expected = match expected {
+        Type::Tuple(types) => types.len(),
+        Type::Error => return None,
+        _ => 1,
+    };
+    let found = match found {
+        Type::Tuple(types) => types.len(),
+        Type::Error => return None,
+        _ => 1,
+    };
+    (expected != found).then_some((expected, found))
+}
+
+fn arity_of(pattern: &Pattern) -> usize {
+    match pattern {
+        Pattern::Name(_) => 1,
+        Pattern::Tuple(names) => names.len(),
+    }
+}
+
+fn count(values: usize) -> String {
+    match values {
+        1 => "1 value".to_string(),
+        n => format!("{n} values"),
+    }
+}
+
+fn is_none(ty: &Type) -> bool {
+    matches!(ty, Type::Optional(inner) if **inner == Type::Error)
+}
+
+/// The element type of an array or map literal whose other elements were
+/// `elem`: optional if any element was `none`, wherever it stood.
+fn with_none(elem: Option<Type>, saw_none: bool) -> Type {
+    match elem {
+        Some(ty @ (Type::Optional(_) | Type::Error)) => ty,
+        Some(ty) if saw_none => Type::Optional(Box::new(ty)),
+        Some(ty) => ty,
+        None if saw_none => Type::Optional(Box::new(Type::Error)),
+        None => Type::Error,
+    }
+}
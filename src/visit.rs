@@ -0,0 +1,601 @@
+//! Traversal of [`ast1`](crate::ast1) trees.
+//!
+//! Implement [`Visitor`] (or [`VisitorMut`] to change the tree in place) and
+//! override the methods for the nodes you care about. Every method defaults
+//! to the matching `walk_*` function, which visits the node's children; an
+//! override calls it to keep descending, or leaves it out to skip the
+//! subtree.
+//!
+//! Expressions and statements are visited by id together with the arena
+//! holding them, so visitors can record results in side tables.
+
+use crate::ast1::*;
+
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_stmt(&mut self, ast: &AstArena, stmt: StmtId) {
+        walk_stmt(self, ast, stmt);
+    }
+
+    fn visit_block(&mut self, ast: &AstArena, block: &Block) {
+        walk_block(self, ast, block);
+    }
+
+    fn visit_expr(&mut self, ast: &AstArena, expr: ExprId) {
+        walk_expr(self, ast, expr);
+    }
+
+    fn visit_attribute(&mut self, ast: &AstArena, attr: &Attribute) {
+        walk_attribute(self, ast, attr);
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        walk_pattern(self, pattern);
+    }
+
+    fn visit_type(&mut self, ty: &TypeRef) {
+        walk_type(self, ty);
+    }
+
+    /// Every name in the tree: bindings, uses, callees and type names.
+    fn visit_ident(&mut self, _ident: &Ident) {}
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(v: &mut V, program: &Program) {
+    for &stmt in &program.stmts {
+        v.visit_stmt(&program.arena, stmt);
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(v: &mut V, ast: &AstArena, stmt: StmtId) {
+    match &ast[stmt] {
+        Stmt::Let(s) => {
+            for attr in &s.attrs {
+                v.visit_attribute(ast, attr);
+            }
+            v.visit_pattern(&s.pattern);
+            if let Some(ty) = &s.ty {
+                v.visit_type(ty);
+            }
+            v.visit_expr(ast, s.value);
+        }
+        Stmt::Const(s) => {
+            for attr in &s.attrs {
+                v.visit_attribute(ast, attr);
+            }
+            v.visit_ident(&s.name);
+            if let Some(ty) = &s.ty {
+                v.visit_type(ty);
+            }
+            v.visit_expr(ast, s.value);
+        }
+        Stmt::Fn(s) => {
+            for attr in &s.attrs {
+                v.visit_attribute(ast, attr);
+            }
+            v.visit_ident(&s.name);
+            for param in &s.params {
+                v.visit_ident(&param.name);
+                v.visit_type(&param.ty);
+                if let Some(default) = param.default {
+                    v.visit_expr(ast, default);
+                }
+            }
+            if let Some(ret) = &s.ret {
+                v.visit_type(ret);
+            }
+            v.visit_block(ast, &s.body);
+        }
+        Stmt::Struct(s) => {
+            for attr in &s.attrs {
+                v.visit_attribute(ast, attr);
+            }
+            v.visit_ident(&s.name);
+            for field in &s.fields {
+                v.visit_ident(&field.name);
+                v.visit_type(&field.ty);
+            }
+        }
+        Stmt::Enum(s) => {
+            for attr in &s.attrs {
+                v.visit_attribute(ast, attr);
+            }
+            v.visit_ident(&s.name);
+            for variant in &s.variants {
+                v.visit_ident(&variant.name);
+            }
+        }
+        Stmt::Assign(s) => {
+            v.visit_ident(&s.target);
+            for step in &s.path {
+                match step {
+                    Projection::Index(index) => v.visit_expr(ast, *index),
+                    Projection::Field(field) => v.visit_ident(field),
+                }
+            }
+            v.visit_expr(ast, s.value);
+        }
+        Stmt::Return(ReturnStmt { values, .. }) | Stmt::Yield(YieldStmt { values, .. }) => {
+            for &value in values {
+                v.visit_expr(ast, value);
+            }
+        }
+        Stmt::If(s) => walk_if(
+            v,
+            ast,
+            s.cond,
+            &s.then_block,
+            &s.elifs,
+            s.else_block.as_ref(),
+        ),
+        Stmt::While(s) => {
+            v.visit_expr(ast, s.cond);
+            v.visit_block(ast, &s.body);
+        }
+        Stmt::For(s) => {
+            v.visit_ident(&s.var);
+            v.visit_expr(ast, s.range);
+            v.visit_block(ast, &s.body);
+        }
+        Stmt::Expr(s) => v.visit_expr(ast, s.expr),
+        Stmt::Error(_) => {}
+    }
+}
+
+fn walk_if<V: Visitor + ?Sized>(
+    v: &mut V,
+    ast: &AstArena,
+    cond: ExprId,
+    then_block: &Block,
+    elifs: &[ElifBranch],
+    else_block: Option<&Block>,
+) {
+    v.visit_expr(ast, cond);
+    v.visit_block(ast, then_block);
+    for elif in elifs {
+        v.visit_expr(ast, elif.cond);
+        v.visit_block(ast, &elif.block);
+    }
+    if let Some(block) = else_block {
+        v.visit_block(ast, block);
+    }
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(v: &mut V, ast: &AstArena, block: &Block) {
+    for &stmt in &block.stmts {
+        v.visit_stmt(ast, stmt);
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(v: &mut V, ast: &AstArena, expr: ExprId) {
+    match &ast[expr] {
+        Expr::Int(_)
+        | Expr::Float(_)
+        | Expr::Str(_)
+        | Expr::Bool(_)
+        | Expr::None(_)
+        | Expr::Error(_) => {}
+        Expr::Ident(ident) => v.visit_ident(ident),
+        Expr::Call(e) => {
+            v.visit_ident(&e.callee);
+            for &arg in &e.args {
+                v.visit_expr(ast, arg);
+            }
+        }
+        Expr::Unary(e) => v.visit_expr(ast, e.operand),
+        Expr::Cast(e) => {
+            v.visit_expr(ast, e.expr);
+            v.visit_type(&e.ty);
+        }
+        Expr::Binary(e) => {
+            v.visit_expr(ast, e.lhs);
+            v.visit_expr(ast, e.rhs);
+        }
+        Expr::Paren(e) => v.visit_expr(ast, e.inner),
+        Expr::If(e) => walk_if(
+            v,
+            ast,
+            e.cond,
+            &e.then_block,
+            &e.elifs,
+            e.else_block.as_ref(),
+        ),
+        Expr::Range(e) => {
+            v.visit_expr(ast, e.start);
+            v.visit_expr(ast, e.end);
+        }
+        Expr::Array(e) => {
+            for &elem in &e.elems {
+                v.visit_expr(ast, elem);
+            }
+        }
+        Expr::Map(e) => {
+            for entry in &e.entries {
+                v.visit_expr(ast, entry.key);
+                v.visit_expr(ast, entry.value);
+            }
+        }
+        Expr::Index(e) => {
+            v.visit_expr(ast, e.base);
+            v.visit_expr(ast, e.index);
+        }
+        Expr::Struct(e) => {
+            v.visit_ident(&e.name);
+            for field in &e.fields {
+                v.visit_ident(&field.name);
+                v.visit_expr(ast, field.value);
+            }
+        }
+        Expr::Field(e) => {
+            v.visit_expr(ast, e.base);
+            v.visit_ident(&e.field);
+        }
+        Expr::Variant(e) => {
+            v.visit_ident(&e.enum_name);
+            v.visit_ident(&e.variant);
+        }
+    }
+}
+
+pub fn walk_attribute<V: Visitor + ?Sized>(v: &mut V, ast: &AstArena, attr: &Attribute) {
+    v.visit_ident(&attr.name);
+    for &arg in &attr.args {
+        v.visit_expr(ast, arg);
+    }
+}
+
+pub fn walk_pattern<V: Visitor + ?Sized>(v: &mut V, pattern: &Pattern) {
+    match pattern {
+        Pattern::Ident(ident) => v.visit_ident(ident),
+        Pattern::Tuple(p) => {
+            for elem in &p.elems {
+                v.visit_pattern(elem);
+            }
+        }
+    }
+}
+
+pub fn walk_type<V: Visitor + ?Sized>(v: &mut V, ty: &TypeRef) {
+    match ty {
+        TypeRef::Named(ident) => v.visit_ident(ident),
+        TypeRef::Unit(_) => {}
+        TypeRef::Tuple(t) => {
+            for elem in &t.elems {
+                v.visit_type(elem);
+            }
+        }
+        TypeRef::Array(t) => v.visit_type(&t.elem),
+        TypeRef::Map(t) => {
+            v.visit_type(&t.key);
+            v.visit_type(&t.value);
+        }
+        TypeRef::Optional(t) => v.visit_type(&t.inner),
+    }
+}
+
+/// Like [`Visitor`], but with mutable access to every node.
+///
+/// Blocks are only lists of statement ids, so they are passed by shared
+/// reference; their statements are reached mutably through the arena.
+pub trait VisitorMut {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program);
+    }
+
+    fn visit_stmt_mut(&mut self, ast: &mut AstArena, stmt: StmtId) {
+        walk_stmt_mut(self, ast, stmt);
+    }
+
+    fn visit_block_mut(&mut self, ast: &mut AstArena, block: &Block) {
+        walk_block_mut(self, ast, block);
+    }
+
+    fn visit_expr_mut(&mut self, ast: &mut AstArena, expr: ExprId) {
+        walk_expr_mut(self, ast, expr);
+    }
+
+    fn visit_attribute_mut(&mut self, ast: &mut AstArena, attr: &mut Attribute) {
+        walk_attribute_mut(self, ast, attr);
+    }
+
+    fn visit_pattern_mut(&mut self, pattern: &mut Pattern) {
+        walk_pattern_mut(self, pattern);
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut TypeRef) {
+        walk_type_mut(self, ty);
+    }
+
+    fn visit_ident_mut(&mut self, _ident: &mut Ident) {}
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(v: &mut V, program: &mut Program) {
+    for &stmt in &program.stmts {
+        v.visit_stmt_mut(&mut program.arena, stmt);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(v: &mut V, ast: &mut AstArena, stmt: StmtId) {
+    // Attributes are taken out while they are visited, as their arguments
+    // live in the same arena.
+    let mut attrs = match &mut ast[stmt] {
+        Stmt::Let(s) => std::mem::take(&mut s.attrs),
+        Stmt::Const(s) => std::mem::take(&mut s.attrs),
+        Stmt::Fn(s) => std::mem::take(&mut s.attrs),
+        Stmt::Struct(s) => std::mem::take(&mut s.attrs),
+        Stmt::Enum(s) => std::mem::take(&mut s.attrs),
+        _ => Vec::new(),
+    };
+    for attr in &mut attrs {
+        v.visit_attribute_mut(ast, attr);
+    }
+    match &mut ast[stmt] {
+        Stmt::Let(s) => {
+            s.attrs = attrs;
+            v.visit_pattern_mut(&mut s.pattern);
+            if let Some(ty) = &mut s.ty {
+                v.visit_type_mut(ty);
+            }
+            let value = s.value;
+            v.visit_expr_mut(ast, value);
+        }
+        Stmt::Const(s) => {
+            s.attrs = attrs;
+            v.visit_ident_mut(&mut s.name);
+            if let Some(ty) = &mut s.ty {
+                v.visit_type_mut(ty);
+            }
+            let value = s.value;
+            v.visit_expr_mut(ast, value);
+        }
+        Stmt::Fn(s) => {
+            s.attrs = attrs;
+            v.visit_ident_mut(&mut s.name);
+            // Defaults live in the arena, so the parameters are visited out
+            // of it and put back.
+            let mut params = std::mem::take(&mut s.params);
+            for param in &mut params {
+                v.visit_ident_mut(&mut param.name);
+                v.visit_type_mut(&mut param.ty);
+                if let Some(default) = param.default {
+                    v.visit_expr_mut(ast, default);
+                }
+            }
+            let Stmt::Fn(s) = &mut ast[stmt] else {
+                unreachable!("visited a function");
+            };
+            s.params = params;
+            if let Some(ret) = &mut s.ret {
+                v.visit_type_mut(ret);
+            }
+            let body = s.body.clone();
+            v.visit_block_mut(ast, &body);
+        }
+        Stmt::Struct(s) => {
+            s.attrs = attrs;
+            v.visit_ident_mut(&mut s.name);
+            for field in &mut s.fields {
+                v.visit_ident_mut(&mut field.name);
+                v.visit_type_mut(&mut field.ty);
+            }
+        }
+        Stmt::Enum(s) => {
+            s.attrs = attrs;
+            v.visit_ident_mut(&mut s.name);
+            for variant in &mut s.variants {
+                v.visit_ident_mut(&mut variant.name);
+            }
+        }
+        Stmt::Assign(s) => {
+            v.visit_ident_mut(&mut s.target);
+            // Fields are renamed in a copy, as indices live in the arena.
+            let (mut path, value) = (s.path.clone(), s.value);
+            for step in &mut path {
+                match step {
+                    Projection::Index(index) => v.visit_expr_mut(ast, *index),
+                    Projection::Field(field) => v.visit_ident_mut(field),
+                }
+            }
+            let Stmt::Assign(s) = &mut ast[stmt] else {
+                unreachable!("visited an assignment");
+            };
+            s.path = path;
+            v.visit_expr_mut(ast, value);
+        }
+        Stmt::Return(ReturnStmt { values, .. }) | Stmt::Yield(YieldStmt { values, .. }) => {
+            for value in values.clone() {
+                v.visit_expr_mut(ast, value);
+            }
+        }
+        Stmt::If(s) => {
+            let s = s.clone();
+            walk_if_mut(
+                v,
+                ast,
+                s.cond,
+                &s.then_block,
+                &s.elifs,
+                s.else_block.as_ref(),
+            );
+        }
+        Stmt::While(s) => {
+            let (cond, body) = (s.cond, s.body.clone());
+            v.visit_expr_mut(ast, cond);
+            v.visit_block_mut(ast, &body);
+        }
+        Stmt::For(s) => {
+            v.visit_ident_mut(&mut s.var);
+            let (range, body) = (s.range, s.body.clone());
+            v.visit_expr_mut(ast, range);
+            v.visit_block_mut(ast, &body);
+        }
+        Stmt::Expr(s) => {
+            let expr = s.expr;
+            v.visit_expr_mut(ast, expr);
+        }
+        Stmt::Error(_) => {}
+    }
+}
+
+fn walk_if_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    ast: &mut AstArena,
+    cond: ExprId,
+    then_block: &Block,
+    elifs: &[ElifBranch],
+    else_block: Option<&Block>,
+) {
+    v.visit_expr_mut(ast, cond);
+    v.visit_block_mut(ast, then_block);
+    for elif in elifs {
+        v.visit_expr_mut(ast, elif.cond);
+        v.visit_block_mut(ast, &elif.block);
+    }
+    if let Some(block) = else_block {
+        v.visit_block_mut(ast, block);
+    }
+}
+
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(v: &mut V, ast: &mut AstArena, block: &Block) {
+    for &stmt in &block.stmts {
+        v.visit_stmt_mut(ast, stmt);
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, ast: &mut AstArena, expr: ExprId) {
+    match &mut ast[expr] {
+        Expr::Int(_)
+        | Expr::Float(_)
+        | Expr::Str(_)
+        | Expr::Bool(_)
+        | Expr::None(_)
+        | Expr::Error(_) => {}
+        Expr::Ident(ident) => v.visit_ident_mut(ident),
+        Expr::Call(e) => {
+            v.visit_ident_mut(&mut e.callee);
+            for arg in e.args.clone() {
+                v.visit_expr_mut(ast, arg);
+            }
+        }
+        Expr::Unary(e) => {
+            let operand = e.operand;
+            v.visit_expr_mut(ast, operand);
+        }
+        Expr::Cast(e) => {
+            let inner = e.expr;
+            v.visit_expr_mut(ast, inner);
+            let Expr::Cast(e) = &mut ast[expr] else {
+                unreachable!("visited a cast");
+            };
+            v.visit_type_mut(&mut e.ty);
+        }
+        Expr::Binary(e) => {
+            let (lhs, rhs) = (e.lhs, e.rhs);
+            v.visit_expr_mut(ast, lhs);
+            v.visit_expr_mut(ast, rhs);
+        }
+        Expr::Paren(e) => {
+            let inner = e.inner;
+            v.visit_expr_mut(ast, inner);
+        }
+        Expr::If(e) => {
+            let e = e.clone();
+            walk_if_mut(
+                v,
+                ast,
+                e.cond,
+                &e.then_block,
+                &e.elifs,
+                e.else_block.as_ref(),
+            );
+        }
+        Expr::Range(e) => {
+            let (start, end) = (e.start, e.end);
+            v.visit_expr_mut(ast, start);
+            v.visit_expr_mut(ast, end);
+        }
+        Expr::Array(e) => {
+            for elem in e.elems.clone() {
+                v.visit_expr_mut(ast, elem);
+            }
+        }
+        Expr::Map(e) => {
+            for entry in e.entries.clone() {
+                v.visit_expr_mut(ast, entry.key);
+                v.visit_expr_mut(ast, entry.value);
+            }
+        }
+        Expr::Index(e) => {
+            let (base, index) = (e.base, e.index);
+            v.visit_expr_mut(ast, base);
+            v.visit_expr_mut(ast, index);
+        }
+        Expr::Struct(e) => {
+            v.visit_ident_mut(&mut e.name);
+            let mut fields = e.fields.clone();
+            for field in &mut fields {
+                v.visit_ident_mut(&mut field.name);
+                v.visit_expr_mut(ast, field.value);
+            }
+            let Expr::Struct(e) = &mut ast[expr] else {
+                unreachable!("visited a struct literal");
+            };
+            e.fields = fields;
+        }
+        Expr::Field(e) => {
+            let base = e.base;
+            v.visit_expr_mut(ast, base);
+            let Expr::Field(e) = &mut ast[expr] else {
+                unreachable!("visited a field access");
+            };
+            v.visit_ident_mut(&mut e.field);
+        }
+        Expr::Variant(e) => {
+            v.visit_ident_mut(&mut e.enum_name);
+            v.visit_ident_mut(&mut e.variant);
+        }
+    }
+}
+
+pub fn walk_attribute_mut<V: VisitorMut + ?Sized>(
+    v: &mut V,
+    ast: &mut AstArena,
+    attr: &mut Attribute,
+) {
+    v.visit_ident_mut(&mut attr.name);
+    for &arg in &attr.args {
+        v.visit_expr_mut(ast, arg);
+    }
+}
+
+pub fn walk_pattern_mut<V: VisitorMut + ?Sized>(v: &mut V, pattern: &mut Pattern) {
+    match pattern {
+        Pattern::Ident(ident) => v.visit_ident_mut(ident),
+        Pattern::Tuple(p) => {
+            for elem in &mut p.elems {
+                v.visit_pattern_mut(elem);
+            }
+        }
+    }
+}
+
+pub fn walk_type_mut<V: VisitorMut + ?Sized>(v: &mut V, ty: &mut TypeRef) {
+    match ty {
+        TypeRef::Named(ident) => v.visit_ident_mut(ident),
+        TypeRef::Unit(_) => {}
+        TypeRef::Tuple(t) => {
+            for elem in &mut t.elems {
+                v.visit_type_mut(elem);
+            }
+        }
+        TypeRef::Array(t) => v.visit_type_mut(&mut t.elem),
+        TypeRef::Map(t) => {
+            v.visit_type_mut(&mut t.key);
+            v.visit_type_mut(&mut t.value);
+        }
+        TypeRef::Optional(t) => v.visit_type_mut(&mut t.inner),
+    }
+}
@@ -0,0 +1,244 @@
+use shallows_vm::analysis::{analyze, analyze_with};
+use shallows_vm::ast1::{AstArena, Expr, ExprId, Ident, Program, Stmt};
+use shallows_vm::ast2;
+use shallows_vm::scope::SymbolKind;
+use shallows_vm::visit::{self, Visitor};
+use shallows_vm::{
+    AnalysisHost, CancellationToken, Cancelled, Diagnostic, Diagnostics, Lines, Plugin, Plugins,
+    Stage, VisitorMut,
+};
+
+fn lines(source: &str) -> Lines {
+    Lines::from_string(source.to_string())
+}
+
+#[test]
+fn analyze_runs_every_pass() {
+    let analysis = analyze(
+        &lines("let _x = $;\nlet _y = if a { yield 1; };\nlet _z: Point = origin();"),
+        &CancellationToken::new(),
+    )
+    .unwrap();
+    let codes: Vec<_> = analysis.diagnostics.iter().map(|d| d.code).collect();
+    assert_eq!(
+        codes,
+        [Some("E0101"), Some("E0202"), Some("E0301"), Some("E0403")]
+    );
+    assert_eq!(analysis.program.stmts.len(), 3);
+    assert_eq!(analysis.resolved.stmts.len(), 3);
+    assert!(analysis.types.is_none());
+}
+
+#[test]
+fn analyze_checks_types_once_names_resolve() {
+    let analysis = analyze(
+        &lines("let hp: int = 1.5;\nprint(hp);"),
+        &CancellationToken::new(),
+    )
+    .unwrap();
+    let codes: Vec<_> = analysis.diagnostics.iter().map(|d| d.code).collect();
+    assert_eq!(codes, [Some("E0501")]);
+    assert!(analysis.types.is_some());
+}
+
+#[test]
+fn cancelled_token_stops_analysis() {
+    let token = CancellationToken::new();
+    token.cancel();
+    assert_eq!(
+        analyze(&lines("let x = 1;"), &token).unwrap_err(),
+        Cancelled
+    );
+}
+
+#[test]
+fn host_compiles_in_the_background() {
+    let mut host = AnalysisHost::new();
+    let pending = host.submit(lines("let x = 1;"));
+    let analysis = pending.wait().unwrap();
+    assert!(!analysis.has_errors());
+}
+
+#[test]
+fn new_submission_cancels_the_previous_one() {
+    let mut host = AnalysisHost::new();
+    let big = "let x = 1 + 2 * 3;\n".repeat(200_000);
+    let stale = host.submit(lines(&big));
+    let fresh = host.submit(lines("let y = 2;"));
+    assert_eq!(stale.wait().unwrap_err(), Cancelled);
+    assert!(!fresh.wait().unwrap().has_errors());
+}
+
+#[test]
+fn poll_does_not_block() {
+    let mut host = AnalysisHost::new();
+    let pending = host.submit(lines("let x = 1;"));
+    let result = loop {
+        if let Some(result) = pending.poll() {
+            break result;
+        }
+        std::thread::yield_now();
+    };
+    assert!(result.is_ok());
+    assert_eq!(pending.poll().unwrap().unwrap_err(), Cancelled);
+}
+
+/// Bans calls to `debug_draw`, as a studio might in shipped UI scripts.
+struct NoDebugDraw;
+
+impl Plugin for NoDebugDraw {
+    fn name(&self) -> &str {
+        "no-debug-draw"
+    }
+
+    fn run(&self, program: &mut Program, diagnostics: &mut Diagnostics) {
+        struct Calls<'a>(&'a mut Diagnostics);
+        impl Visitor for Calls<'_> {
+            fn visit_expr(&mut self, ast: &AstArena, expr: ExprId) {
+                if let Expr::Call(call) = &ast[expr]
+                    && call.callee.name == "debug_draw"
+                {
+                    self.0.push(Diagnostic::error(
+                        call.span,
+                        "`debug_draw` is not allowed here",
+                    ));
+                }
+                visit::walk_expr(self, ast, expr);
+            }
+        }
+        Calls(diagnostics).visit_program(program);
+    }
+}
+
+/// Renames calls to `old_move` to `move`.
+struct RenameOldMove;
+
+impl Plugin for RenameOldMove {
+    fn name(&self) -> &str {
+        "rename-old-move"
+    }
+
+    fn run(&self, program: &mut Program, _: &mut Diagnostics) {
+        struct Rename;
+        impl VisitorMut for Rename {
+            fn visit_ident_mut(&mut self, ident: &mut Ident) {
+                if ident.name == "old_move" {
+                    ident.name = "move".to_string();
+                }
+            }
+        }
+        Rename.visit_program_mut(program);
+    }
+}
+
+#[test]
+fn plugins_run_at_their_stage() {
+    let plugins = Plugins::new()
+        .with(Stage::AfterPass1, NoDebugDraw)
+        .with(Stage::AfterParse, RenameOldMove);
+    let analysis = analyze_with(
+        &lines("old_move(1.0);\nif a { debug_draw(); }\nlet _x = if b { yield 1; };"),
+        &CancellationToken::new(),
+        &plugins,
+    )
+    .unwrap();
+    let messages: Vec<_> = analysis
+        .diagnostics
+        .iter()
+        .map(|d| &d.message[..])
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "`if` used as a value must have an `else` branch",
+            "`debug_draw` is not allowed here"
+        ]
+    );
+    let ast = &analysis.program.arena;
+    let Stmt::Expr(s) = &ast[analysis.program.stmts[0]] else {
+        panic!("expected a call statement");
+    };
+    let Expr::Call(call) = &ast[s.expr] else {
+        panic!("expected a call");
+    };
+    assert_eq!(call.callee.name, "move");
+}
+
+/// Bans the host's globals, as a studio might in scripts that must run
+/// without a level loaded.
+struct NoGlobals;
+
+impl Plugin for NoGlobals {
+    fn name(&self) -> &str {
+        "no-globals"
+    }
+
+    fn check_resolved(&self, program: &ast2::Program, diagnostics: &mut Diagnostics) {
+        for (_, expr) in program.arena.exprs() {
+            if let ast2::Expr::Name(name) = expr
+                && program.symbols[name.symbol].kind == SymbolKind::Global
+            {
+                diagnostics.push(Diagnostic::error(
+                    name.span,
+                    format!(
+                        "global `{}` is not allowed here",
+                        program.symbols[name.symbol].name
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+#[test]
+fn resolved_plugins_see_symbols() {
+    let plugins = Plugins::new().with(Stage::AfterResolve, NoGlobals);
+    let analysis = analyze_with(
+        &lines("let hp = 10;\nprint(hp, level);"),
+        &CancellationToken::new(),
+        &plugins,
+    )
+    .unwrap();
+    let messages: Vec<_> = analysis
+        .diagnostics
+        .iter()
+        .map(|d| &d.message[..])
+        .collect();
+    assert_eq!(messages, ["global `level` is not allowed here"]);
+    assert!(analysis.types.is_none());
+}
+
+#[test]
+fn host_runs_its_plugins() {
+    let mut host =
+        AnalysisHost::new().with_plugins(Plugins::new().with(Stage::AfterPass1, NoDebugDraw));
+    let analysis = host.submit(lines("debug_draw();")).wait().unwrap();
+    assert!(analysis.has_errors());
+}
+
+#[test]
+fn host_reports_diagnostics_to_the_error_sink() {
+    use shallows_vm::telemetry::{ErrorOrigin, ErrorRecord, ErrorSink};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Collect(Mutex<Vec<(String, ErrorOrigin, Option<&'static str>)>>);
+    impl ErrorSink for Collect {
+        fn record(&self, r: &ErrorRecord<'_>) {
+            let record = (r.script.to_string(), r.origin, r.code);
+            self.0.lock().unwrap().push(record);
+        }
+    }
+
+    let sink = Arc::new(Collect::default());
+    let mut host = AnalysisHost::new().with_error_sink(sink.clone());
+    let source = lines("let _x = if a { yield 1; };\n#[exprt] let _y = 2;").with_path("ui/hud.ss");
+    host.submit(source).wait().unwrap();
+    assert_eq!(
+        *sink.0.lock().unwrap(),
+        [
+            ("ui/hud.ss".to_string(), ErrorOrigin::Compile, Some("E0301")),
+            ("ui/hud.ss".to_string(), ErrorOrigin::Compile, Some("W0301")),
+        ]
+    );
+}
@@ -0,0 +1,28 @@
+use std::path::Path;
+
+/// Every code emitted anywhere in `src` must have an explanation.
+#[test]
+fn every_emitted_code_is_explained() {
+    fn visit(dir: &Path, missing: &mut Vec<String>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                visit(&path, missing);
+                continue;
+            }
+            let text = std::fs::read_to_string(&path).unwrap();
+            for (i, _) in text.match_indices("with_code(\"") {
+                let code = &text[i + 11..i + 16];
+                if shallows_vm::codes::lookup(code).is_none() {
+                    missing.push(format!("{}: {code}", path.display()));
+                }
+            }
+        }
+    }
+    let mut missing = Vec::new();
+    visit(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+        &mut missing,
+    );
+    assert!(missing.is_empty(), "codes without explanation: {missing:?}");
+}
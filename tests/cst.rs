@@ -0,0 +1,79 @@
+use std::path::Path;
+
+use shallows_vm::cst::{self, SyntaxElement, SyntaxKind};
+use shallows_vm::{Lines, TokenKind, parse};
+
+fn sources() -> Vec<String> {
+    let mut sources: Vec<String> = [
+        "",
+        "\n\n",
+        "let x = 1;",
+        "let a = \"t\\tb\";\r\nlet b = 2;\rmove(a,\tb); // done\r\n",
+        "let p = 5 $ 2;\nlet s = \"open\nf(&x);\n",
+        "let a = * 2;\nlet b = 1;\n}\nmove(b);",
+        "if a {\n  let x =\n",
+        "let t: (int) = 1;\nlet u, v, = (1);\n",
+    ]
+    .map(String::from)
+    .to_vec();
+    let mut dirs = vec![Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/spec")];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|e| e == "ss") {
+                sources.push(std::fs::read_to_string(path).unwrap());
+            }
+        }
+    }
+    sources
+}
+
+#[test]
+fn text_round_trips() {
+    for source in sources() {
+        let lines = Lines::from_string(source.clone());
+        assert_eq!(cst::parse(&lines).root.text(), source);
+    }
+}
+
+#[test]
+fn lowering_matches_parser() {
+    for source in sources() {
+        let lines = Lines::from_string(source.clone());
+        let tree = cst::parse(&lines);
+        let ast = parse(&lines);
+        assert_eq!(cst::lower(&tree.root), ast.program, "{source:?}");
+        assert_eq!(tree.diagnostics, ast.diagnostics, "{source:?}");
+    }
+}
+
+#[test]
+fn leading_trivia_belongs_to_the_parent() {
+    let lines = Lines::from_string("// speed\nlet x = a + 1;\n".to_string());
+    let root = cst::parse(&lines).root;
+    let SyntaxElement::Token(comment) = &root.children()[0] else {
+        panic!("expected the comment first, got {:?}", root.children()[0]);
+    };
+    assert_eq!(comment.kind, TokenKind::Comment("// speed".to_string()));
+
+    let stmt = root.nodes().next().unwrap();
+    assert_eq!(stmt.kind(), SyntaxKind::LetStmt);
+    assert_eq!(stmt.text(), "let x = a + 1;");
+    let kinds: Vec<_> = stmt.nodes().map(|n| n.kind()).collect();
+    assert_eq!(kinds, [SyntaxKind::Pattern, SyntaxKind::BinaryExpr]);
+}
+
+#[test]
+fn deep_nesting_builds_in_linear_time() {
+    // Opening a node used to shift every event after it, which made this
+    // take minutes.
+    let depth = 100_000;
+    let source = format!("{}{}", "if a {\n".repeat(depth), "}\n".repeat(depth));
+    let started = std::time::Instant::now();
+    let parsed = cst::parse(&Lines::from_string(source.clone()));
+    let elapsed = started.elapsed();
+    assert_eq!(parsed.root.text(), source);
+    assert!(elapsed.as_secs() < 10, "took {elapsed:?}");
+}
@@ -0,0 +1,14 @@
+use shallows_vm::{Lines, parse};
+
+#[test]
+fn render_handles_very_long_lines() {
+    let source = format!("{}$", " ".repeat(70_000));
+    let lines = Lines::from_string(source);
+    let result = parse(&lines);
+    let rendered = result.diagnostics[0].render(&lines);
+    let caret = rendered
+        .lines()
+        .find(|line| line.ends_with('^'))
+        .expect("a caret line");
+    assert_eq!(caret.len(), "  | ".len() + 70_000 + 1);
+}
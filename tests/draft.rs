@@ -0,0 +1,453 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use shallows_vm::draft::{EnumValue, Interpreter, Trap, Value};
+use shallows_vm::{Lines, parse};
+
+fn run(interpreter: &mut Interpreter, source: &str) -> Result<Vec<Value>, Trap> {
+    let parsed = parse(&Lines::from_string(source.to_string()));
+    assert!(!parsed.has_errors(), "{:?}", parsed.diagnostics);
+    interpreter.run(&parsed.program)
+}
+
+fn trap(source: &str) -> String {
+    run(&mut Interpreter::new(), source).unwrap_err().message
+}
+
+#[test]
+fn returns_top_level_values() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        run(&mut interpreter, "let x = 2; return x * 3, \"done\";"),
+        Ok(vec![Value::Int(6), Value::Str("done".to_string())])
+    );
+    assert_eq!(run(&mut interpreter, "let x = 2;"), Ok(vec![]));
+}
+
+#[test]
+fn host_globals_and_functions() {
+    let moved = Rc::new(RefCell::new(Vec::new()));
+    let log = moved.clone();
+    let mut interpreter = Interpreter::new()
+        .with_global("hp", Value::Int(8))
+        .with_function("move", move |args| {
+            log.borrow_mut().extend_from_slice(args);
+            Ok(vec![])
+        })
+        .with_function("nearest", |_| Ok(vec![Value::Float(1.5), Value::Int(3)]));
+
+    let source = "
+        let dist, id = nearest();
+        if hp < 10 { move(-dist); }
+        return id;
+    ";
+    assert_eq!(run(&mut interpreter, source), Ok(vec![Value::Int(3)]));
+    assert_eq!(*moved.borrow(), [Value::Float(-1.5)]);
+
+    assert_eq!(
+        run(&mut interpreter, "hp = 1;").unwrap_err().message,
+        "cannot assign to global `hp`"
+    );
+}
+
+#[test]
+fn host_errors_trap_at_the_call() {
+    let mut interpreter = Interpreter::new().with_function("fail", |_| Err("no target".into()));
+    let trap = run(&mut interpreter, "let a = 1;\nfail();").unwrap_err();
+    assert_eq!(trap.message, "no target");
+    assert_eq!(trap.span.start.line, 1);
+}
+
+#[test]
+fn dynamic_checks() {
+    assert_eq!(
+        trap("let x = 1; x = 2.0;"),
+        "cannot assign `float` to `x`, which holds `int`"
+    );
+    assert_eq!(
+        trap("let x: uint = 1;"),
+        "value of type `int` does not match"
+    );
+    assert_eq!(trap("if 1 { }"), "condition must be `bool`, found `int`");
+    assert_eq!(
+        trap("let a, b = 1;"),
+        "cannot destructure a single `int` into 2 names"
+    );
+    assert_eq!(trap("let x = -1u;"), "cannot apply `-` to `uint`");
+    assert_eq!(
+        trap("let x = 1 < 2 && 3;"),
+        "operands of `&&` must be `bool`, found `int`"
+    );
+    assert_eq!(trap("let x = 9223372036854775807 + 1;"), "integer overflow");
+    assert_eq!(trap("let x = 0u - 1u;"), "integer overflow");
+    assert_eq!(
+        run(&mut Interpreter::new(), "return -9223372036854775808;"),
+        Ok(vec![Value::Int(i64::MIN)])
+    );
+    assert_eq!(
+        trap("let x = -9223372036854775809;"),
+        "literal `-9223372036854775809` does not fit in `int`"
+    );
+    assert_eq!(trap("let x = y;"), "unknown name `y`");
+    assert_eq!(trap("jump();"), "unknown function `jump`");
+    assert_eq!(
+        trap("let x = print(1);"),
+        "`print` returned `()`, which is not a value"
+    );
+    assert_eq!(trap("let x = if true { };"), "block ended without `yield`");
+    assert_eq!(
+        trap("let x = if true { yield; };"),
+        "this block yields `()`, but the `if` needs a value"
+    );
+    assert_eq!(trap("yield 1;"), "`yield` outside an `if` expression");
+}
+
+#[test]
+fn only_type_errors_note_that_draft_mode_does_not_type_check() {
+    let notes = |source: &str| {
+        run(&mut Interpreter::new(), source)
+            .unwrap_err()
+            .to_diagnostic()
+            .notes
+    };
+    assert_eq!(
+        notes("if 1 { }"),
+        ["reported at runtime; draft mode does not type-check"]
+    );
+    assert!(notes("let x = 1 / 0;").is_empty());
+    assert!(notes("let x = 9223372036854775807 + 1;").is_empty());
+    assert!(notes("let xs = [1]; let x = xs[1];").is_empty());
+}
+
+#[test]
+fn logic_short_circuits() {
+    // The right side would trap if it were evaluated.
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        run(&mut interpreter, "return false && 1 / 0 == 0, true || x;"),
+        Ok(vec![Value::Bool(false), Value::Bool(true)])
+    );
+}
+
+#[test]
+fn locals_end_with_their_block() {
+    assert_eq!(
+        trap("if true { let inner = 1; } print(inner);"),
+        "unknown name `inner`"
+    );
+}
+
+#[test]
+fn runs_until_broken_code() {
+    let parsed = parse(&Lines::from_string(
+        "print(1);\nlet x = * 2;\nprint(2);".to_string(),
+    ));
+    assert!(parsed.has_errors());
+    let mut interpreter = Interpreter::new();
+    let trap = interpreter.run(&parsed.program).unwrap_err();
+    assert_eq!(trap.message, "reached code that failed to parse");
+    assert_eq!(trap.span.start.line, 1);
+    assert_eq!(interpreter.output(), ["1"]);
+}
+
+#[test]
+fn functions_check_calls_at_runtime() {
+    let mut interpreter = Interpreter::new();
+    let source = "
+        fn pair(a: int, b: float) -> (int, float) { return a * 2, b; }
+        return pair(3, 0.5);
+    ";
+    assert_eq!(
+        run(&mut interpreter, source),
+        Ok(vec![Value::Tuple(vec![Value::Int(6), Value::Float(0.5)])])
+    );
+
+    assert_eq!(
+        trap("fn f(a: int) { } f(1u);"),
+        "parameter `a` of `f` expects `int`, found `uint`"
+    );
+    assert_eq!(
+        trap("fn f() -> int { return 1.0; } f();"),
+        "`f` must return `int`, but returned `float`"
+    );
+    assert_eq!(
+        trap("fn f() -> int { } let x = f();"),
+        "`f` ended without returning `int`"
+    );
+    assert_eq!(
+        trap("fn f() { return 1; } f();"),
+        "`f` returned a value but declares no return type"
+    );
+    assert_eq!(
+        trap("fn f() -> () { return 1; } f();"),
+        "`f` returned a value but its return type is `()`"
+    );
+    assert_eq!(trap("fn f() { f(); } f();"), "call stack overflow");
+}
+
+#[test]
+fn traps_reach_the_error_sink() {
+    use shallows_vm::telemetry::{ErrorOrigin, ErrorRecord, ErrorSink, hash_message};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Collect(Mutex<Vec<(String, ErrorOrigin, u64, usize)>>);
+    impl ErrorSink for Collect {
+        fn record(&self, r: &ErrorRecord<'_>) {
+            let record = (r.script.to_string(), r.origin, r.message_hash, r.line);
+            self.0.lock().unwrap().push(record);
+        }
+    }
+
+    let sink = Arc::new(Collect::default());
+    let mut interpreter = Interpreter::new().with_error_sink("ai/guard.ss", sink.clone());
+    assert!(run(&mut interpreter, "let a = 1;").is_ok());
+    assert!(run(&mut interpreter, "let a = 1;\nlet b = a / 0;").is_err());
+    assert_eq!(
+        *sink.0.lock().unwrap(),
+        [(
+            "ai/guard.ss".to_string(),
+            ErrorOrigin::Runtime,
+            hash_message("division by zero"),
+            1
+        )]
+    );
+}
+
+#[test]
+fn constants_are_evaluated_first_and_read_only() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        run(&mut interpreter, "return LIMIT;\nconst LIMIT = 2 * 3;"),
+        Ok(vec![Value::Int(6)])
+    );
+    let overflow = run(
+        &mut interpreter,
+        "print(1);\nconst LIMIT: int = 9223372036854775807 + 1;",
+    )
+    .unwrap_err();
+    assert_eq!(overflow.to_string(), "2:20: integer overflow");
+    assert!(interpreter.output().is_empty());
+    assert_eq!(
+        trap("const LIMIT = 1; LIMIT = 2;"),
+        "cannot assign to constant `LIMIT`"
+    );
+    assert_eq!(
+        trap("const LIMIT: uint = 1;"),
+        "value of type `int` does not match"
+    );
+}
+
+#[test]
+fn hosts_return_none_for_nothing() {
+    let mut interpreter = Interpreter::new().with_function("find_target", |args| {
+        Ok(vec![match args {
+            [Value::Int(id)] if *id > 0 => Value::Int(*id),
+            _ => Value::None,
+        }])
+    });
+    let source = "let a: int? = find_target(0);\nreturn a ?? -1, is_some(find_target(2));";
+    assert_eq!(
+        run(&mut interpreter, source),
+        Ok(vec![Value::Int(-1), Value::Bool(true)])
+    );
+    assert_eq!(
+        trap("let a: int? = none; let b = a + 1;"),
+        "cannot apply `+` to `none` and `int`"
+    );
+    assert_eq!(
+        trap("let a = 1 < none;"),
+        "cannot apply `<` to `int` and `none`"
+    );
+    assert_eq!(
+        trap("let a = is_some();"),
+        "`is_some` takes 1 argument, but 0 were given"
+    );
+}
+
+#[test]
+fn casts_trap_instead_of_wrapping() {
+    assert_eq!(
+        trap("let x = 10000000000000000000.0 as int;"),
+        "`1e19` does not fit in `int`"
+    );
+    assert_eq!(
+        trap("let x = 0.0 / 0.0 as uint;"),
+        "cannot apply `/` to `float` and `uint`"
+    );
+    assert_eq!(
+        trap("let x = (0.0 / 0.0) as uint;"),
+        "`NaN` does not fit in `uint`"
+    );
+    assert_eq!(
+        trap("let x = 18446744073709551615u as int;"),
+        "`18446744073709551615` does not fit in `int`"
+    );
+    assert_eq!(trap("let x = true as int;"), "cannot cast `bool` to `int`");
+}
+
+#[test]
+fn float_checks_trap_at_the_first_nan_or_inf() {
+    let source = "let speed = 0.0;\nlet t = 1.0 / speed;\nreturn t * 0.0;";
+    assert_eq!(
+        run(&mut Interpreter::new(), "return 0.0 / 0.0 < 1.0;"),
+        Ok(vec![Value::Bool(false)])
+    );
+    let trap = run(&mut Interpreter::new().with_float_checks(), source).unwrap_err();
+    assert_eq!(trap.to_string(), "2:9: `1.0 / 0.0` gives inf");
+
+    let mut interpreter = Interpreter::new()
+        .with_float_checks()
+        .with_global("dx", Value::Float(f64::NAN));
+    assert_eq!(
+        run(&mut interpreter, "let x = 1.0 + 2.0; return dx * x;")
+            .unwrap_err()
+            .message,
+        "`NaN * 3.0` gives NaN"
+    );
+}
+
+#[test]
+fn array_checks() {
+    assert_eq!(
+        trap("let a = [1, 2.0];"),
+        "array elements must have one type: expected `int`, found `float`"
+    );
+    assert_eq!(
+        trap("let a = [1]; let x = a[-1];"),
+        "index -1 is out of bounds for an array of length 1"
+    );
+    assert_eq!(
+        trap("let a = [1]; let x = a[0.0];"),
+        "array index must be `int` or `uint`, found `float`"
+    );
+    assert_eq!(
+        trap("let a = [1]; a[0] = true;"),
+        "cannot assign `bool` to an element of `a`, which holds `int`"
+    );
+    assert_eq!(trap("let a = 1; let x = a[0];"), "cannot index into `int`");
+    assert_eq!(trap("let a = [1]; a[0][0] = 2;"), "cannot index into `int`");
+    assert_eq!(
+        trap("let a: [int] = [1u];"),
+        "value of type `uint` does not match"
+    );
+}
+
+#[test]
+fn map_checks() {
+    assert_eq!(
+        trap("let m = { 1.5: 1 };"),
+        "map keys must be `str` or `int`, found `float`"
+    );
+    assert_eq!(
+        trap("let m = { \"a\": 1, 2: 1 };"),
+        "map keys must have one type: expected `str`, found `int`"
+    );
+    assert_eq!(
+        trap("let m = { \"a\": 1, \"b\": true };"),
+        "map values must have one type: expected `int`, found `bool`"
+    );
+    assert_eq!(
+        trap("let m = { \"a\": 1, \"a\": 2 };"),
+        "key `a` appears twice in this map"
+    );
+    assert_eq!(
+        trap("let m = { \"a\": 1 }; m[\"b\"] = 2.0;"),
+        "cannot assign `float` to an element of `m`, which holds `int`"
+    );
+    assert_eq!(
+        trap("let m = { \"a\": [1] }; m[\"b\"][0] = 2;"),
+        "no entry for key `b`"
+    );
+}
+
+#[test]
+fn hosts_exchange_enums_as_ints() {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let log = seen.clone();
+    let mut interpreter = Interpreter::new()
+        .with_function("host_state", |_| Ok(vec![Value::Int(5)]))
+        .with_function("report", move |args| {
+            log.borrow_mut().extend_from_slice(args);
+            Ok(vec![])
+        });
+    let source = "
+        enum State { Idle, Walk = 5, Attack }
+        let s: State = host_state();
+        report(s, State::Attack);
+        return s == State::Walk;
+    ";
+    assert_eq!(run(&mut interpreter, source), Ok(vec![Value::Bool(true)]));
+    let variant = |variant: &str, value| {
+        Value::Enum(EnumValue {
+            name: "State".to_string(),
+            variant: variant.to_string(),
+            value,
+        })
+    };
+    assert_eq!(*seen.borrow(), [variant("Walk", 5), variant("Attack", 6)]);
+
+    assert_eq!(
+        trap("enum State { Idle, Walk }\nlet s: State = 2;"),
+        "no variant of `State` has the value 2"
+    );
+    assert_eq!(
+        trap("enum State { Idle }\nfn f(s: State) { }\nf(0u);"),
+        "parameter `s` of `f` expects `State`, found `uint`"
+    );
+}
+
+#[test]
+fn try_call_skips_missing_functions() {
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let log = calls.clone();
+    let mut interpreter = Interpreter::new().with_function("play_sound", move |args| {
+        log.borrow_mut().extend_from_slice(args);
+        Ok(vec![Value::Bool(true)])
+    });
+    let source = "
+        let played = try_call(play_sound(\"step\"));
+        try_call(shake_camera(print(\"not evaluated\")));
+        return played, try_call(shake_camera(1.0)) ?? false;
+    ";
+    assert_eq!(
+        run(&mut interpreter, source),
+        Ok(vec![Value::Bool(true), Value::Bool(false)])
+    );
+    assert_eq!(*calls.borrow(), [Value::Str("step".to_string())]);
+    assert!(interpreter.output().is_empty());
+
+    assert_eq!(trap("let x = try_call(1);"), "`try_call` expects a call");
+    assert_eq!(
+        trap("let x = try_call();"),
+        "`try_call` takes 1 argument, but 0 were given"
+    );
+    assert_eq!(
+        trap("fn f(x: int) { }\ntry_call(f(1.0));"),
+        "parameter `x` of `f` expects `int`, found `float`"
+    );
+}
+
+#[test]
+fn string_functions_check_their_arguments() {
+    assert_eq!(
+        trap("let x = contains(\"abc\", 1);"),
+        "`contains` expects `(str, str)`, found `(str, int)`"
+    );
+    assert_eq!(
+        trap("let x = substring(\"abc\", 0);"),
+        "`substring` takes 3 arguments, but 2 were given"
+    );
+    assert_eq!(
+        trap("let x = substring(\"abc\", 2, 1);"),
+        "substring 2..1 is out of range for a string of 3 characters"
+    );
+    assert_eq!(
+        trap("let x = len(1.0);"),
+        "`len` expects a string, array or map, found `float`"
+    );
+    assert_eq!(
+        trap("let x = \"a\" - \"b\";"),
+        "cannot apply `-` to `str` and `str`"
+    );
+}
@@ -0,0 +1,84 @@
+//! The evaluation order promised in the crate docs, observed through the
+//! order of host calls. `t(n)` records `n` and returns it.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use shallows_vm::draft::{Interpreter, Value};
+use shallows_vm::{Lines, parse};
+
+/// Runs `source` and returns the arguments `t` was called with, in order.
+fn order(source: &str) -> Vec<i64> {
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let log = calls.clone();
+    let mut interpreter = Interpreter::new().with_function("t", move |args| {
+        let [Value::Int(n)] = args else {
+            return Err("t expects an int".to_string());
+        };
+        log.borrow_mut().push(*n);
+        Ok(vec![Value::Int(*n)])
+    });
+    let parsed = parse(&Lines::from_string(source.to_string()));
+    assert!(!parsed.has_errors(), "{:?}", parsed.diagnostics);
+    if let Err(trap) = interpreter.run(&parsed.program) {
+        panic!("{trap}");
+    }
+    calls.take()
+}
+
+#[test]
+fn call_arguments() {
+    assert_eq!(
+        order("fn f(a: int, b: int, c: int) { }\nf(t(1), t(2), t(3));"),
+        [1, 2, 3]
+    );
+    assert_eq!(
+        order("fn f(a: int, b: int) -> int { return t(3); }\nlet x = f(t(1), t(2));"),
+        [1, 2, 3]
+    );
+    assert_eq!(order("let x = t(t(1) + t(2));"), [1, 2, 3]);
+}
+
+#[test]
+fn binary_operands() {
+    assert_eq!(order("let x = t(1) - t(2) * t(3);"), [1, 2, 3]);
+    assert_eq!(order("let x = (t(1) - t(2)) * t(3);"), [1, 2, 3]);
+    assert_eq!(order("let x = t(1) < t(2) && t(3) > t(4);"), [1, 2, 3, 4]);
+}
+
+#[test]
+fn short_circuits_skip_the_right_operand() {
+    assert_eq!(order("let x = t(1) > 5 && t(2) > 0;"), [1]);
+    assert_eq!(order("let x = t(1) > 0 || t(2) > 0;"), [1]);
+    assert_eq!(order("let x = t(1) ?? t(2);"), [1]);
+    assert_eq!(order("let a: int? = none;\nlet x = a ?? t(1);"), [1]);
+}
+
+#[test]
+fn returned_and_yielded_values() {
+    assert_eq!(order("return t(1), t(2), t(3);"), [1, 2, 3]);
+    assert_eq!(order("let a, b = if true { yield t(1), t(2); };"), [1, 2]);
+    assert_eq!(
+        order("fn f() -> (int, int) { return t(2), t(3); }\nlet a, b = f();\nt(4);"),
+        [2, 3, 4]
+    );
+}
+
+#[test]
+fn literals() {
+    assert_eq!(order("let a = [t(1), t(2), t(3)];"), [1, 2, 3]);
+    assert_eq!(order("let m = { t(1): t(2), t(3): t(4) };"), [1, 2, 3, 4]);
+    assert_eq!(
+        order("struct P { x: int, y: int }\nlet p = P { y: t(1), x: t(2) };"),
+        [1, 2]
+    );
+}
+
+#[test]
+fn indexing_and_assignment() {
+    assert_eq!(order("let a = [1, 2];\nlet x = a[t(1)] + t(2);"), [1, 2]);
+    assert_eq!(
+        order("let a = [[0, 0], [0, 0]];\na[t(1)][t(0)] = t(2);"),
+        [1, 0, 2]
+    );
+}
@@ -0,0 +1,103 @@
+use shallows_vm::{Lexer, Lines, Loc, Span, tokenize};
+
+fn loc(line: usize, col: usize) -> Loc {
+    Loc {
+        line,
+        col,
+        display_col: col,
+    }
+}
+
+#[test]
+fn relex_dirty_matches_full_lex() {
+    let source = "let a = 1;\n\nif a > 2 {\n    yield \"x\";\n}\nlet b = a $ 3;\n";
+    let edits = [
+        (Span::new(loc(0, 8), loc(0, 9)), "10"),
+        (Span::new(loc(1, 0), loc(1, 0)), "let c = 3;\nlet d = 4;"),
+        (Span::new(loc(2, 5), loc(4, 1)), "true"),
+        (Span::new(loc(0, 0), loc(2, 0)), ""),
+        (Span::new(loc(3, 0), loc(3, 0)), "\"open"),
+        (Span::new(loc(0, 0), loc(0, 0)), "x\r\n"),
+    ];
+    let mut lines = Lines::from_string(source.to_string());
+    let mut incremental = tokenize(&lines);
+    for (span, text) in edits {
+        let dirty = lines.apply_edit(span, text).unwrap();
+        Lexer::relex_dirty(&lines, &mut incremental, dirty);
+        let full = tokenize(&lines);
+        assert_eq!(incremental.tokens, full.tokens, "after inserting {text:?}");
+        assert_eq!(incremental.diagnostics, full.diagnostics);
+    }
+}
+
+#[test]
+fn append_at_end_of_file() {
+    let mut lines = Lines::from_string("a\n".to_string());
+    let mut result = tokenize(&lines);
+    let dirty = lines.apply_edit(Span::point(loc(1, 0)), "b").unwrap();
+    Lexer::relex_dirty(&lines, &mut result, dirty);
+    assert_eq!(lines.source(), "a\nb");
+    assert_eq!(result.tokens, tokenize(&lines).tokens);
+}
+
+#[test]
+fn parallel_matches_sequential() {
+    let source = "let a = 1; // c\n\nyield \"s\", a $ 2;\n".repeat(5000);
+    let lines = Lines::from_string(source);
+    let sequential = tokenize(&lines);
+    let parallel = shallows_vm::tokenize_parallel(&lines);
+    assert_eq!(parallel.tokens, sequential.tokens);
+    assert_eq!(parallel.diagnostics, sequential.diagnostics);
+}
+
+#[test]
+fn highlight_classifies_tokens() {
+    use shallows_vm::HighlightKind::*;
+    let lines = Lines::from_string("let x = 1.5 + \"s\"; // done".to_string());
+    let kinds: Vec<_> = shallows_vm::highlight(&lines)
+        .into_iter()
+        .map(|(_, k)| k)
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            Keyword,
+            Ident,
+            Operator,
+            Number,
+            Operator,
+            String,
+            Punctuation,
+            Comment
+        ]
+    );
+}
+
+#[test]
+fn block_ranges_pair_delimiters() {
+    let source = "fn f(a: int) {\n    if a > 0 { g([1]); }\n}\n} (";
+    let lines = Lines::from_string(source.to_string());
+    let pairs: Vec<_> = shallows_vm::block_ranges(&lines)
+        .into_iter()
+        .map(|(open, close)| {
+            (
+                (open.start.line, open.start.col),
+                (close.start.line, close.start.col),
+            )
+        })
+        .collect();
+    assert_eq!(
+        pairs,
+        [
+            ((0, 4), (0, 11)),
+            ((0, 13), (2, 0)),
+            ((1, 13), (1, 23)),
+            ((1, 16), (1, 20)),
+            ((1, 17), (1, 19)),
+        ]
+    );
+
+    // An unclosed `(` does not stop the `}` after it from matching.
+    let lines = Lines::from_string("{ f(1; }".to_string());
+    assert_eq!(shallows_vm::block_ranges(&lines).len(), 1);
+}
@@ -0,0 +1,69 @@
+use shallows_vm::{LineReader, Lines, tokenize};
+
+fn token_positions(source: &str) -> Vec<(usize, usize, usize)> {
+    let lines = Lines::from_string(source.to_string());
+    tokenize(&lines)
+        .tokens
+        .iter()
+        .map(|t| (t.span.start.line, t.span.start.col, t.span.end.col))
+        .collect()
+}
+
+#[test]
+fn crlf_and_cr_split_like_lf() {
+    let lf = "let a = 1;\n\nlet b = \"x\";\n";
+    for other in [lf.replace('\n', "\r\n"), lf.replace('\n', "\r")] {
+        let lines = Lines::from_string(other.clone());
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|l| !l.content.contains('\r')));
+        assert_eq!(token_positions(&other), token_positions(lf));
+    }
+}
+
+#[test]
+fn mixed_terminators() {
+    let lines = Lines::from_string("a\r\nb\rc\n\r\nd".to_string());
+    let contents: Vec<_> = lines.iter().map(|l| l.content).collect();
+    assert_eq!(contents, ["a", "b", "c", "", "d"]);
+    assert_eq!(lines.line_start(4), Some(9));
+}
+
+#[test]
+fn line_reader_normalizes_terminators() {
+    let input = "a\r\nb\rc\n\r\nd";
+    let read: Vec<String> = LineReader::new(input.as_bytes())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(read, ["a", "b", "c", "", "d"]);
+
+    let read: Vec<String> = LineReader::new("x\r\n".as_bytes())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(read, ["x"]);
+}
+
+#[test]
+fn multibyte_columns_count_chars() {
+    let lines = Lines::from_string("let é = \"ü€\"; x".to_string());
+    let tokens = tokenize(&lines).tokens;
+    let ident = &tokens[1];
+    assert_eq!((ident.span.start.col, ident.span.end.col), (4, 5));
+    let x = &tokens[5];
+    assert_eq!((x.span.start.col, x.span.start.display_col), (14, 14));
+    assert_eq!(lines.byte_offset(x.span.start), Some(18));
+    assert_eq!(
+        &lines.source()[lines.byte_offset(x.span.start).unwrap()..],
+        "x"
+    );
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn mmap_matches_read() {
+    let path = std::env::temp_dir().join("shallows_mmap_matches_read.ss");
+    std::fs::write(&path, "let a = 1;\r\n\r\nlet b = 2;\n").unwrap();
+    let mapped = Lines::from_path_mmap(&path).unwrap();
+    let read = Lines::from_path(&path).unwrap();
+    assert!(mapped.iter().eq(read.iter()));
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,277 @@
+use shallows_vm::ast1::*;
+use shallows_vm::{Lines, Parser, parse, tokenize};
+
+/// Fully parenthesized prefix form of an expression.
+fn sexpr(ast: &AstArena, expr: ExprId) -> String {
+    let sexpr = |e| sexpr(ast, e);
+    match &ast[expr] {
+        Expr::Int(lit) => lit.raw.clone(),
+        Expr::Float(lit) => lit.raw.clone(),
+        Expr::Str(lit) => format!("{:?}", lit.value),
+        Expr::Bool(lit) => lit.value.to_string(),
+        Expr::None(_) => "none".to_string(),
+        Expr::Ident(ident) => ident.name.clone(),
+        Expr::Call(call) => {
+            let args: Vec<_> = call.args.iter().map(|&a| sexpr(a)).collect();
+            format!("(call {} {})", call.callee.name, args.join(" "))
+        }
+        Expr::Unary(u) => format!("({} {})", u.op.as_str(), sexpr(u.operand)),
+        Expr::Cast(c) => match &c.ty {
+            TypeRef::Named(ty) => format!("(as {} {})", sexpr(c.expr), ty.name),
+            ty => format!("(as {} {ty:?})", sexpr(c.expr)),
+        },
+        Expr::Binary(b) => format!("({} {} {})", b.op.as_str(), sexpr(b.lhs), sexpr(b.rhs)),
+        Expr::Paren(p) => sexpr(p.inner),
+        Expr::If(e) => format!("(if {})", sexpr(e.cond)),
+        Expr::Range(r) => format!("(.. {} {})", sexpr(r.start), sexpr(r.end)),
+        Expr::Array(a) => {
+            let elems: Vec<_> = a.elems.iter().map(|&e| sexpr(e)).collect();
+            format!("[{}]", elems.join(" "))
+        }
+        Expr::Map(m) => {
+            let entries: Vec<_> = m
+                .entries
+                .iter()
+                .map(|e| format!("{}: {}", sexpr(e.key), sexpr(e.value)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        Expr::Index(i) => format!("(index {} {})", sexpr(i.base), sexpr(i.index)),
+        Expr::Struct(s) => {
+            let fields: Vec<_> = s
+                .fields
+                .iter()
+                .map(|f| format!("{}: {}", f.name.name, sexpr(f.value)))
+                .collect();
+            format!("{} {{{}}}", s.name.name, fields.join(", "))
+        }
+        Expr::Field(f) => format!("(. {} {})", sexpr(f.base), f.field.name),
+        Expr::Variant(v) => format!("{}::{}", v.enum_name.name, v.variant.name),
+        Expr::Error(_) => "<error>".to_string(),
+    }
+}
+
+fn parse_expr(source: &str) -> String {
+    let fragment = shallows_vm::parse_expr(source).unwrap();
+    sexpr(&fragment.arena, fragment.root)
+}
+
+#[test]
+fn precedence() {
+    assert_eq!(
+        parse_expr("a + b * -c(d) < e && f"),
+        "(&& (< (+ a (* b (- (call c d)))) e) f)"
+    );
+    assert_eq!(parse_expr("a - b - c"), "(- (- a b) c)");
+    assert_eq!(parse_expr("a || b && c"), "(&& (|| a b) c)");
+    assert_eq!(parse_expr("(a + b) * c"), "(* (+ a b) c)");
+    assert_eq!(parse_expr("!a == b"), "(== (! a) b)");
+    assert_eq!(parse_expr("f() % 2"), "(% (call f ) 2)");
+    assert_eq!(
+        parse_expr("a ?? b + 1 == c ?? none ?? 0"),
+        "(== (?? a (+ b 1)) (?? (?? c none) 0))"
+    );
+    assert_eq!(
+        parse_expr("-a as uint * b as float as int"),
+        "(* (as (- a) uint) (as (as b float) int))"
+    );
+    assert_eq!(
+        parse_expr("-a[i][j + 1]"),
+        "(- (index (index a i) (+ j 1)))"
+    );
+    assert_eq!(
+        parse_expr("[1, f()[0],][2]"),
+        "(index [1 (index (call f ) 0)] 2)"
+    );
+    assert_eq!(
+        parse_expr("-a.b[i].c * P { x: 1 }.x"),
+        "(* (- (. (index (. a b) i) c)) (. P {x: 1} x))"
+    );
+    assert_eq!(
+        parse_expr("s == State::Walk && !f(E::A)"),
+        "(&& (== s State::Walk) (! (call f E::A)))"
+    );
+}
+
+#[test]
+fn partial_program_on_errors() {
+    let lines = Lines::from_string("let a = * 2;\nlet b = 1;\n}\nmove(b);".to_string());
+    let result = parse(&lines);
+    assert_eq!(result.diagnostics.len(), 2);
+    let ast = &result.program.arena;
+    let stmts: Vec<_> = result.program.stmts.iter().map(|&s| &ast[s]).collect();
+    match stmts[0] {
+        Stmt::Let(s) => assert!(matches!(ast[s.value], Expr::Error(_))),
+        other => panic!("expected let, got {other:?}"),
+    }
+    assert!(matches!(stmts[1], Stmt::Let(_)));
+    assert!(matches!(stmts[2], Stmt::Error(_)));
+    assert!(matches!(stmts[3], Stmt::Expr(_)));
+    // Nothing is left over from the statements that failed.
+    assert_eq!(ast.expr_count(), 4);
+}
+
+#[test]
+fn if_form_follows_position() {
+    let lines = Lines::from_string(
+        "if a { f(); }\nlet x = if a { yield 1; } else { yield 2; };\ng(1 + if b { yield 1; } else { yield 0; });".to_string(),
+    );
+    let program = parse(&lines).into_result().unwrap();
+    let ast = &program.arena;
+    assert!(matches!(ast[program.stmts[0]], Stmt::If(_)));
+    match &ast[program.stmts[1]] {
+        Stmt::Let(s) => assert_eq!(sexpr(ast, s.value), "(if a)"),
+        other => panic!("expected let, got {other:?}"),
+    }
+    match &ast[program.stmts[2]] {
+        Stmt::Expr(s) => assert_eq!(sexpr(ast, s.expr), "(call g (+ 1 (if b)))"),
+        other => panic!("expected call, got {other:?}"),
+    }
+}
+
+#[test]
+fn deep_nesting_is_an_error_not_a_crash() {
+    let depth = 100_000;
+    let source = format!("let x = {}1{};", "(".repeat(depth), ")".repeat(depth));
+    let result = parse(&Lines::from_string(source));
+    let codes: Vec<_> = result.diagnostics.iter().map(|d| d.code).collect();
+    assert_eq!(codes, [Some("E0208")]);
+
+    let source = format!("f({}1);\ng(1);", "-".repeat(depth));
+    let result = parse(&Lines::from_string(source));
+    assert_eq!(result.diagnostics.len(), 1);
+    let ast = &result.program.arena;
+    let stmts = &result.program.stmts;
+    assert!(matches!(ast[stmts[0]], Stmt::Error(_)));
+    assert!(matches!(ast[stmts[1]], Stmt::Expr(_)));
+
+    let source = format!("let x: {}int{};", "[".repeat(depth), "]".repeat(depth));
+    let result = parse(&Lines::from_string(source));
+    let codes: Vec<_> = result.diagnostics.iter().map(|d| d.code).collect();
+    assert_eq!(codes, [Some("E0208")]);
+}
+
+#[test]
+fn long_operator_chains_count_as_nesting() {
+    let source = format!("let x = 1{};", " + 1".repeat(100_000));
+    let result = parse(&Lines::from_string(source));
+    let codes: Vec<_> = result.diagnostics.iter().map(|d| d.code).collect();
+    assert_eq!(codes, [Some("E0208")]);
+
+    let source = format!(
+        "let x = a{};\nlet y: int{} = 1;",
+        ".b".repeat(100_000),
+        " ?".repeat(100_000)
+    );
+    let result = parse(&Lines::from_string(source));
+    let codes: Vec<_> = result.diagnostics.iter().map(|d| d.code).collect();
+    assert_eq!(codes, [Some("E0208"); 2]);
+
+    let source = format!("let x = 1{};", " + 1".repeat(100));
+    assert!(parse(&Lines::from_string(source)).diagnostics.is_empty());
+}
+
+#[test]
+fn max_depth_is_configurable() {
+    let lines = Lines::from_string("let x = ((1));\nif a { if b { if c {} } }".to_string());
+    let mut parser = Parser::new(tokenize(&lines).tokens).with_max_depth(2);
+    parser.parse_program();
+    let messages: Vec<_> = parser
+        .diagnostics()
+        .iter()
+        .map(|d| d.message.as_str())
+        .collect();
+    assert_eq!(messages, ["expression too deeply nested"; 2]);
+
+    let mut parser = Parser::new(tokenize(&lines).tokens).with_max_depth(3);
+    parser.parse_program();
+    assert!(parser.diagnostics().is_empty());
+}
+
+#[test]
+fn fragments() {
+    assert_eq!(parse_expr("f(1, 2,)"), "(call f 1 2)");
+    let stmt = shallows_vm::parse_stmt("let x, y = origin();").unwrap();
+    assert!(matches!(stmt.arena[stmt.root], Stmt::Let(_)));
+
+    let trailing = shallows_vm::parse_expr("a + b;").unwrap_err();
+    assert_eq!(trailing.len(), 1);
+    assert_eq!(trailing[0].message, "expected end of file, found `;`");
+    let errors = shallows_vm::parse_stmt("let x = 1").unwrap_err();
+    assert_eq!(errors[0].code, Some("E0201"));
+    let errors = shallows_vm::parse_expr("\"a\\q\"").unwrap_err();
+    assert_eq!(errors[0].code, Some("E0103"));
+}
+
+#[test]
+fn spans_cover_whole_constructs() {
+    let lines = Lines::from_string(
+        "#[export] let x = f(a, -b) + 1;\nif x > 2 {\n    yield (x);\n} elif y { }".to_string(),
+    );
+    let text = |span: shallows_vm::Span| {
+        let start = lines.byte_offset(span.start).unwrap();
+        let end = lines.byte_offset(span.end).unwrap();
+        lines.source()[start..end].to_string()
+    };
+    let program = parse(&lines).program;
+    let ast = &program.arena;
+    let Stmt::Let(s) = &ast[program.stmts[0]] else {
+        panic!("expected a let");
+    };
+    assert_eq!(text(s.span), "#[export] let x = f(a, -b) + 1;");
+    assert_eq!(text(s.attrs[0].span), "#[export]");
+    assert_eq!(text(ast[s.value].span()), "f(a, -b) + 1");
+    let Expr::Binary(sum) = &ast[s.value] else {
+        panic!("expected a sum");
+    };
+    let Expr::Call(call) = &ast[sum.lhs] else {
+        panic!("expected a call");
+    };
+    assert_eq!(text(ast[call.args[1]].span()), "-b");
+
+    let Stmt::If(s) = &ast[program.stmts[1]] else {
+        panic!("expected an if");
+    };
+    assert_eq!(text(s.span), "if x > 2 {\n    yield (x);\n} elif y { }");
+    assert_eq!(text(s.then_block.span), "{\n    yield (x);\n}");
+    assert_eq!(text(s.elifs[0].span), "elif y { }");
+    assert_eq!(text(ast[s.then_block.stmts[0]].span()), "yield (x);");
+}
+
+#[test]
+fn attributes_belong_to_their_let() {
+    let stmt = shallows_vm::parse_stmt("#[export] #[deprecated(\"old\")] let x = 1;").unwrap();
+    let Stmt::Let(s) = &stmt.arena[stmt.root] else {
+        panic!("expected a let");
+    };
+    let names: Vec<_> = s.attrs.iter().map(|a| a.name.name.as_str()).collect();
+    assert_eq!(names, ["export", "deprecated"]);
+    assert!(s.attrs[0].args.is_empty());
+    assert!(matches!(&stmt.arena[s.attrs[1].args[0]], Expr::Str(lit) if lit.value == "old"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+    let parsed = parse(&Lines::from_string(
+        "let a, b: (int, float) = f(1u, 2.5);\nlet c = if a < 2 { yield \"x\"; } else { yield $; };"
+            .to_string(),
+    ));
+    let json = serde_json::to_string(&parsed.program).unwrap();
+    let back: Program = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, parsed.program);
+}
+
+#[test]
+fn range_bounds_are_whole_expressions() {
+    let result = parse(&Lines::from_string(
+        "for i in lo + 1..hi * 2 { }".to_string(),
+    ));
+    assert!(!result.has_errors(), "{:?}", result.diagnostics);
+    let ast = &result.program.arena;
+    let Stmt::For(s) = &ast[result.program.stmts[0]] else {
+        panic!("expected for");
+    };
+    assert_eq!(s.var.name, "i");
+    assert_eq!(sexpr(ast, s.range), "(.. (+ lo 1) (* hi 2))");
+}
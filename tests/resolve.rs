@@ -0,0 +1,295 @@
+use shallows_vm::ast2::{Expr, Program, Stmt, Type};
+use shallows_vm::resolve::{HostNames, resolve};
+use shallows_vm::scope::SymbolKind;
+use shallows_vm::{Diagnostic, Lines, parse};
+
+fn resolved(source: &str, host: Option<&HostNames>) -> (Program, Vec<Diagnostic>) {
+    let parsed = parse(&Lines::from_string(source.to_string()));
+    assert!(!parsed.has_errors(), "{:?}", parsed.diagnostics);
+    resolve(&parsed.program, host)
+}
+
+/// The names used as values, in expression order, with the kind and the
+/// declaration line of the symbol each refers to.
+fn uses(program: &Program) -> Vec<(String, SymbolKind, Option<usize>)> {
+    program
+        .arena
+        .exprs()
+        .filter_map(|(_, expr)| match expr {
+            Expr::Name(name) => {
+                let symbol = &program.symbols[name.symbol];
+                Some((
+                    symbol.name.clone(),
+                    symbol.kind,
+                    symbol.span.map(|s| s.start.line),
+                ))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn messages(diagnostics: &[Diagnostic]) -> Vec<(&str, &str)> {
+    diagnostics
+        .iter()
+        .map(|d| (d.code.unwrap_or(""), d.message.as_str()))
+        .collect()
+}
+
+fn game() -> HostNames {
+    HostNames::new().with_global("hp").with_function("move")
+}
+
+#[test]
+fn names_resolve_to_their_declarations() {
+    let (program, diagnostics) = resolved(
+        "const LIMIT = 3;\n\
+         fn twice(n: int) -> int { return n * 2; }\n\
+         let mut total = 0;\n\
+         for i in 0..LIMIT { total = total + twice(i); }\n\
+         print(hp);",
+        None,
+    );
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    let local = SymbolKind::Local { mutable: true };
+    assert_eq!(
+        uses(&program),
+        [
+            ("n".to_string(), SymbolKind::Param, Some(1)),
+            ("LIMIT".to_string(), SymbolKind::Const, Some(0)),
+            ("total".to_string(), local, Some(2)),
+            ("i".to_string(), SymbolKind::LoopVar, Some(3)),
+            ("hp".to_string(), SymbolKind::Global, None),
+        ]
+    );
+    let calls: Vec<_> = program
+        .arena
+        .exprs()
+        .filter_map(|(_, expr)| match expr {
+            Expr::Call(call) => Some(program.symbols[call.callee.symbol].kind),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(calls, [SymbolKind::Fn, SymbolKind::Builtin]);
+}
+
+#[test]
+fn shadowing_declares_a_new_symbol() {
+    let (program, diagnostics) = resolved(
+        "fn f(x: int) -> int { let x = x + 1; return x; }\n\
+         let x = 1;\n\
+         if true { let x = 2.0; print(x); }\n\
+         for x in 0..x { print(x); }\n\
+         print(x);",
+        None,
+    );
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    let lines: Vec<_> = uses(&program)
+        .into_iter()
+        .map(|(_, _, line)| line)
+        .collect();
+    assert_eq!(
+        lines,
+        [Some(0), Some(0), Some(2), Some(1), Some(3), Some(1)]
+    );
+}
+
+#[test]
+fn redeclaring_in_the_same_scope_warns() {
+    let (program, diagnostics) = resolved(
+        "let x = 1;\n\
+         let _y = x;\n\
+         let x = \"one\";\n\
+         let a, a = pair();\n\
+         print(x, a);",
+        None,
+    );
+    assert_eq!(
+        messages(&diagnostics),
+        [
+            ("W0401", "`x` is already declared in this scope"),
+            ("W0401", "`a` is already declared in this scope"),
+            ("W0302", "unused variable `a`"),
+        ]
+    );
+    assert_eq!(diagnostics[2].span.start.col, 4);
+    let label = &diagnostics[0].labels[0];
+    assert_eq!(
+        (label.span.start.line, label.message.as_str()),
+        (0, "first declared here")
+    );
+    let lines: Vec<_> = uses(&program)
+        .into_iter()
+        .map(|(_, _, line)| line)
+        .collect();
+    assert_eq!(lines, [Some(0), Some(2), Some(3)]);
+}
+
+#[test]
+fn function_bodies_see_only_their_params() {
+    let host = game();
+    let (program, diagnostics) = resolved(
+        "let x = 1;\n\
+         fn f(a: int = hp) -> int { return a + x; }\n\
+         let _y = f() + x;",
+        Some(&host),
+    );
+    assert_eq!(messages(&diagnostics), [("E0401", "unknown name `x`")]);
+    let kinds: Vec<_> = uses(&program)
+        .into_iter()
+        .map(|(_, kind, _)| kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            SymbolKind::Global,
+            SymbolKind::Param,
+            SymbolKind::Unresolved,
+            SymbolKind::Local { mutable: false },
+        ]
+    );
+}
+
+#[test]
+fn globals_and_constants_are_read_only() {
+    let (_, diagnostics) = resolved(
+        "const LIMIT = 3;\n\
+         hp = hp - 1;\n\
+         LIMIT = 4;\n\
+         let mut hp = hp;\n\
+         hp = hp - 1;",
+        Some(&game()),
+    );
+    assert_eq!(
+        messages(&diagnostics),
+        [
+            ("E0404", "cannot assign to global `hp`"),
+            ("E0404", "cannot assign to constant `LIMIT`"),
+        ]
+    );
+    assert_eq!(
+        diagnostics[0].notes,
+        ["copy it into a local first: `let mut hp = hp;`"]
+    );
+    assert!(diagnostics[0].labels.is_empty());
+    assert_eq!(diagnostics[1].labels[0].span.start.line, 0);
+}
+
+#[test]
+fn functions_may_be_called_before_they_are_declared() {
+    let (_, diagnostics) = resolved(
+        "let _n = later(1);\n\
+         fn later(n: int) -> int { return n; }",
+        Some(&game()),
+    );
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+}
+
+#[test]
+fn unknown_names_with_a_known_host() {
+    let (_, diagnostics) = resolved(
+        "let _p: Point = none;\n\
+         let _q: Pointt? = none;\n\
+         move(hp, mp);\n\
+         jump();\n\
+         let _r = Dir::Up;",
+        Some(&game()),
+    );
+    assert_eq!(
+        messages(&diagnostics),
+        [
+            ("E0403", "unknown type `Point`"),
+            ("E0403", "unknown type `Pointt`"),
+            ("E0401", "unknown name `mp`"),
+            ("E0402", "unknown function `jump`"),
+            ("E0403", "unknown enum `Dir`"),
+        ]
+    );
+}
+
+#[test]
+fn without_a_host_unknown_values_are_assumed_to_be_provided() {
+    let (program, diagnostics) = resolved(
+        "move(hp);\n\
+         let _p: Point = none;",
+        None,
+    );
+    assert_eq!(messages(&diagnostics), [("E0403", "unknown type `Point`")]);
+    let Stmt::Expr(stmt) = &program.arena[program.stmts[0]] else {
+        panic!("expected an expression statement");
+    };
+    let Expr::Call(call) = &program.arena[stmt.expr] else {
+        panic!("expected a call");
+    };
+    assert_eq!(program.symbols[call.callee.symbol].kind, SymbolKind::HostFn);
+    let Stmt::Let(stmt) = &program.arena[program.stmts[1]] else {
+        panic!("expected a let");
+    };
+    assert_eq!(stmt.ty.as_ref().map(|(ty, _)| ty), Some(&Type::Error));
+}
+
+#[test]
+fn try_call_may_name_a_missing_function() {
+    let (program, diagnostics) = resolved("let _r = try_call(missing(hp));", Some(&game()));
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    let missing = program
+        .symbols
+        .iter()
+        .find(|(_, symbol)| symbol.name == "missing")
+        .map(|(_, symbol)| symbol.kind);
+    assert_eq!(missing, Some(SymbolKind::Unresolved));
+}
+
+#[test]
+fn types_tell_structs_from_enums() {
+    let (program, diagnostics) = resolved(
+        "struct P { x: int }\n\
+         enum Dir { Up, Down }\n\
+         fn f(_p: P, _d: Dir, _n: {int: str?}) -> () { }",
+        None,
+    );
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    let Stmt::Fn(f) = &program.arena[program.stmts[2]] else {
+        panic!("expected a function");
+    };
+    let types: Vec<_> = f.params.iter().map(|p| p.ty.clone()).collect();
+    let Stmt::Struct(p) = &program.arena[program.stmts[0]] else {
+        panic!("expected a struct");
+    };
+    let Stmt::Enum(dir) = &program.arena[program.stmts[1]] else {
+        panic!("expected an enum");
+    };
+    assert_eq!(
+        types,
+        [
+            Type::Struct(p.symbol),
+            Type::Enum(dir.symbol),
+            Type::Map(
+                Box::new(Type::Int),
+                Box::new(Type::Optional(Box::new(Type::Str)))
+            ),
+        ]
+    );
+    assert_eq!(f.ret, Some(Type::Unit));
+}
+
+#[test]
+fn nodes_keep_their_ast1_ids() {
+    let source = "fn f(a: int) -> int { if a > 0 { return a; } return 0 - a; }\n\
+                  let mut n = f(3);\n\
+                  while n > 0 { n = n - 1; }";
+    let parsed = parse(&Lines::from_string(source.to_string()));
+    let (program, _) = resolve(&parsed.program, None);
+    assert_eq!(
+        program.arena.expr_count(),
+        parsed.program.arena.expr_count()
+    );
+    assert_eq!(
+        program.arena.stmt_count(),
+        parsed.program.arena.stmt_count()
+    );
+    assert_eq!(program.stmts, parsed.program.stmts);
+    for ((_, before), (_, after)) in parsed.program.arena.exprs().zip(program.arena.exprs()) {
+        assert_eq!(before.span(), after.span());
+    }
+}
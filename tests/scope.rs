@@ -0,0 +1,114 @@
+use shallows_vm::resolve::resolve;
+use shallows_vm::scope::{ScopeKind, ScopeTree, SymbolId, SymbolInfo, SymbolKind, SymbolTable};
+use shallows_vm::{Lines, Loc, Span, parse};
+
+fn loc(line: usize, col: usize) -> Loc {
+    Loc {
+        line,
+        col,
+        display_col: col,
+    }
+}
+
+fn span(from: (usize, usize), to: (usize, usize)) -> Span {
+    Span::new(loc(from.0, from.1), loc(to.0, to.1))
+}
+
+fn symbol(symbols: &mut SymbolTable, name: &str, kind: SymbolKind) -> SymbolId {
+    symbols.alloc(SymbolInfo {
+        name: name.to_string(),
+        kind,
+        span: None,
+    })
+}
+
+#[test]
+fn lookup_finds_the_innermost_binding() {
+    let mut symbols = SymbolTable::new();
+    let mut scopes = ScopeTree::new();
+    let limit = symbol(&mut symbols, "LIMIT", SymbolKind::Const);
+    scopes.declare("LIMIT", limit, Loc::default());
+    scopes.enter(ScopeKind::TopLevel, span((0, 0), (9, 0)));
+    let outer = symbol(&mut symbols, "x", SymbolKind::Local { mutable: false });
+    scopes.declare("x", outer, loc(0, 10));
+    let block = scopes.enter(ScopeKind::Block, span((1, 0), (3, 1)));
+    let inner = symbol(&mut symbols, "x", SymbolKind::Local { mutable: true });
+    scopes.declare("x", inner, loc(2, 14));
+
+    assert_eq!(scopes.current(), block);
+    assert_eq!(scopes.lookup("x"), Some(inner));
+    assert_eq!(scopes.lookup("LIMIT"), Some(limit));
+    assert_eq!(scopes.lookup("y"), None);
+    scopes.exit();
+    assert_eq!(scopes.lookup("x"), Some(outer));
+    assert_eq!(symbols.len(), 3);
+}
+
+#[test]
+fn function_scopes_skip_to_the_global_scope() {
+    let mut symbols = SymbolTable::new();
+    let mut scopes = ScopeTree::new();
+    let limit = symbol(&mut symbols, "LIMIT", SymbolKind::Const);
+    scopes.declare("LIMIT", limit, Loc::default());
+    scopes.enter(ScopeKind::TopLevel, span((0, 0), (9, 0)));
+    let x = symbol(&mut symbols, "x", SymbolKind::Local { mutable: false });
+    scopes.declare("x", x, loc(0, 10));
+    let f = scopes.enter(ScopeKind::Function, span((1, 0), (3, 1)));
+    let n = symbol(&mut symbols, "n", SymbolKind::Param);
+    scopes.declare("n", n, loc(1, 20));
+    scopes.enter(ScopeKind::Block, span((1, 20), (3, 1)));
+
+    assert_eq!(scopes.lookup("n"), Some(n));
+    assert_eq!(scopes.lookup("LIMIT"), Some(limit));
+    assert_eq!(scopes.lookup("x"), None);
+    scopes.exit();
+    scopes.exit();
+    scopes.exit();
+    assert_eq!(scopes.current(), ScopeTree::GLOBAL);
+    assert_eq!(
+        scopes[f].parent,
+        Some(scopes[ScopeTree::GLOBAL].children[0])
+    );
+}
+
+#[test]
+fn names_visible_at_a_position() {
+    let source = "const LIMIT = 3;\n\
+                  let a = 1;\n\
+                  fn f(n: int) -> int {\n\
+                  \x20   let m = n;\n\
+                  \x20   return m;\n\
+                  }\n\
+                  for i in 0..LIMIT {\n\
+                  \x20   let a = i;\n\
+                  \x20   print(a);\n\
+                  }\n\
+                  print(a);";
+    let parsed = parse(&Lines::from_string(source.to_string()));
+    assert!(!parsed.has_errors(), "{:?}", parsed.diagnostics);
+    let (program, diagnostics) = resolve(&parsed.program, None);
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    let visible = |line, col| -> Vec<(&str, Option<usize>)> {
+        program
+            .scopes
+            .visible_at(loc(line, col))
+            .into_iter()
+            .map(|(name, symbol)| (name, program.symbols[symbol].span.map(|s| s.start.line)))
+            .collect()
+    };
+
+    assert_eq!(
+        program.scopes[program.scopes.scope_at(loc(3, 4))].kind,
+        ScopeKind::Block
+    );
+    assert_eq!(visible(3, 4), [("n", Some(2)), ("LIMIT", Some(0))]);
+    assert_eq!(
+        visible(4, 4),
+        [("m", Some(3)), ("n", Some(2)), ("LIMIT", Some(0))]
+    );
+    assert_eq!(
+        visible(8, 4),
+        [("a", Some(7)), ("i", Some(6)), ("LIMIT", Some(0))]
+    );
+    assert_eq!(visible(10, 0), [("a", Some(1)), ("LIMIT", Some(0))]);
+}
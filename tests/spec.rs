@@ -0,0 +1,70 @@
+//! Runs the conformance corpus in `tests/spec` against this crate.
+
+use std::path::{Path, PathBuf};
+
+use shallows_vm::draft::Interpreter;
+use shallows_vm::spec::{Outcome, SPEC_VERSION, SpecTest, Verdict};
+use shallows_vm::{Lines, parse, pass1, resolve, typeck};
+
+fn collect(dir: &Path, out: &mut Vec<PathBuf>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect(&path, out);
+        } else if path.extension().is_some_and(|e| e == "ss") {
+            out.push(path);
+        }
+    }
+}
+
+/// Checks the program, and runs it in draft mode if the test expects output
+/// and nothing was reported.
+fn run(lines: &Lines, test: &SpecTest) -> Outcome {
+    let parsed = parse(lines);
+    let mut diagnostics = parsed.diagnostics;
+    diagnostics.extend(pass1::check(&parsed.program));
+    let (resolved, resolve_diagnostics) = resolve::resolve(&parsed.program, None);
+    diagnostics.extend(resolve_diagnostics);
+    if !diagnostics.iter().any(|d| d.is_error()) {
+        diagnostics.extend(typeck::check(&resolved).1);
+    }
+    let mut output = None;
+    if test.expects_output() && !diagnostics.iter().any(|d| d.is_error()) {
+        let mut interpreter = Interpreter::new();
+        if let Err(trap) = interpreter.run(&parsed.program) {
+            diagnostics.push(trap.to_diagnostic());
+        }
+        output = Some(interpreter.take_output());
+    }
+    Outcome {
+        spec_version: SPEC_VERSION,
+        diagnostics,
+        output,
+    }
+}
+
+#[test]
+fn spec_corpus() {
+    let mut paths = Vec::new();
+    collect(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/spec"),
+        &mut paths,
+    );
+    paths.sort();
+    assert!(!paths.is_empty(), "no spec tests found");
+
+    let mut failed = Vec::new();
+    for path in &paths {
+        let lines = Lines::from_path(path).unwrap();
+        let test = SpecTest::parse(&lines);
+        match test.check(&run(&lines, &test)) {
+            Verdict::Pass | Verdict::Skipped(_) => {}
+            Verdict::Fail(reasons) => failed.push(format!(
+                "{}:\n    {}",
+                path.display(),
+                reasons.join("\n    ")
+            )),
+        }
+    }
+    assert!(failed.is_empty(), "spec failures:\n{}", failed.join("\n"));
+}
@@ -0,0 +1,478 @@
+use shallows_vm::ast2::{Expr, Program, Type};
+use shallows_vm::resolve::resolve;
+use shallows_vm::typeck::{Types, check};
+use shallows_vm::{Diagnostic, Lines, parse};
+
+fn checked(source: &str) -> (Program, Types, Vec<Diagnostic>) {
+    let parsed = parse(&Lines::from_string(source.to_string()));
+    assert!(!parsed.has_errors(), "{:?}", parsed.diagnostics);
+    // Unused locals are fine; the checker only needs names that resolve.
+    let (program, diagnostics) = resolve(&parsed.program, None);
+    assert!(
+        !diagnostics.iter().any(Diagnostic::is_error),
+        "{diagnostics:?}"
+    );
+    let (types, diagnostics) = check(&program);
+    (program, types, diagnostics)
+}
+
+fn messages(source: &str) -> Vec<(String, String)> {
+    let (_, _, diagnostics) = checked(source);
+    diagnostics
+        .iter()
+        .map(|d| (d.code.unwrap_or("").to_string(), d.message.clone()))
+        .collect()
+}
+
+fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+    expected
+        .iter()
+        .map(|&(code, message)| (code.to_string(), message.to_string()))
+        .collect()
+}
+
+#[test]
+fn well_typed_programs_pass() {
+    let (program, types, diagnostics) = checked(
+        "const LIMIT: int = 3;\n\
+         fn scale(x: float, by: float = 2.0) -> float { return x * by; }\n\
+         let n: int = LIMIT * 2 + 1;\n\
+         let f: float = scale(n as float) / 3.0;\n\
+         let s: str = \"hp: \" + \"10\";\n\
+         let ok: bool = n > 2 && !(f == 0.0) || s != \"\";\n\
+         let u: uint = 7u % 2u;\n\
+         for i in 0..n { print(i); }",
+    );
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    let binaries: Vec<_> = program
+        .arena
+        .exprs()
+        .filter(|(_, e)| matches!(e, Expr::Binary(_)))
+        .map(|(id, _)| types[id].clone())
+        .collect();
+    assert_eq!(
+        binaries,
+        [
+            Type::Float,
+            Type::Int,
+            Type::Int,
+            Type::Float,
+            Type::Str,
+            Type::Bool,
+            Type::Bool,
+            Type::Bool,
+            Type::Bool,
+            Type::Bool,
+            Type::UInt,
+        ]
+    );
+}
+
+#[test]
+fn operators_need_matching_operands() {
+    assert_eq!(
+        messages(
+            "let a: int = 1;\n\
+             let b: float = 2.0;\n\
+             let c: uint = 3u;\n\
+             let d = a + b;\n\
+             let e = c * a;\n\
+             let f = \"x\" + a;\n\
+             let g = a < \"y\";\n\
+             let h = -c;\n\
+             let i = !a;\n\
+             let j = true == 1;"
+        ),
+        pairs(&[
+            ("E0502", "cannot apply `+` to `int` and `float`"),
+            ("E0502", "cannot apply `*` to `uint` and `int`"),
+            ("E0502", "cannot apply `+` to `str` and `int`"),
+            ("E0502", "cannot apply `<` to `int` and `str`"),
+            ("E0502", "cannot apply `-` to `uint`"),
+            ("E0502", "cannot apply `!` to `int`"),
+            ("E0502", "cannot apply `==` to `bool` and `int`"),
+        ])
+    );
+}
+
+#[test]
+fn conditions_must_be_bool() {
+    assert_eq!(
+        messages(
+            "let n: int = 3;\n\
+             if n { print(n); } elif \"yes\" { print(0); }\n\
+             while 1.5 { print(n); }\n\
+             let b: bool = n > 0 && n;\n\
+             let v: int = if n > 0 { yield 1; } else { yield 2; };"
+        ),
+        pairs(&[
+            ("E0503", "condition must be `bool`, found `int`"),
+            ("E0503", "condition must be `bool`, found `str`"),
+            ("E0503", "condition must be `bool`, found `float`"),
+            ("E0503", "operands of `&&` must be `bool`, found `int`"),
+        ])
+    );
+}
+
+#[test]
+fn annotations_and_arguments_are_checked() {
+    let (_, _, diagnostics) = checked(
+        "fn heal(amount: float, times: uint = 1u) { }\n\
+         let a: int = 1.5;\n\
+         let b: float = 2;\n\
+         let c: str? = none;\n\
+         let d: bool? = 1;\n\
+         heal(10);\n\
+         heal(1.0, 2);\n\
+         heal(1.0, 2u);",
+    );
+    let found: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.code.unwrap_or(""), d.message.as_str(), d.span.start.line))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("E0501", "expected `int`, found `float` literal", 1),
+            ("E0501", "expected `float`, found `int` literal", 2),
+            ("E0501", "expected `bool?`, found `int` literal", 4),
+            (
+                "E0501",
+                "parameter `amount` of `heal` expects `float`, found `int` literal",
+                5
+            ),
+            (
+                "E0501",
+                "parameter `times` of `heal` expects `uint`, found `int` literal",
+                6
+            ),
+        ]
+    );
+    assert_eq!(diagnostics[3].labels[0].span.start.line, 0);
+}
+
+#[test]
+fn returns_and_calls_without_a_value() {
+    assert_eq!(
+        messages(
+            "fn greet() { print(\"hi\"); }\n\
+             fn half(n: int) -> float { return n / 2; }\n\
+             greet();\n\
+             let g = greet();\n\
+             let h: float = half(4);"
+        ),
+        pairs(&[
+            ("E0501", "`half` returns `float`, but this value is `int`"),
+            ("E0504", "`greet` returns `()`, which is not a value"),
+        ])
+    );
+}
+
+#[test]
+fn assignments_keep_the_type_of_the_target() {
+    assert_eq!(
+        messages(
+            "struct P { x: float }\n\
+             let mut n: int = 1;\n\
+             let mut p: P = P { x: 1 };\n\
+             let mut xs: [int] = [1, 2];\n\
+             n = \"one\";\n\
+             p.x = 2;\n\
+             xs[0] = 1.0;\n\
+             xs[true] = 1;\n\
+             p.y = 0.0;\n\
+             n.x = 1;"
+        ),
+        pairs(&[
+            (
+                "E0501",
+                "field `x` of `P` expects `float`, found `int` literal"
+            ),
+            ("E0501", "cannot assign `str` to `n`, which holds `int`"),
+            (
+                "E0501",
+                "cannot assign `int` literal to field `x` of `p`, which holds `float`"
+            ),
+            (
+                "E0501",
+                "cannot assign `float` literal to an element of `xs`, which holds `int`"
+            ),
+            ("E0503", "array index must be `int` or `uint`, found `bool`"),
+            ("E0506", "struct `P` has no field `y`"),
+            ("E0506", "cannot access field `x` of `int`"),
+        ])
+    );
+}
+
+#[test]
+fn literals_and_branches_agree_on_one_type() {
+    assert_eq!(
+        messages(
+            "let c: bool = true;\n\
+             let xs: [int] = [1, 2.0];\n\
+             let m: {str: int} = {\"a\": 1, 2: 2};\n\
+             let v: int = if c { yield 1; } else { yield \"one\"; };\n\
+             let w: float = len(3) as float;\n\
+             let x: int = c as int;\n\
+             for i in 0..2u { print(i); }"
+        ),
+        pairs(&[
+            (
+                "E0501",
+                "array elements must have one type: expected `int`, found `float`"
+            ),
+            (
+                "E0501",
+                "map keys must have one type: expected `str`, found `int`"
+            ),
+            (
+                "E0501",
+                "the branches of this `if` yield different types: `int` and `str`"
+            ),
+            ("E0507", "`len` expects a string, array or map, found `int`"),
+            ("E0505", "cannot cast `bool` to `int`"),
+            (
+                "E0503",
+                "range bounds must both be `int` or both `uint`, found `int` and `uint`"
+            ),
+        ])
+    );
+}
+
+#[test]
+fn none_makes_array_and_map_literals_optional_in_any_position() {
+    assert_eq!(
+        messages(
+            "let a = [none, 1];\n\
+             let b = [1, none];\n\
+             let c = {\"x\": none, \"y\": 2};\n\
+             let d = {\"x\": 2, \"y\": none};\n\
+             let fine: [[int?]] = [a, b, [none], [none, none]];\n\
+             let also: [{str: int?}] = [c, d];\n\
+             let e: [int] = a;\n\
+             let f: [int] = b;\n\
+             let g: {str: int} = c;\n\
+             let h: {str: int} = d;"
+        ),
+        pairs(&[
+            ("E0501", "expected `[int]`, found `[int?]`"),
+            ("E0501", "expected `[int]`, found `[int?]`"),
+            ("E0501", "expected `{str: int}`, found `{str: int?}`"),
+            ("E0501", "expected `{str: int}`, found `{str: int?}`"),
+        ])
+    );
+}
+
+#[test]
+fn unannotated_locals_take_the_type_of_their_initializer() {
+    assert_eq!(
+        messages(
+            "const STEP = 2;\n\
+             let speed = 10.0;\n\
+             let mut count = 0;\n\
+             let name = \"bat\";\n\
+             let big = 3u;\n\
+             let slow = speed / STEP;\n\
+             count = count + 0.5;\n\
+             let label = name + count;\n\
+             let more = big + STEP;\n\
+             let maybe = none;\n\
+             let xs = [1, 2];\n\
+             let first: float = xs[0];"
+        ),
+        pairs(&[
+            ("E0502", "cannot apply `/` to `float` and `int`"),
+            ("E0502", "cannot apply `+` to `int` and `float`"),
+            ("E0502", "cannot apply `+` to `str` and `int`"),
+            ("E0502", "cannot apply `+` to `uint` and `int`"),
+            ("E0501", "expected `float`, found `int`"),
+        ])
+    );
+}
+
+#[test]
+fn unknown_types_are_not_reported() {
+    let (program, types, diagnostics) = checked(
+        "let n = hp + 1;\n\
+         let mut total = hp;\n\
+         total = total + 0.5;\n\
+         move(n, \"x\");",
+    );
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    let Some((id, _)) = program
+        .arena
+        .exprs()
+        .find(|(_, e)| matches!(e, Expr::Binary(_)))
+    else {
+        panic!("expected a binary expression");
+    };
+    assert_eq!(types[id], Type::Error);
+}
+
+#[test]
+fn tuples_unpack_into_one_name_per_value() {
+    let (program, types, diagnostics) = checked(
+        "fn pair() -> (int, float) { return 1, 2.0; }\n\
+         let a, b = pair();\n\
+         let c: (int, float) = pair();\n\
+         let d, e = c;\n\
+         let f: float = a;\n\
+         let g: (int, float, str) = pair();\n\
+         let h, i, j = c;\n\
+         let k, l = if a > 0 { yield 1; } else { yield 2; };\n\
+         let m, n: (int, float, str) = g;",
+    );
+    assert_eq!(diagnostics[0].message, "expected `float`, found `int`");
+    let found: Vec<_> = diagnostics[1..]
+        .iter()
+        .map(|d| {
+            (
+                d.code.unwrap_or(""),
+                d.message.as_str(),
+                d.span.start.line,
+                d.labels[0].message.as_str(),
+                d.labels[0].span.start.line,
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (
+                "E0508",
+                "expected 3 values, found 2 values",
+                5,
+                "3 values expected here",
+                5
+            ),
+            (
+                "E0508",
+                "cannot destructure 2 values into 3 names",
+                6,
+                "2 values given here",
+                6
+            ),
+            (
+                "E0508",
+                "cannot destructure a single `int` into 2 names",
+                7,
+                "a single `int` given here",
+                7
+            ),
+            (
+                "E0508",
+                "cannot destructure 3 values into 2 names",
+                8,
+                "3 values annotated here",
+                8
+            ),
+        ]
+    );
+    let mut unpacked = program
+        .symbols
+        .iter()
+        .filter(|(_, s)| ["b", "d", "e"].contains(&s.name.as_str()))
+        .map(|(id, _)| types.symbol(id).cloned());
+    assert_eq!(unpacked.next(), Some(Some(Type::Float)));
+    assert_eq!(unpacked.next(), Some(Some(Type::Int)));
+    assert_eq!(unpacked.next(), Some(Some(Type::Float)));
+}
+
+#[test]
+fn literals_keep_the_type_their_suffix_gives() {
+    let (_, _, diagnostics) = checked(
+        "fn wait(ticks: uint, speed: float) { }\n\
+         let a: uint = 10u;\n\
+         let b: float = 10f;\n\
+         let c: int = 10i;\n\
+         let d: float = 10.5f;\n\
+         let e = 10.5u;\n\
+         wait(10, (3));\n\
+         let f = b * 2;",
+    );
+    let found: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.code.unwrap_or(""), d.message.as_str(), d.notes.clone()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (
+                "E0509",
+                "float literal cannot have suffix `u`",
+                vec![
+                    "only whole numbers take `u`; write `10.5` for a `float`, or `10u`".to_string()
+                ]
+            ),
+            (
+                "E0501",
+                "parameter `ticks` of `wait` expects `uint`, found `int` literal",
+                vec!["write `10u` for a `uint` literal".to_string()]
+            ),
+            (
+                "E0501",
+                "parameter `speed` of `wait` expects `float`, found `int` literal",
+                vec!["write `3.0` for a `float` literal".to_string()]
+            ),
+            (
+                "E0502",
+                "cannot apply `*` to `float` and `int`",
+                vec!["write `2.0` for a `float` literal".to_string()]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn int_literals_must_fit_their_type() {
+    let (_, _, diagnostics) = checked(
+        "let a = 9223372036854775807;\n\
+         let b = 9223372036854775808;\n\
+         let c = 18446744073709551615u;\n\
+         let d = 18446744073709551616u;\n\
+         let e = 99999999999999999999;\n\
+         let f = -(3u);\n\
+         let g = 99999999999999999999f;\n\
+         let h = -9223372036854775808;\n\
+         let i = -9223372036854775809;",
+    );
+    let found: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.message.as_str(), d.span.start.line, d.notes.clone()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (
+                "integer literal too large for `int`",
+                1,
+                vec![
+                    "`int` holds up to 9223372036854775807; write `9223372036854775808u` for a \
+                     `uint`"
+                        .to_string()
+                ]
+            ),
+            (
+                "integer literal too large for `uint`",
+                3,
+                vec!["`uint` holds up to 18446744073709551615".to_string()]
+            ),
+            (
+                "integer literal too large for `int`",
+                4,
+                vec!["`int` holds up to 9223372036854775807".to_string()]
+            ),
+            (
+                "a `uint` literal cannot be negative",
+                5,
+                vec!["drop the `u` suffix for an `int`".to_string()]
+            ),
+            (
+                "integer literal too large for `int`",
+                8,
+                vec!["`int` holds down to -9223372036854775808".to_string()]
+            ),
+        ]
+    );
+    assert!(diagnostics.iter().all(|d| d.code == Some("E0510")));
+}
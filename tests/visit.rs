@@ -0,0 +1,84 @@
+use shallows_vm::ast1::*;
+use shallows_vm::visit::{self, Visitor, VisitorMut};
+use shallows_vm::{Lines, parse};
+
+const SOURCE: &str = "\
+let hp, mp: (int, float) = stats(me);
+if hp < limit {
+    flee(-hp);
+} elif ready {
+    mp = mp - 1.0;
+}
+let speed = if boosted { yield base * 2; } else { yield base; };
+";
+
+fn program() -> Program {
+    parse(&Lines::from_string(SOURCE.to_string()))
+        .into_result()
+        .unwrap()
+}
+
+#[derive(Default)]
+struct Names(Vec<String>);
+
+impl Visitor for Names {
+    fn visit_ident(&mut self, ident: &Ident) {
+        self.0.push(ident.name.clone());
+    }
+}
+
+#[test]
+fn visitor_sees_every_name_in_order() {
+    let mut names = Names::default();
+    names.visit_program(&program());
+    assert_eq!(
+        names.0,
+        [
+            "hp", "mp", "int", "float", "stats", "me", "hp", "limit", "flee", "hp", "ready", "mp",
+            "mp", "speed", "boosted", "base", "base"
+        ]
+    );
+}
+
+/// Skips `if` expressions by not walking into them.
+struct CallsOutsideIfExprs(usize);
+
+impl Visitor for CallsOutsideIfExprs {
+    fn visit_expr(&mut self, ast: &AstArena, expr: ExprId) {
+        match &ast[expr] {
+            Expr::If(_) => {}
+            Expr::Call(_) => {
+                self.0 += 1;
+                visit::walk_expr(self, ast, expr);
+            }
+            _ => visit::walk_expr(self, ast, expr),
+        }
+    }
+}
+
+#[test]
+fn overrides_control_descent() {
+    let mut calls = CallsOutsideIfExprs(0);
+    calls.visit_program(&program());
+    assert_eq!(calls.0, 2);
+}
+
+struct Rename<'a>(&'a str, &'a str);
+
+impl VisitorMut for Rename<'_> {
+    fn visit_ident_mut(&mut self, ident: &mut Ident) {
+        if ident.name == self.0 {
+            ident.name = self.1.to_string();
+        }
+    }
+}
+
+#[test]
+fn visitor_mut_rewrites_in_place() {
+    let mut program = program();
+    Rename("hp", "health").visit_program_mut(&mut program);
+    let mut names = Names::default();
+    names.visit_program(&program);
+    assert_eq!(names.0.iter().filter(|n| *n == "health").count(), 3);
+    assert!(!names.0.iter().any(|n| n == "hp"));
+}